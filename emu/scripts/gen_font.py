@@ -65,13 +65,13 @@ def main():
     assert len(font16) == 4096
 
     out = [
-        "/// Built-in CP437 (American English) fonts, IBM VGA typeface.",
-        "///",
-        "/// \"Wide\" format: `font[row * 256 + glyph_code]` gives one byte",
-        "/// (8 pixels, MSB = leftmost) for that glyph at that row.",
-        "///",
-        "/// Generated from firmware/src/vga/term/font.c by scripts/gen_font.py.",
-        "/// SPDX-License-Identifier: BSD-3-Clause",
+        "//! Built-in CP437 (American English) fonts, IBM VGA typeface.",
+        "//!",
+        "//! \"Wide\" format: `font[row * 256 + glyph_code]` gives one byte",
+        "//! (8 pixels, MSB = leftmost) for that glyph at that row.",
+        "//!",
+        "//! Generated from firmware/src/vga/term/font.c by scripts/gen_font.py.",
+        "//! SPDX-License-Identifier: BSD-3-Clause",
         "",
         f"/// 8x8 font: 256 glyphs, 8 rows per glyph, 1 byte per row.",
         format_rust_array("FONT8", font8),
@@ -109,7 +109,7 @@ def main():
         "    }",
         "",
         "    #[test]",
-        "    fn test_font8_A_has_content() {",
+        "    fn test_font8_a_has_content() {",
         "        // 'A' (glyph 0x41) should have non-zero rows",
         "        let mut has_content = false;",
         "        for row in 0..8 {",
@@ -121,7 +121,7 @@ def main():
         "    }",
         "",
         "    #[test]",
-        "    fn test_font16_A_has_content() {",
+        "    fn test_font16_a_has_content() {",
         "        let mut has_content = false;",
         "        for row in 0..16 {",
         "            if FONT16[row * 256 + 0x41] != 0 {",
@@ -1,3 +1,44 @@
+/// RIA registers at $FFE0-$FFF9, by name. The discriminant is the register
+/// index (`BusTransaction::ria_reg`).
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RiaReg {
+    /// $FFE0: UART flow control (bit 7 TX ready, bit 6 RX data).
+    Uart = 0x00,
+    UartTx = 0x01,
+    UartRx = 0x02,
+    Vsync = 0x03,
+    Rw0 = 0x04,
+    Step0 = 0x05,
+    Addr0Lo = 0x06,
+    Addr0Hi = 0x07,
+    Rw1 = 0x08,
+    Step1 = 0x09,
+    Addr1Lo = 0x0A,
+    Addr1Hi = 0x0B,
+    Xstack = 0x0C,
+    ErrnoLo = 0x0D,
+    ErrnoHi = 0x0E,
+    Op = 0x0F,
+    /// $FFF0: write enables the VSYNC IRQ, read acknowledges it.
+    IrqEnable = 0x10,
+    /// $FFF4: A of the fastcall return stub.
+    A = 0x14,
+    /// $FFF6: X of the fastcall return stub.
+    X = 0x16,
+    SregLo = 0x18,
+    SregHi = 0x19,
+}
+
+impl RiaReg {
+    /// Bus address of the register.
+    #[allow(dead_code)]
+    pub fn to_addr(self) -> u16 {
+        0xFFE0 | self as u16
+    }
+}
+
 /// A single 6502 bus transaction.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct BusTransaction {
@@ -18,6 +59,12 @@ impl BusTransaction {
         Self { cycle, addr, data, rw: true }
     }
 
+    /// `write` to a RIA register by name.
+    #[allow(dead_code)]
+    pub fn write_reg(cycle: u64, reg: RiaReg, data: u8) -> Self {
+        Self::write(cycle, reg.to_addr(), data)
+    }
+
     /// Returns true if this transaction targets the RIA register space ($FFE0-$FFFF).
     pub fn hits_ria(&self) -> bool {
         self.addr >= 0xFFE0
@@ -46,4 +93,12 @@ mod tests {
         assert_eq!(BusTransaction::write(0, 0xFFFF, 0).ria_reg(), 0x1F);
         assert_eq!(BusTransaction::write(0, 0xFFE0, 0).ria_reg(), 0x00);
     }
+
+    #[test]
+    fn test_write_reg() {
+        assert_eq!(BusTransaction::write_reg(0, RiaReg::Addr0Lo, 0x34), BusTransaction::write(0, 0xFFE6, 0x34));
+        assert_eq!(RiaReg::Op.to_addr(), 0xFFEF);
+        assert_eq!(RiaReg::IrqEnable.to_addr(), 0xFFF0);
+        assert_eq!(BusTransaction::write_reg(0, RiaReg::SregHi, 0).ria_reg(), RiaReg::SregHi as u8);
+    }
 }
@@ -0,0 +1,72 @@
+use std::fmt::Write;
+use crate::vga::font::{FONT8, FONT16};
+
+/// Render a built-in font as a C header defining `const uint8_t fontN[]`.
+///
+/// The array uses the emulator's "wide" layout: `font[row * 256 + glyph_code]`.
+/// Returns None for heights other than 8 or 16.
+pub fn font_c_header(height: u8) -> Option<String> {
+    let data: &[u8] = match height {
+        8 => &FONT8,
+        16 => &FONT16,
+        _ => return None,
+    };
+
+    let mut out = String::new();
+    let _ = writeln!(out, "// Built-in CP437 8x{height} font exported by rp6502-emu.");
+    let _ = writeln!(out, "// Layout: font{height}[row * 256 + glyph_code], MSB = leftmost pixel.");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "#include <stdint.h>");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "const uint8_t font{height}[{}] = {{", data.len());
+    for chunk in data.chunks(16) {
+        let hex: Vec<String> = chunk.iter().map(|b| format!("0x{b:02X}")).collect();
+        let _ = writeln!(out, "    {},", hex.join(", "));
+    }
+    let _ = writeln!(out, "}};");
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse the hex values between the array braces.
+    fn parse_array(header: &str) -> Vec<u8> {
+        let body = &header[header.find('{').unwrap() + 1..header.rfind('}').unwrap()];
+        body.split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(|t| u8::from_str_radix(t.trim_start_matches("0x"), 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_font8_header() {
+        let header = font_c_header(8).unwrap();
+        assert!(header.contains("const uint8_t font8[2048] = {"));
+        let data = parse_array(&header);
+        assert_eq!(data.len(), 2048);
+        // Full block glyph 0xDB is 0xFF on every row
+        for row in 0..8 {
+            assert_eq!(data[row * 256 + 0xDB], 0xFF, "row {row}");
+        }
+        assert_eq!(data, FONT8);
+    }
+
+    #[test]
+    fn test_font16_header() {
+        let header = font_c_header(16).unwrap();
+        assert!(header.contains("const uint8_t font16[4096] = {"));
+        let data = parse_array(&header);
+        assert_eq!(data.len(), 4096);
+        for row in 0..16 {
+            assert_eq!(data[row * 256 + 0xDB], 0xFF, "row {row}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_height() {
+        assert!(font_c_header(12).is_none());
+    }
+}
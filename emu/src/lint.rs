@@ -0,0 +1,209 @@
+use crossbeam_channel::unbounded;
+use crate::bus::BusTransaction;
+use crate::pix::PixEvent;
+use crate::ria::Ria;
+use crate::vga::mode3::{ColorFormat, Mode3Config};
+
+/// VGA PIX device number.
+const VGA_DEVICE: u8 = 1;
+/// VGA channel 0 CANVAS register.
+const CANVAS_REGISTER: u8 = 0;
+/// VGA channel 0 MODE register.
+const MODE_REGISTER: u8 = 1;
+/// Bit depths Mode 3 supports.
+const MODE3_BPPS: [u32; 5] = [1, 2, 4, 8, 16];
+
+/// A problem found by `lint`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// Frames elapse but no VGA mode is ever programmed, so nothing renders.
+    NoVgaMode { frames: u64 },
+    /// A Mode 3 plane's bitmap data was written in the size another bit depth
+    /// would need, so the attr bpp and the data disagree.
+    BppMismatch { data_ptr: u16, attr_bpp: u32, data_bpp: u32, written: usize, expected: usize },
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Warning::NoVgaMode { frames } => write!(
+                f,
+                "trace waits {} frame(s) but never programs a VGA mode; nothing will render",
+                frames
+            ),
+            Warning::BppMismatch { data_ptr, attr_bpp, data_bpp, written, expected } => write!(
+                f,
+                "Mode 3 bitmap at 0x{:04X} is programmed as {}bpp ({} bytes) but {} bytes were written, the size at {}bpp",
+                data_ptr, attr_bpp, expected, written, data_bpp
+            ),
+        }
+    }
+}
+
+/// Check a trace for problems without rendering it.
+///
+/// The trace is replayed through a `Ria` (stopping at the exit op) so XRAM
+/// portal writes and xreg ops are decoded exactly; the lints then inspect
+/// the resulting PIX events.
+pub fn lint(trace: &[BusTransaction]) -> Vec<Warning> {
+    let events = pix_events(trace);
+    let mut warnings = Vec::new();
+
+    let frames = events.iter().filter(|e| matches!(e, PixEvent::FrameSync)).count() as u64;
+    let programs_mode = events.iter().any(|e| {
+        matches!(e, PixEvent::Reg(r) if r.device == VGA_DEVICE && r.channel == 0 && r.register == MODE_REGISTER)
+    });
+    if frames > 0 && !programs_mode {
+        warnings.push(Warning::NoVgaMode { frames });
+    }
+
+    for w in bpp_mismatches(&events) {
+        if !warnings.contains(&w) {
+            warnings.push(w);
+        }
+    }
+
+    warnings
+}
+
+/// Cross-check each programmed Mode 3 plane's bpp against the bitmap data.
+///
+/// The run of XRAM bytes written contiguously from `xram_data_ptr` (over the
+/// whole trace, since programs often draw after programming the mode) is
+/// compared with the bitmap size. Only a run that exactly matches the size at
+/// another bpp is flagged; partial or adjacent data is too ambiguous to judge.
+fn bpp_mismatches(events: &[PixEvent]) -> Vec<Warning> {
+    let mut xram = vec![0u8; 0x10000];
+    let mut written = vec![false; 0x10000];
+    let mut xregs = [0u16; 8];
+    // (attr, config_ptr) for every Mode 3 MODE write
+    let mut programs = Vec::new();
+
+    for event in events {
+        match event {
+            PixEvent::Xram(w) => {
+                xram[w.addr as usize] = w.data;
+                written[w.addr as usize] = true;
+            }
+            PixEvent::Reg(r) if r.device == VGA_DEVICE && r.channel == 0 => {
+                if let Some(slot) = xregs.get_mut(r.register as usize) {
+                    *slot = r.value;
+                }
+                match r.register {
+                    CANVAS_REGISTER => xregs = [0; 8],
+                    MODE_REGISTER => {
+                        if r.value == 3 {
+                            programs.push((xregs[2], xregs[3]));
+                        }
+                        xregs = [0; 8];
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let xram: Box<[u8; 0x10000]> = xram.into_boxed_slice().try_into().expect("64K");
+    let mut warnings = Vec::new();
+    for (attr, config_ptr) in programs {
+        let Some(format) = ColorFormat::from_attr(attr) else { continue };
+        let cfg = Mode3Config::from_xram(&xram, config_ptr);
+        if cfg.width_px < 1 || cfg.height_px < 1 {
+            continue;
+        }
+        let size_at = |bpp: u32| (cfg.width_px as usize * bpp as usize).div_ceil(8) * cfg.height_px as usize;
+        let attr_bpp = format.bits_per_pixel();
+        let expected = size_at(attr_bpp);
+        let run = written[cfg.xram_data_ptr as usize..].iter().take_while(|&&w| w).count();
+        if run == 0 || run == expected {
+            continue;
+        }
+        let other = MODE3_BPPS.iter().copied().find(|&bpp| bpp != attr_bpp && size_at(bpp) == run);
+        if let Some(data_bpp) = other {
+            let w = Warning::BppMismatch { data_ptr: cfg.xram_data_ptr, attr_bpp, data_bpp, written: run, expected };
+            if !warnings.contains(&w) {
+                warnings.push(w);
+            }
+        }
+    }
+    warnings
+}
+
+/// PIX events the RIA emits while replaying `trace`.
+fn pix_events(trace: &[BusTransaction]) -> Vec<PixEvent> {
+    let (pix_tx, pix_rx) = unbounded();
+    let (_back_tx, back_rx) = unbounded();
+    let mut ria = Ria::new(pix_tx, back_rx);
+    for txn in trace {
+        if !ria.running {
+            break;
+        }
+        ria.process(txn);
+    }
+    drop(ria);
+    pix_rx.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ria_api::{vga_mode3_config_t, TraceBuilder};
+    use crate::test_harness::{generate_test_trace, TestMode};
+
+    #[test]
+    fn test_wait_without_mode_flagged() {
+        let mut tb = TraceBuilder::new();
+        tb.wait_frames(3);
+        tb.op_exit();
+        let warnings = lint(&tb.trace);
+        assert!(matches!(warnings.as_slice(), [Warning::NoVgaMode { frames }] if *frames > 0));
+    }
+
+    #[test]
+    fn test_canvas_only_still_flagged() {
+        let mut tb = TraceBuilder::new();
+        tb.xreg_vga_canvas(1);
+        tb.wait_frames(1);
+        tb.op_exit();
+        assert_eq!(lint(&tb.trace).len(), 1);
+    }
+
+    /// Program an 8bpp 16x4 Mode 3 plane and write `data_len` bytes of pixels.
+    fn mode3_trace(data_len: usize) -> Vec<BusTransaction> {
+        let mut tb = TraceBuilder::new();
+        tb.xram0_struct_set(0, vga_mode3_config_t::WIDTH_PX, &16i16.to_le_bytes());
+        tb.xram0_struct_set(0, vga_mode3_config_t::HEIGHT_PX, &4i16.to_le_bytes());
+        tb.xram0_struct_set(0, vga_mode3_config_t::XRAM_DATA_PTR, &0x0100u16.to_le_bytes());
+        tb.xram0_struct_set(0, vga_mode3_config_t::XRAM_PALETTE_PTR, &0xFFFFu16.to_le_bytes());
+        tb.xreg_vga_canvas(1);
+        tb.xreg_vga_mode(&[3, 3, 0x0000]); // 8bpp
+        tb.xram0_write(0x0100, &vec![0x11; data_len]);
+        tb.wait_frames(1);
+        tb.op_exit();
+        tb.trace
+    }
+
+    #[test]
+    fn test_bpp_data_mismatch_flagged() {
+        // 16x4 at 4bpp is 32 bytes; at 8bpp it would be 64
+        let warnings = lint(&mode3_trace(32));
+        assert_eq!(
+            warnings,
+            vec![Warning::BppMismatch { data_ptr: 0x0100, attr_bpp: 8, data_bpp: 4, written: 32, expected: 64 }]
+        );
+        assert!(lint(&mode3_trace(64)).is_empty());
+    }
+
+    #[test]
+    fn test_partial_bitmap_not_flagged() {
+        assert!(lint(&mode3_trace(40)).is_empty());
+    }
+
+    #[test]
+    fn test_builtin_modes_are_clean() {
+        for mode in TestMode::all() {
+            assert_eq!(lint(&generate_test_trace(*mode)), vec![], "mode {mode}");
+        }
+    }
+}
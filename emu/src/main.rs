@@ -1,16 +1,24 @@
 mod bus;
+mod font_export;
+mod lint;
 mod pix;
+mod replay;
 mod ria;
 mod screenshot;
 mod ria_api;
 mod test_harness;
+mod trace_export;
+#[cfg(test)]
+mod test_util;
 mod vga;
 
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use clap::{Parser, Subcommand};
 use eframe::egui;
+use crate::replay::{Replay, ReplayCommand};
 use crate::vga::Vga;
 
 #[derive(Parser)]
@@ -18,76 +26,729 @@ use crate::vga::Vga;
 struct Cli {
     #[command(subcommand)]
     command: Option<Command>,
+    /// Upload the GUI texture as premultiplied RGBA
+    #[arg(long)]
+    premultiplied_alpha: bool,
+    /// Initial GUI texture sampling (can be changed in the window)
+    #[arg(long, value_enum, default_value_t = TextureFilter::Nearest)]
+    texture_filter: TextureFilter,
+    /// PNG shown beside the emulator output at the same zoom, e.g. a hardware capture
+    #[arg(long, value_name = "PATH")]
+    reference: Option<PathBuf>,
+    /// GUI color outside the canvas (16:9 letterbox), as RRGGBB or RRGGBBAA hex
+    #[arg(long, value_name = "RRGGBBAA", value_parser = parse_rgba, default_value = "00000000")]
+    border: [u8; 4],
+    /// Stretch the canvas over the whole GUI display, ignoring its aspect ratio
+    #[arg(long)]
+    stretch: bool,
+}
+
+/// How egui samples the framebuffer texture when it is drawn scaled.
+/// Independent of the canvas-to-display integer upscale done by the VGA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum TextureFilter {
+    /// Sharp pixels
+    Nearest,
+    /// Smoothed (bilinear)
+    Linear,
+}
+
+impl TextureFilter {
+    fn options(self) -> egui::TextureOptions {
+        match self {
+            TextureFilter::Nearest => egui::TextureOptions::NEAREST,
+            TextureFilter::Linear => egui::TextureOptions::LINEAR,
+        }
+    }
 }
 
 #[derive(Subcommand)]
 enum Command {
-    /// Render a test pattern to a PNG file (headless, no window)
+    /// Render a test pattern or raw bitmap to a PNG file (headless, no window)
     Screenshot {
-        /// Test mode name (e.g. mono320x240, color8bpp320x180)
+        #[command(flatten)]
+        source: TraceSource,
+        /// Output PNG file path
+        #[arg(short, long, required_unless_present = "raw_stdout")]
+        output: Option<PathBuf>,
+        /// Write the 640x480 RGBA frame to stdout as raw bytes (e.g. for ffmpeg) instead of a PNG
+        #[arg(long, conflicts_with_all = ["output", "json"])]
+        raw_stdout: bool,
+        /// Suppress informational output (errors are still reported)
+        #[arg(short, long)]
+        quiet: bool,
+        /// Also write each plane's resolved palette as JSON to this file
         #[arg(long)]
-        mode: test_harness::TestMode,
+        dump_palettes: Option<PathBuf>,
+        /// Stop replay after N bus transactions, even before the exit op
+        #[arg(long, value_name = "N")]
+        max_transactions: Option<usize>,
+        /// Watchdog: stop replay at the first bus cycle past N and render the current frame
+        #[arg(long, value_name = "N")]
+        max_cycles: Option<u64>,
+        /// Capture frame N (0 is the first rendered) instead of the final state; replay stops there
+        #[arg(long, value_name = "N")]
+        frame: Option<u64>,
+        /// Report average per-frame time in each render stage
+        #[arg(long)]
+        profile: bool,
+        /// Report min/avg/max frame render time
+        #[arg(long)]
+        stats: bool,
+        /// Print a JSON summary (mode, output, canvas, planes, opaque pixels) instead of messages
+        #[arg(long)]
+        json: bool,
+        /// Load up to 64K of XRAM from this image; --mode then only programs the canvas and mode
+        #[arg(long, requires = "mode", conflicts_with = "data")]
+        xram: Option<PathBuf>,
+        /// Warn about planes whose XRAM config and data regions overlap
+        #[arg(long)]
+        lint_layout: bool,
+        /// Log every PIX event the VGA receives to this file, for `replay-pix`
+        #[arg(long, value_name = "PATH")]
+        record_pix: Option<PathBuf>,
+        /// Write a 320x240 preview, box-filtered from 640-wide canvases, instead of the 640x480 frame
+        #[arg(long)]
+        preview: bool,
+    },
+    /// Render a PIX event log from `screenshot --record-pix` to a PNG file, bypassing the RIA
+    ReplayPix {
+        /// PIX event log
+        log: PathBuf,
         /// Output PNG file path
         #[arg(short, long)]
         output: PathBuf,
+        /// Suppress informational output (errors are still reported)
+        #[arg(short, long)]
+        quiet: bool,
+    },
+    /// Render every test mode to <OUT_DIR>/<mode>.png (headless, no window)
+    Gallery {
+        /// Directory for the PNG files, created if missing
+        out_dir: PathBuf,
     },
+    /// Check a trace for problems without rendering it
+    Lint {
+        #[command(flatten)]
+        source: TraceSource,
+    },
+    /// Print a trace's bus writes in another format (stdout unless -o is given)
+    DumpTrace {
+        #[command(flatten)]
+        source: TraceSource,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = DumpFormat::Asm)]
+        format: DumpFormat,
+        /// Output file path
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Write a built-in font as a C header (const uint8_t fontN[])
+    ExportFont {
+        /// Font height in pixels
+        #[arg(long, value_parser = parse_font_height)]
+        height: u8,
+        /// Output header file path
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+}
+
+/// Output formats for `dump-trace`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum DumpFormat {
+    /// 6502 assembly: LDA #$xx / STA $xxxx per bus write
+    Asm,
+}
+
+/// Where a headless command gets its bus trace: a built-in test mode or a raw bitmap file.
+#[derive(clap::Args)]
+struct TraceSource {
+    /// Test mode name (e.g. mono320x240, color8bpp320x180)
+    #[arg(long, required_unless_present = "data", conflicts_with = "data")]
+    mode: Option<test_harness::TestMode>,
+    /// Raw packed Mode 3 pixel data to display instead of a test pattern
+    #[arg(long, requires_all = ["width", "height", "bpp"])]
+    data: Option<PathBuf>,
+    /// Bitmap width in pixels (with --data)
+    #[arg(long, requires = "data")]
+    width: Option<u16>,
+    /// Bitmap height in pixels (with --data)
+    #[arg(long, requires = "data")]
+    height: Option<u16>,
+    /// Bits per pixel of the data: 1, 2, 4, 8 or 16 (with --data)
+    #[arg(long, requires = "data", value_parser = parse_bpp)]
+    bpp: Option<u16>,
+}
+
+impl TraceSource {
+    /// Build the trace; fails if the bitmap file is unreadable or does not
+    /// match the given size.
+    fn into_trace(self) -> Result<Vec<bus::BusTransaction>, AppError> {
+        match (self.mode, self.data) {
+            (Some(mode), _) => Ok(test_harness::generate_test_trace(mode)),
+            (None, Some(path)) => {
+                let bytes = std::fs::read(&path).map_err(|source| AppError::Io { path: path.clone(), source })?;
+                let (width, height, bpp) = (
+                    self.width.expect("required by clap"),
+                    self.height.expect("required by clap"),
+                    self.bpp.expect("required by clap"),
+                );
+                test_harness::generate_bitmap_trace(&bytes, width, height, bpp)
+                    .map_err(|message| AppError::Bitmap { path, message })
+            }
+            (None, None) => unreachable!("clap requires --mode or --data"),
+        }
+    }
+}
+
+fn parse_font_height(s: &str) -> Result<u8, String> {
+    match s {
+        "8" => Ok(8),
+        "16" => Ok(16),
+        _ => Err(format!("font height must be 8 or 16, got '{s}'")),
+    }
+}
+
+fn parse_rgba(s: &str) -> Result<[u8; 4], String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    let value = u32::from_str_radix(hex, 16).ok().filter(|_| hex.bytes().all(|b| b.is_ascii_hexdigit()));
+    match (hex.len(), value) {
+        (6, Some(rgb)) => Ok(((rgb << 8) | 0xFF).to_be_bytes()),
+        (8, Some(rgba)) => Ok(rgba.to_be_bytes()),
+        _ => Err(format!("color must be RRGGBB or RRGGBBAA hex, got '{s}'")),
+    }
+}
+
+fn parse_bpp(s: &str) -> Result<u16, String> {
+    match s {
+        "1" => Ok(1),
+        "2" => Ok(2),
+        "4" => Ok(4),
+        "8" => Ok(8),
+        "16" => Ok(16),
+        _ => Err(format!("bpp must be 1, 2, 4, 8 or 16, got '{s}'")),
+    }
 }
 
 fn main() {
     let cli = Cli::parse();
 
     match cli.command {
-        Some(Command::Screenshot { mode, output }) => {
-            run_screenshot(mode, &output);
+        Some(Command::Screenshot { source, output, raw_stdout, quiet, dump_palettes, max_transactions, max_cycles, frame, profile, stats, json, xram, lint_layout, record_pix, preview }) => {
+            let opts = ScreenshotOptions {
+                mode: source.mode,
+                output: match (&output, raw_stdout) {
+                    (_, true) => ScreenshotOutput::RawStdout,
+                    (Some(path), false) => ScreenshotOutput::Png(path),
+                    (None, false) => unreachable!("clap requires --output or --raw-stdout"),
+                },
+                quiet,
+                json,
+                dump_palettes: dump_palettes.as_deref(),
+                max_transactions,
+                max_cycles,
+                frame,
+                profile,
+                stats,
+                xram: xram.as_deref(),
+                lint_layout,
+                record_pix: record_pix.as_deref(),
+                preview,
+            };
+            let mut trace = or_exit(source.into_trace());
+            if xram.is_some() {
+                trace = test_harness::without_xram_writes(&trace);
+            }
+            if let Err(e) = run_screenshot(trace, &opts) {
+                eprintln!("error: {e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Command::ReplayPix { log, output, quiet }) => {
+            if let Err(e) = run_replay_pix(&log, &output, quiet) {
+                eprintln!("error: {e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Gallery { out_dir }) => {
+            if let Err(e) = run_gallery(&out_dir, &mut std::io::stdout()) {
+                eprintln!("error: {e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Lint { source }) => {
+            let warnings = lint::lint(&or_exit(source.into_trace()));
+            for w in &warnings {
+                println!("warning: {w}");
+            }
+            if !warnings.is_empty() {
+                std::process::exit(1);
+            }
+            println!("No problems found");
+        }
+        Some(Command::DumpTrace { source, format, output }) => {
+            let text = match format {
+                DumpFormat::Asm => trace_export::trace_asm(&or_exit(source.into_trace())),
+            };
+            match output {
+                Some(path) => std::fs::write(&path, text).expect("failed to write trace"),
+                // Ignore write errors so piping into e.g. `head` exits quietly
+                None => {
+                    let _ = std::io::Write::write_all(&mut std::io::stdout(), text.as_bytes());
+                }
+            }
+        }
+        Some(Command::ExportFont { height, output }) => {
+            let header = font_export::font_c_header(height)
+                .expect("height validated by clap");
+            std::fs::write(&output, header).expect("failed to write font header");
+            println!("Font saved to {}", output.display());
         }
         None => {
-            run_gui();
+            let scale_mode = if cli.stretch { vga::ScaleMode::StretchFill } else { vga::ScaleMode::IntegerTopLeft };
+            run_gui(cli.premultiplied_alpha, cli.texture_filter, cli.reference.as_deref(), cli.border, scale_mode);
         }
     }
 }
 
-fn run_screenshot(mode: test_harness::TestMode, output: &std::path::Path) {
+/// Write an informational message unless `quiet` is set.
+fn info(out: &mut dyn std::io::Write, quiet: bool, msg: &str) {
+    if !quiet {
+        let _ = writeln!(out, "{msg}");
+    }
+}
+
+/// Unwrap a headless command's result, or print the error and exit 1.
+fn or_exit<T>(result: Result<T, AppError>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    })
+}
+
+/// Print a report line on stdout, or on stderr when stdout carries the
+/// JSON summary or raw frame.
+fn report(stdout_taken: bool, msg: &str) {
+    if stdout_taken {
+        eprintln!("{msg}");
+    } else {
+        println!("{msg}");
+    }
+}
+
+/// Errors that end a headless command with a nonzero exit code.
+#[derive(Debug)]
+enum AppError {
+    /// Encoding or writing the PNG failed.
+    Screenshot(screenshot::ScreenshotError),
+    /// Reading an input file or writing an auxiliary output file failed.
+    Io { path: PathBuf, source: std::io::Error },
+    /// The named emulator thread panicked.
+    ThreadPanicked(&'static str),
+    /// An XRAM image is larger than 64K.
+    XramImageTooLarge { path: PathBuf, len: usize },
+    /// A PIX event log line (1-based) is not an event.
+    PixLog { path: PathBuf, line: usize },
+    /// The trace ended before the requested frame was rendered.
+    FrameNotReached { frame: u64, rendered: u64 },
+    /// A `--data` bitmap does not match its `--width`/`--height`/`--bpp`.
+    Bitmap { path: PathBuf, message: String },
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppError::Screenshot(e) => write!(f, "{}", e),
+            AppError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            AppError::ThreadPanicked(name) => write!(f, "{} thread panicked", name),
+            AppError::XramImageTooLarge { path, len } => {
+                write!(f, "{}: XRAM image is {} bytes, at most 65536 fit", path.display(), len)
+            }
+            AppError::PixLog { path, line } => write!(f, "{}:{}: not a PIX event", path.display(), line),
+            AppError::FrameNotReached { frame, rendered } => {
+                write!(f, "frame {} requested, but the trace rendered only {} frame(s)", frame, rendered)
+            }
+            AppError::Bitmap { path, message } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl From<screenshot::ScreenshotError> for AppError {
+    fn from(e: screenshot::ScreenshotError) -> Self {
+        AppError::Screenshot(e)
+    }
+}
+
+/// Where `run_screenshot` writes the frame.
+#[derive(Debug, Clone, Copy)]
+enum ScreenshotOutput<'a> {
+    Png(&'a std::path::Path),
+    /// Raw 640x480 RGBA bytes on stdout, which then carries nothing else.
+    RawStdout,
+}
+
+/// Options for `run_screenshot`.
+struct ScreenshotOptions<'a> {
+    /// Test mode name for the JSON summary (None for --data).
+    mode: Option<test_harness::TestMode>,
+    output: ScreenshotOutput<'a>,
+    quiet: bool,
+    /// Print a JSON summary instead of the informational lines.
+    json: bool,
+    dump_palettes: Option<&'a std::path::Path>,
+    max_transactions: Option<usize>,
+    max_cycles: Option<u64>,
+    frame: Option<u64>,
+    profile: bool,
+    /// Print min/avg/max frame render time.
+    stats: bool,
+    /// XRAM image loaded before the trace runs.
+    xram: Option<&'a std::path::Path>,
+    /// Print `Vga::validate_layout` warnings for the final planes.
+    lint_layout: bool,
+    /// Log the PIX events sent to the VGA to this file.
+    record_pix: Option<&'a std::path::Path>,
+    /// Write the `PREVIEW_SIZE` preview instead of the full frame.
+    preview: bool,
+}
+
+/// Result of replaying a trace headlessly, before anything is written to disk.
+struct HeadlessFrame {
+    /// 640x480 RGBA display buffer.
+    framebuffer: Vec<u8>,
+    canvas_width: u16,
+    canvas_height: u16,
+    /// Planes programmed when the trace ended.
+    plane_count: usize,
+    palettes: [Option<vga::palette::ResolvedPalette>; 3],
+    profile: Option<vga::RenderProfile>,
+    frame_times: Option<vga::FrameTimes>,
+    /// XRAM layout problems of the planes programmed when the trace ended.
+    layout_warnings: Vec<vga::layout::LayoutWarning>,
+    /// Replay was stopped by the `max_cycles` watchdog.
+    watchdog_fired: bool,
+    /// XRAM writes the VGA received out of sequence.
+    xram_seq_errors: u64,
+    /// MODE writes whose scanline_end was clamped to the canvas height.
+    scanline_clamps: u64,
+    /// `PREVIEW_SIZE` RGBA copy of the final canvas, with `RenderOptions::preview`.
+    preview: Option<Vec<u8>>,
+}
+
+/// Size of `screenshot --preview` output.
+const PREVIEW_SIZE: (usize, usize) = (320, 240);
+
+impl HeadlessFrame {
+    /// Display pixels with nonzero alpha.
+    fn opaque_pixels(&self) -> usize {
+        self.framebuffer.chunks_exact(4).filter(|px| px[3] != 0).count()
+    }
+
+    /// One-line JSON summary for `screenshot --json`.
+    fn summary_json(&self, mode: Option<test_harness::TestMode>, output: &std::path::Path) -> String {
+        let mode = mode.map_or("null".to_string(), |m| json_string(&m.to_string()));
+        format!(
+            "{{\"mode\": {}, \"output\": {}, \"canvas\": {{\"width\": {}, \"height\": {}}}, \"planes\": {}, \"opaque_pixels\": {}}}",
+            mode,
+            json_string(&output.display().to_string()),
+            self.canvas_width,
+            self.canvas_height,
+            self.plane_count,
+            self.opaque_pixels()
+        )
+    }
+}
+
+/// Quote and escape a string for JSON output.
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Settings for `render_headless`; the default replays the whole trace
+/// with no extra measurements.
+#[derive(Debug, Clone, Copy, Default)]
+struct RenderOptions<'a> {
+    capture_palettes: bool,
+    /// Stop replay after this many bus transactions.
+    max_transactions: Option<usize>,
+    /// Watchdog: stop replay at the first bus cycle past this one.
+    max_cycles: Option<u64>,
+    /// Capture this rendered frame (0 is the first) and stop replay there.
+    frame: Option<u64>,
+    profile: bool,
+    stats: bool,
+    /// Log the PIX events sent to the VGA to this file.
+    record_pix: Option<&'a std::path::Path>,
+    /// Also scale the final canvas to `PREVIEW_SIZE`.
+    preview: bool,
+}
+
+/// Replay `trace` through the RIA and VGA threads and return the final frame.
+fn render_headless(
+    trace: Vec<bus::BusTransaction>,
+    xram_image: Option<Vec<u8>>,
+    opts: &RenderOptions,
+) -> Result<HeadlessFrame, AppError> {
     let framebuffer: Arc<Mutex<Vec<u8>>> =
         Arc::new(Mutex::new(vec![0u8; 640 * 480 * 4]));
 
     let (pix_tx, pix_rx) = crossbeam_channel::unbounded();
     let (back_tx, back_rx) = crossbeam_channel::unbounded();
 
-    let fb_vga = framebuffer.clone();
-    thread::spawn(move || {
-        let mut vga = Vga::new(pix_rx, back_tx, fb_vga);
-        vga.run();
-    });
+    let mut vga = Vga::new(pix_rx, back_tx, framebuffer.clone());
+    vga.capture_palettes = opts.capture_palettes;
+    if opts.profile {
+        vga.profile = Some(vga::RenderProfile::default());
+    }
+    if opts.stats {
+        vga.frame_times = Some(vga::FrameTimes::default());
+    }
+    if let Some(path) = opts.record_pix {
+        let file = std::fs::File::create(path).map_err(|source| AppError::Io { path: path.to_path_buf(), source })?;
+        vga.pix_log = Some(Box::new(std::io::BufWriter::new(file)));
+    }
+    vga.stop_after_frame = opts.frame;
+    let (max_transactions, max_cycles) = (opts.max_transactions, opts.max_cycles);
+    // Set once the VGA stops, so the RIA need not replay past a captured frame
+    let vga_done = Arc::new(AtomicBool::new(false));
+    let vga_handle = {
+        let vga_done = vga_done.clone();
+        thread::spawn(move || {
+            vga.run();
+            vga_done.store(true, Ordering::Relaxed);
+            vga
+        })
+    };
+    let shutdown_tx = pix_tx.clone();
 
     // Run RIA on a joinable thread
     let ria_handle = thread::spawn(move || {
-        let mut ria_state = ria::Ria::new(pix_tx, back_rx);
-        let trace = test_harness::generate_test_trace(mode);
-        for txn in &trace {
-            if !ria_state.running {
-                break;
-            }
-            ria_state.process(txn);
+        let config = ria::RiaConfig { max_cycles, ..Default::default() };
+        let mut ria_state = ria::Ria::with_config(pix_tx, back_rx, config);
+        if let Some(image) = xram_image {
+            ria_state.load_xram(&image);
         }
-        // pix_tx is dropped here, which causes VGA thread to exit
+        let mut replay = Replay::new(trace);
+        if let Some(n) = max_transactions {
+            replay = replay.with_max_transactions(n);
+        }
+        while !vga_done.load(Ordering::Relaxed) && replay.step(&mut ria_state) {}
+        ria_state.watchdog_fired
     });
 
-    ria_handle.join().expect("RIA thread panicked");
+    let watchdog_fired = ria_handle.join().map_err(|_| AppError::ThreadPanicked("RIA"))?;
+
+    // Queued behind every RIA event: the VGA drains them, renders the final
+    // state and stops
+    let _ = shutdown_tx.send(pix::PixEvent::Shutdown);
+    let vga = vga_handle.join().map_err(|_| AppError::ThreadPanicked("VGA"))?;
+    if let Some(frame) = opts.frame.filter(|&n| vga.frames_rendered <= n) {
+        return Err(AppError::FrameNotReached { frame, rendered: vga.frames_rendered });
+    }
+    Ok(headless_frame(vga, &framebuffer, watchdog_fired, opts.preview))
+}
+
+/// Feed logged PIX events straight to a VGA, with no RIA, and return the
+/// final frame. A log cut short before its `shutdown` still renders.
+fn render_pix_events(events: Vec<pix::PixEvent>) -> HeadlessFrame {
+    let framebuffer: Arc<Mutex<Vec<u8>>> =
+        Arc::new(Mutex::new(vec![0u8; 640 * 480 * 4]));
+    // The VGA is driven directly; the channels only satisfy its constructor
+    let (_pix_tx, pix_rx) = crossbeam_channel::unbounded();
+    let (back_tx, _back_rx) = crossbeam_channel::unbounded();
+    let mut vga = Vga::new(pix_rx, back_tx, framebuffer.clone());
+    let shut_down = events.last() == Some(&pix::PixEvent::Shutdown);
+    for event in events {
+        vga.handle_event(event);
+    }
+    if !shut_down {
+        vga.handle_event(pix::PixEvent::Shutdown);
+    }
+    headless_frame(vga, &framebuffer, false, false)
+}
+
+/// Collect the final frame and state of a stopped VGA.
+fn headless_frame(vga: Vga, framebuffer: &Mutex<Vec<u8>>, watchdog_fired: bool, preview: bool) -> HeadlessFrame {
+    let info = *vga.frame_info().lock().unwrap_or_else(|e| e.into_inner());
+    let plane_count = vga.active_plane_count();
+    let layout_warnings = vga.validate_layout();
+    let preview = preview.then(|| vga.render_preview(PREVIEW_SIZE.0, PREVIEW_SIZE.1));
+    let (palettes, profile, frame_times) = (vga.palettes, vga.profile, vga.frame_times);
+    let (xram_seq_errors, scanline_clamps) = (vga.xram_seq_errors, vga.scanline_clamps);
+
+    let framebuffer = std::mem::take(&mut *framebuffer.lock().unwrap_or_else(|e| e.into_inner()));
+    HeadlessFrame {
+        framebuffer,
+        canvas_width: info.canvas_width,
+        canvas_height: info.canvas_height,
+        plane_count,
+        palettes,
+        profile,
+        frame_times,
+        layout_warnings,
+        watchdog_fired,
+        xram_seq_errors,
+        scanline_clamps,
+        preview,
+    }
+}
+
+fn run_screenshot(trace: Vec<bus::BusTransaction>, opts: &ScreenshotOptions) -> Result<(), AppError> {
+    let xram_image = match opts.xram {
+        Some(path) => {
+            let image = std::fs::read(path).map_err(|source| AppError::Io { path: path.to_path_buf(), source })?;
+            if image.len() > 0x10000 {
+                return Err(AppError::XramImageTooLarge { path: path.to_path_buf(), len: image.len() });
+            }
+            Some(image)
+        }
+        None => None,
+    };
+    let render_opts = RenderOptions {
+        capture_palettes: opts.dump_palettes.is_some(),
+        max_transactions: opts.max_transactions,
+        max_cycles: opts.max_cycles,
+        frame: opts.frame,
+        profile: opts.profile,
+        stats: opts.stats,
+        record_pix: opts.record_pix,
+        preview: opts.preview,
+    };
+    let frame = render_headless(trace, xram_image, &render_opts)?;
+    // Messages move to stderr when stdout carries the JSON summary or raw frame
+    let stdout_taken = opts.json || matches!(opts.output, ScreenshotOutput::RawStdout);
+    let quiet = opts.quiet || stdout_taken;
+    if frame.watchdog_fired {
+        // The frame is still written: it shows how far the trace got
+        eprintln!("warning: watchdog stopped replay after cycle {} (--max-cycles)", opts.max_cycles.unwrap_or(0));
+    }
+    if frame.xram_seq_errors > 0 {
+        eprintln!("warning: {} XRAM write(s) arrived out of sequence (reordered or dropped)", frame.xram_seq_errors);
+    }
+    if frame.scanline_clamps > 0 {
+        eprintln!("warning: {} MODE write(s) had scanline_end past the canvas height, clamped", frame.scanline_clamps);
+    }
+
+    let (image, width, height) = match &frame.preview {
+        Some(preview) => (preview, PREVIEW_SIZE.0 as u32, PREVIEW_SIZE.1 as u32),
+        None => (&frame.framebuffer, 640, 480),
+    };
+    match opts.output {
+        ScreenshotOutput::Png(path) => {
+            screenshot::save_png(path, image, width, height)?;
+            info(
+                &mut std::io::stdout(),
+                quiet,
+                &format!("Screenshot saved to {}", path.display()),
+            );
+        }
+        ScreenshotOutput::RawStdout => {
+            let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+            screenshot::write_raw_frame(&mut out, image, width, height)?;
+        }
+    }
+
+    if let Some(path) = opts.dump_palettes {
+        std::fs::write(path, vga::palette::palettes_json(&frame.palettes))
+            .map_err(|source| AppError::Io { path: path.to_path_buf(), source })?;
+        info(
+            &mut std::io::stdout(),
+            quiet,
+            &format!("Palettes saved to {}", path.display()),
+        );
+    }
+
+    if let Some(render_profile) = &frame.profile {
+        report(stdout_taken, &render_profile.report());
+    }
 
-    // Small delay to let VGA thread finish processing final events
-    thread::sleep(std::time::Duration::from_millis(100));
+    if let Some(times) = &frame.frame_times {
+        report(stdout_taken, &times.report());
+    }
 
-    let fb = framebuffer.lock().expect("framebuffer lock poisoned");
-    screenshot::save_png(output, &fb, 640, 480)
-        .expect("failed to write PNG");
+    if opts.lint_layout {
+        for w in &frame.layout_warnings {
+            report(stdout_taken, &format!("warning: {w}"));
+        }
+    }
 
-    println!("Screenshot saved to {}", output.display());
+    if let (true, ScreenshotOutput::Png(path)) = (opts.json, opts.output) {
+        println!("{}", frame.summary_json(opts.mode, path));
+    }
+    Ok(())
 }
 
-fn run_gui() {
+/// Render a `--record-pix` log to a PNG.
+fn run_replay_pix(log: &std::path::Path, output: &std::path::Path, quiet: bool) -> Result<(), AppError> {
+    let text = std::fs::read_to_string(log).map_err(|source| AppError::Io { path: log.to_path_buf(), source })?;
+    let events = pix::parse_pix_log(&text).map_err(|line| AppError::PixLog { path: log.to_path_buf(), line })?;
+    let frame = render_pix_events(events);
+    screenshot::save_png(output, &frame.framebuffer, 640, 480)?;
+    info(&mut std::io::stdout(), quiet, &format!("Screenshot saved to {}", output.display()));
+    Ok(())
+}
+
+/// Render each `TestMode` to `<out_dir>/<mode>.png`, printing a line per mode
+/// and the total time to `out`.
+///
+/// Each mode gets its own VGA thread: a VGA keeps its XRAM replica and PIX
+/// sequence state, so reusing one would let a mode's image depend on the
+/// modes rendered before it and differ from `screenshot --mode`.
+fn run_gallery(out_dir: &std::path::Path, out: &mut dyn std::io::Write) -> Result<(), AppError> {
+    std::fs::create_dir_all(out_dir).map_err(|source| AppError::Io { path: out_dir.to_path_buf(), source })?;
+    let start = std::time::Instant::now();
+    for &mode in test_harness::TestMode::all() {
+        let mode_start = std::time::Instant::now();
+        let frame = render_headless(test_harness::generate_test_trace(mode), None, &RenderOptions::default())?;
+        let path = out_dir.join(format!("{mode}.png"));
+        screenshot::save_png(&path, &frame.framebuffer, 640, 480)?;
+        let _ = writeln!(
+            out,
+            "{mode}: {}x{} canvas, {} planes -> {} ({} ms)",
+            frame.canvas_width,
+            frame.canvas_height,
+            frame.plane_count,
+            path.display(),
+            mode_start.elapsed().as_millis()
+        );
+    }
+    let _ = writeln!(
+        out,
+        "Rendered {} modes in {:.2} s",
+        test_harness::TestMode::all().len(),
+        start.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+fn run_gui(
+    premultiplied_alpha: bool,
+    texture_filter: TextureFilter,
+    reference: Option<&std::path::Path>,
+    border: [u8; 4],
+    scale_mode: vga::ScaleMode,
+) {
+    // A load failure is shown in the reference panel rather than ending the run
+    let reference = reference.map(|path| {
+        screenshot::load_png(path)
+            .map(|(w, h, pixels)| egui::ColorImage::from_rgba_unmultiplied([w as usize, h as usize], &pixels))
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    });
+    let width = if reference.is_some() { 1320.0 } else { 660.0 };
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([660.0, 500.0])
+            .with_inner_size([width, 580.0])
             .with_title("RP6502 Emulator"),
         ..Default::default()
     };
@@ -98,21 +759,19 @@ fn run_gui() {
     let (pix_tx, pix_rx) = crossbeam_channel::unbounded();
     let (back_tx, back_rx) = crossbeam_channel::unbounded();
 
-    let fb_vga = framebuffer.clone();
-    thread::spawn(move || {
-        let mut vga = Vga::new(pix_rx, back_tx, fb_vga);
-        vga.run();
-    });
+    let mut vga = Vga::new(pix_rx, back_tx, framebuffer.clone());
+    vga.premultiplied_alpha = premultiplied_alpha;
+    vga.border_rgba = border;
+    vga.scale_mode = scale_mode;
+    let frame_info = vga.frame_info();
+    vga.spawn();
 
+    let (cmd_tx, cmd_rx) = crossbeam_channel::unbounded();
     thread::spawn(move || {
-        let mut ria_state = ria::Ria::new(pix_tx, back_rx);
+        let config = ria::RiaConfig { real_time: true, ..Default::default() };
+        let mut ria_state = ria::Ria::with_config(pix_tx, back_rx, config);
         let trace = test_harness::generate_test_trace(test_harness::TestMode::Mono320x240);
-        for txn in &trace {
-            if !ria_state.running {
-                break;
-            }
-            ria_state.process(txn);
-        }
+        Replay::new(trace).run(&mut ria_state, &cmd_rx);
     });
 
     eframe::run_native(
@@ -121,7 +780,17 @@ fn run_gui() {
         Box::new(move |_cc| {
             Ok(Box::new(EmulatorApp {
                 framebuffer,
+                frame_info,
                 texture: None,
+                premultiplied_alpha,
+                texture_filter,
+                commands: cmd_tx,
+                step_cycles: 1,
+                zoom: 1.0,
+                pan: egui::Vec2::ZERO,
+                reference,
+                reference_texture: None,
+                checkerboard: false,
             }))
         }),
     ).expect("eframe failed");
@@ -129,7 +798,123 @@ fn run_gui() {
 
 struct EmulatorApp {
     framebuffer: Arc<Mutex<Vec<u8>>>,
+    /// Canvas size behind the framebuffer, for mapping display to canvas pixels.
+    frame_info: Arc<Mutex<vga::FrameInfo>>,
     texture: Option<egui::TextureHandle>,
+    /// Framebuffer bytes are already premultiplied by the VGA thread.
+    premultiplied_alpha: bool,
+    /// Sampling used when uploading the framebuffer texture.
+    texture_filter: TextureFilter,
+    /// Replay control for the RIA thread.
+    commands: crossbeam_channel::Sender<ReplayCommand>,
+    /// Number of bus transactions to advance per Step click.
+    step_cycles: u64,
+    /// Integer zoom factor of the image viewer (1x–8x).
+    zoom: f32,
+    /// Offset of the zoomed image's top-left corner from the viewport's.
+    pan: egui::Vec2,
+    /// Reference image from `--reference`, or why it could not be loaded.
+    reference: Option<Result<egui::ColorImage, String>>,
+    /// Texture of `reference` and the filter it was uploaded with.
+    reference_texture: Option<(egui::TextureHandle, TextureFilter)>,
+    /// Show transparent framebuffer pixels over a grey checkerboard.
+    checkerboard: bool,
+}
+
+/// Size of the image viewer, equal to the display framebuffer.
+const VIEWPORT: egui::Vec2 = egui::vec2(640.0, 480.0);
+const MAX_ZOOM: f32 = 8.0;
+
+/// Next integer zoom step for a scroll delta (positive = zoom in).
+fn zoom_step(zoom: f32, scroll: f32) -> f32 {
+    let step = if scroll > 0.0 {
+        1.0
+    } else if scroll < 0.0 {
+        -1.0
+    } else {
+        0.0
+    };
+    (zoom + step).clamp(1.0, MAX_ZOOM)
+}
+
+/// Clamp `pan` so the zoomed image always covers the whole viewport.
+fn clamp_pan(pan: egui::Vec2, zoom: f32) -> egui::Vec2 {
+    let min = VIEWPORT - VIEWPORT * zoom;
+    egui::vec2(pan.x.clamp(min.x, 0.0), pan.y.clamp(min.y, 0.0))
+}
+
+/// Side of a checkerboard square, in display pixels.
+const CHECKER_SIZE: usize = 8;
+
+/// Composite a 640x480 RGBA display buffer over a light/dark grey
+/// checkerboard, so transparent pixels stand out. The result is opaque;
+/// opaque pixels cover the checkerboard completely.
+fn composite_checkerboard(pixels: &[u8], premultiplied: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(pixels.len());
+    for (i, px) in pixels.chunks_exact(4).enumerate() {
+        let (x, y) = (i % 640, i / 640);
+        let bg: u32 = if (x / CHECKER_SIZE + y / CHECKER_SIZE).is_multiple_of(2) { 0xCC } else { 0x99 };
+        let a = px[3] as u32;
+        for &c in &px[..3] {
+            // Premultiplied color already carries its alpha
+            let fg = if premultiplied { c as u32 * 255 } else { c as u32 * a };
+            out.push(((fg + bg * (255 - a) + 127) / 255) as u8);
+        }
+        out.push(0xFF);
+    }
+    out
+}
+
+/// Readout text for one framebuffer pixel.
+fn rgba_label(px: &[u8]) -> String {
+    if px[3] == 0 {
+        "transparent".to_string()
+    } else {
+        format!("RGBA #{:02X}{:02X}{:02X}{:02X}", px[0], px[1], px[2], px[3])
+    }
+}
+
+/// Framebuffer pixel under a viewport-relative position, if any.
+fn pixel_under(pos: egui::Vec2, pan: egui::Vec2, zoom: f32) -> Option<(u32, u32)> {
+    let p = (pos - pan) / zoom;
+    if p.x < 0.0 || p.y < 0.0 || p.x >= VIEWPORT.x || p.y >= VIEWPORT.y {
+        return None;
+    }
+    Some((p.x as u32, p.y as u32))
+}
+
+impl EmulatorApp {
+    /// Draw the reference image with the emulator view's zoom and pan, so
+    /// display pixels line up, or an error placeholder if it failed to load.
+    fn paint_reference(&mut self, ui: &egui::Ui, ctx: &egui::Context, viewport: egui::Rect) {
+        let painter = ui.painter_at(viewport);
+        match &self.reference {
+            Some(Ok(image)) => {
+                let size = egui::vec2(image.size[0] as f32, image.size[1] as f32);
+                // Upload once, and again when the filter changes
+                let filter = self.texture_filter;
+                if self.reference_texture.as_ref().is_none_or(|(_, f)| *f != filter) {
+                    let tex = ctx.load_texture("reference", image.clone(), filter.options());
+                    self.reference_texture = Some((tex, filter));
+                }
+                let (tex, _) = self.reference_texture.as_ref().expect("uploaded above");
+                let rect = egui::Rect::from_min_size(viewport.min + self.pan, size * self.zoom);
+                let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+                painter.image(tex.id(), rect, uv, egui::Color32::WHITE);
+            }
+            Some(Err(msg)) => {
+                painter.rect_filled(viewport, 0.0, egui::Color32::from_gray(32));
+                painter.text(
+                    viewport.center(),
+                    egui::Align2::CENTER_CENTER,
+                    format!("Reference not loaded\n{msg}"),
+                    egui::FontId::proportional(14.0),
+                    egui::Color32::LIGHT_RED,
+                );
+            }
+            None => {}
+        }
+    }
 }
 
 impl eframe::App for EmulatorApp {
@@ -137,36 +922,345 @@ impl eframe::App for EmulatorApp {
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("RP6502 Emulator");
 
+            ui.horizontal(|ui| {
+                if ui.button("Run").clicked() {
+                    let _ = self.commands.send(ReplayCommand::Run);
+                }
+                if ui.button("Pause").clicked() {
+                    let _ = self.commands.send(ReplayCommand::Pause);
+                }
+                if ui.button("Step").clicked() {
+                    let _ = self.commands.send(ReplayCommand::StepCycles(self.step_cycles));
+                }
+                ui.add(egui::DragValue::new(&mut self.step_cycles).range(1..=1_000_000));
+                ui.label("cycles");
+                if ui.button("Present").clicked() {
+                    let _ = self.commands.send(ReplayCommand::PresentFrame);
+                }
+                ui.separator();
+                ui.radio_value(&mut self.texture_filter, TextureFilter::Nearest, "Nearest");
+                ui.radio_value(&mut self.texture_filter, TextureFilter::Linear, "Linear");
+                ui.separator();
+                ui.checkbox(&mut self.checkerboard, "Checkerboard");
+            });
+
             let pixels = if let Ok(fb) = self.framebuffer.lock() {
                 fb.clone()
             } else {
                 vec![0u8; 640 * 480 * 4]
             };
 
-            let image = egui::ColorImage::from_rgba_unmultiplied(
-                [640, 480],
-                &pixels,
-            );
+            // Composite a copy: the pixel readout below shows the real alpha
+            let composited;
+            let upload = if self.checkerboard {
+                composited = composite_checkerboard(&pixels, self.premultiplied_alpha);
+                &composited
+            } else {
+                &pixels
+            };
+            let image = if self.premultiplied_alpha {
+                egui::ColorImage::from_rgba_premultiplied([640, 480], upload)
+            } else {
+                egui::ColorImage::from_rgba_unmultiplied([640, 480], upload)
+            };
 
+            let options = self.texture_filter.options();
             match &mut self.texture {
-                Some(tex) => tex.set(image, egui::TextureOptions::NEAREST),
+                Some(tex) => tex.set(image, options),
                 None => {
-                    self.texture = Some(ctx.load_texture(
-                        "screen",
-                        image,
-                        egui::TextureOptions::NEAREST,
-                    ));
+                    self.texture = Some(ctx.load_texture("screen", image, options));
                 }
             }
 
+            let (viewport, response, reference_viewport) = ui
+                .horizontal(|ui| {
+                    let (viewport, response) = ui.allocate_exact_size(VIEWPORT, egui::Sense::drag());
+                    let reference = self.reference.is_some().then(|| ui.allocate_exact_size(VIEWPORT, egui::Sense::hover()).0);
+                    (viewport, response, reference)
+                })
+                .inner;
+
+            // Scroll-wheel zoom, keeping the point under the cursor fixed
+            if let Some(cursor) = response.hover_pos() {
+                let scroll = ui.input(|i| i.raw_scroll_delta.y);
+                let zoom = zoom_step(self.zoom, scroll);
+                if zoom != self.zoom {
+                    let rel = cursor - viewport.min;
+                    self.pan = rel - (rel - self.pan) * (zoom / self.zoom);
+                    self.zoom = zoom;
+                }
+            }
+            self.pan = clamp_pan(self.pan + response.drag_delta(), self.zoom);
+
             if let Some(tex) = &self.texture {
-                ui.add(
-                    egui::Image::from_texture(tex)
-                        .fit_to_exact_size(egui::vec2(640.0, 480.0)),
-                );
+                let rect = egui::Rect::from_min_size(viewport.min + self.pan, VIEWPORT * self.zoom);
+                let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+                ui.painter_at(viewport).image(tex.id(), rect, uv, egui::Color32::WHITE);
+            }
+
+            if let Some(reference_viewport) = reference_viewport {
+                self.paint_reference(ui, ctx, reference_viewport);
             }
+
+            let hovered = response
+                .hover_pos()
+                .and_then(|pos| pixel_under(pos - viewport.min, self.pan, self.zoom));
+            let mut status = format!("Zoom {}x", self.zoom);
+            if let Some((x, y)) = hovered {
+                status.push_str(&format!("  display ({x}, {y})"));
+                let info = self.frame_info.lock().map(|i| *i).ok();
+                if let Some((cx, cy)) = info.and_then(|i| i.canvas_coord(x, y)) {
+                    status.push_str(&format!("  canvas ({cx}, {cy})"));
+                }
+                let idx = (y as usize * 640 + x as usize) * 4;
+                status.push_str("  ");
+                status.push_str(&rgba_label(&pixels[idx..idx + 4]));
+            }
+            ui.label(status);
         });
 
         ctx.request_repaint();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_info_prints_when_not_quiet() {
+        let mut out = Vec::new();
+        info(&mut out, false, "Screenshot saved to out.png");
+        assert_eq!(out, b"Screenshot saved to out.png\n");
+    }
+
+    #[test]
+    fn test_zoom_step_clamped() {
+        assert_eq!(zoom_step(1.0, 10.0), 2.0);
+        assert_eq!(zoom_step(1.0, -10.0), 1.0);
+        assert_eq!(zoom_step(8.0, 10.0), 8.0);
+        assert_eq!(zoom_step(3.0, 0.0), 3.0);
+    }
+
+    #[test]
+    fn test_clamp_pan_keeps_viewport_covered() {
+        assert_eq!(clamp_pan(egui::vec2(50.0, -50.0), 1.0), egui::Vec2::ZERO);
+        assert_eq!(clamp_pan(egui::vec2(10.0, -5000.0), 2.0), egui::vec2(0.0, -480.0));
+    }
+
+    #[test]
+    fn test_pixel_under_zoomed() {
+        let pan = egui::vec2(-640.0, -480.0);
+        assert_eq!(pixel_under(egui::vec2(0.0, 0.0), pan, 4.0), Some((160, 120)));
+        assert_eq!(pixel_under(egui::vec2(7.0, 3.0), pan, 4.0), Some((161, 120)));
+        assert_eq!(pixel_under(egui::vec2(-1.0, 0.0), egui::Vec2::ZERO, 1.0), None);
+    }
+
+    #[test]
+    fn test_texture_filter_options() {
+        assert_eq!(TextureFilter::Nearest.options(), egui::TextureOptions::NEAREST);
+        assert_eq!(TextureFilter::Linear.options(), egui::TextureOptions::LINEAR);
+    }
+
+    #[test]
+    fn test_composite_checkerboard() {
+        let mut pixels = vec![0u8; 640 * 480 * 4];
+        pixels[..4].copy_from_slice(&[0x12, 0x34, 0x56, 0xFF]); // opaque at (0, 0)
+        let out = composite_checkerboard(&pixels, false);
+        assert_eq!(out[..4], [0x12, 0x34, 0x56, 0xFF], "opaque covers the checkerboard");
+        assert_eq!(out[4..8], [0xCC, 0xCC, 0xCC, 0xFF]);
+        let dark = CHECKER_SIZE * 4;
+        assert_eq!(out[dark..dark + 4], [0x99, 0x99, 0x99, 0xFF]);
+        assert!(out.chunks_exact(4).all(|px| px[3] == 0xFF));
+
+        // Transparent pixels with stale color still show only the checkerboard
+        pixels[4..8].copy_from_slice(&[0xFF, 0x00, 0x00, 0x00]);
+        assert_eq!(composite_checkerboard(&pixels, false)[4..8], [0xCC, 0xCC, 0xCC, 0xFF]);
+        assert_eq!(composite_checkerboard(&pixels, true)[..4], [0x12, 0x34, 0x56, 0xFF]);
+    }
+
+    #[test]
+    fn test_rgba_label() {
+        assert_eq!(rgba_label(&[0xC6, 0xC6, 0xC6, 0xFF]), "RGBA #C6C6C6FF");
+        assert_eq!(rgba_label(&[0x12, 0x34, 0x56, 0x00]), "transparent");
+    }
+
+    #[test]
+    fn test_render_headless_summary() {
+        let trace = test_harness::generate_test_trace(test_harness::TestMode::Mono320x240);
+        let frame = render_headless(trace, None, &RenderOptions::default()).unwrap();
+        assert_eq!((frame.canvas_width, frame.canvas_height), (320, 240));
+        assert_eq!(frame.plane_count, 1);
+        let opaque = frame.opaque_pixels();
+        assert!(opaque > 0 && opaque < 640 * 480, "1bpp pattern has transparent background");
+        assert_eq!(
+            frame.summary_json(Some(test_harness::TestMode::Mono320x240), std::path::Path::new("out.png")),
+            format!(
+                "{{\"mode\": \"mono320x240\", \"output\": \"out.png\", \"canvas\": {{\"width\": 320, \"height\": 240}}, \"planes\": 1, \"opaque_pixels\": {opaque}}}"
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_headless_with_xram_image() {
+        // Mono320x240's setup with its pixel writes removed: a blank image renders
+        // nothing, while one with the 1bpp config and data shows pixels
+        let mode = test_harness::TestMode::Mono320x240;
+        let setup = test_harness::without_xram_writes(&test_harness::generate_test_trace(mode));
+        let blank = render_headless(setup.clone(), None, &RenderOptions::default()).unwrap();
+        assert_eq!(blank.opaque_pixels(), 0);
+
+        let mut image = vec![0u8; 0x0100 + 40];
+        image[6..8].copy_from_slice(&320i16.to_le_bytes()); // width_px
+        image[8..10].copy_from_slice(&1i16.to_le_bytes()); // height_px
+        image[10..12].copy_from_slice(&0x0100u16.to_le_bytes()); // xram_data_ptr
+        image[0x0100..].fill(0xFF);
+        let frame = render_headless(setup, Some(image), &RenderOptions::default()).unwrap();
+        // One canvas row, 2x2 doubled to 640x2 display pixels
+        assert_eq!(frame.opaque_pixels(), 640 * 2);
+    }
+
+    #[test]
+    fn test_render_headless_layout_warnings() {
+        let clean = test_harness::generate_test_trace(test_harness::TestMode::MultiPlane);
+        assert!(render_headless(clean, None, &RenderOptions::default()).unwrap().layout_warnings.is_empty());
+
+        // Mono320x240 puts its 9600-byte bitmap at 0x0100; a Mode 1 plane whose
+        // config sits inside it is reported
+        let mut tb = ria_api::TraceBuilder::new();
+        tb.trace = test_harness::generate_test_trace(test_harness::TestMode::Mono320x240);
+        tb.trace.pop(); // exit
+        tb.cycle = tb.trace.last().unwrap().cycle + 1;
+        tb.xreg_vga_mode(&[1, 0, 0x0200, 1, 0, 0]);
+        tb.wait_frames(1);
+        tb.op_exit();
+        let frame = render_headless(tb.trace, None, &RenderOptions::default()).unwrap();
+        assert!(
+            frame.layout_warnings.iter().any(|w| w.to_string().starts_with("plane 0 data (0x0100-0x267F) overlaps plane 1 config")),
+            "{:?}", frame.layout_warnings
+        );
+    }
+
+    #[test]
+    fn test_max_cycles_watchdog_renders_current_frame() {
+        let mut trace = test_harness::generate_test_trace(test_harness::TestMode::Mono320x240);
+        trace.pop(); // exit
+        let end = trace.last().unwrap().cycle;
+        // An endless tail of bus reads outside the RIA
+        trace.extend((1..100_000).map(|i| bus::BusTransaction::read(end + i, 0x0200, 0)));
+
+        let frame = render_headless(trace, None, &RenderOptions { max_cycles: Some(end), ..Default::default() }).unwrap();
+        assert!(frame.watchdog_fired);
+        assert_eq!((frame.canvas_width, frame.plane_count), (320, 1));
+        assert!(frame.opaque_pixels() > 0);
+    }
+
+    #[test]
+    fn test_record_pix_replays_identical_frame() {
+        let path = std::env::temp_dir().join("rp6502_test_record.pix");
+        let trace = test_harness::generate_test_trace(test_harness::TestMode::MultiPlane);
+        let opts = RenderOptions { record_pix: Some(&path), ..Default::default() };
+        let recorded = render_headless(trace, None, &opts).unwrap();
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert!(log.ends_with("shutdown\n"));
+        let replayed = render_pix_events(pix::parse_pix_log(&log).unwrap());
+        assert!(recorded.opaque_pixels() > 0);
+        assert!(recorded.framebuffer == replayed.framebuffer, "replayed frame differs");
+        assert_eq!(
+            (replayed.canvas_width, replayed.canvas_height, replayed.plane_count),
+            (recorded.canvas_width, recorded.canvas_height, recorded.plane_count)
+        );
+    }
+
+    #[test]
+    fn test_frame_captures_nth_frame() {
+        // scroll_demo renders one frame per scroll step
+        let render = |frame| {
+            let trace = test_harness::generate_test_trace(test_harness::TestMode::ScrollDemo);
+            render_headless(trace, None, &RenderOptions { frame: Some(frame), ..Default::default() })
+        };
+        let frame0 = render(0).unwrap();
+        let frame2 = render(2).unwrap();
+        assert!(frame0.opaque_pixels() > 0);
+        assert!(frame0.framebuffer != frame2.framebuffer, "frame 2 should be scrolled");
+        assert!(render(2).unwrap().framebuffer == frame2.framebuffer, "capture is deterministic");
+        assert!(matches!(render(100_000), Err(AppError::FrameNotReached { frame: 100_000, .. })));
+    }
+
+    #[test]
+    fn test_parse_rgba() {
+        assert_eq!(parse_rgba("0000FF"), Ok([0x00, 0x00, 0xFF, 0xFF]));
+        assert_eq!(parse_rgba("#11223380"), Ok([0x11, 0x22, 0x33, 0x80]));
+        assert!(parse_rgba("12345").is_err());
+        assert!(parse_rgba("+1234567").is_err());
+    }
+
+    #[test]
+    fn test_json_string_escapes() {
+        assert_eq!(json_string(r#"a"b\c"#), r#""a\"b\\c""#);
+        assert_eq!(json_string("\n"), r#""\u000a""#);
+    }
+
+    #[test]
+    fn test_missing_data_file_is_reported() {
+        let source = TraceSource {
+            mode: None,
+            data: Some(PathBuf::from("/nonexistent-dir/pixels.bin")),
+            width: Some(8),
+            height: Some(8),
+            bpp: Some(8),
+        };
+        assert!(matches!(source.into_trace(), Err(AppError::Io { .. })));
+    }
+
+    #[test]
+    fn test_screenshot_write_error_is_reported() {
+        let trace = test_harness::generate_test_trace(test_harness::TestMode::Mono320x240);
+        let opts = ScreenshotOptions {
+            mode: None,
+            output: ScreenshotOutput::Png(std::path::Path::new("/nonexistent-dir/out.png")),
+            quiet: true,
+            json: false,
+            dump_palettes: None,
+            max_transactions: None,
+            max_cycles: None,
+            frame: None,
+            profile: false,
+            stats: false,
+            xram: None,
+            lint_layout: false,
+            record_pix: None,
+            preview: false,
+        };
+        assert!(matches!(run_screenshot(trace, &opts), Err(AppError::Screenshot(_))));
+    }
+
+    #[test]
+    fn test_gallery_writes_one_png_per_mode() {
+        let dir = std::env::temp_dir().join("rp6502_test_gallery");
+        std::fs::remove_dir_all(&dir).ok();
+        let mut out = Vec::new();
+        run_gallery(&dir, &mut out).unwrap();
+
+        let modes = test_harness::TestMode::all();
+        for mode in modes {
+            let bytes = std::fs::read(dir.join(format!("{mode}.png"))).unwrap();
+            assert_eq!(&bytes[..4], &[0x89, 0x50, 0x4E, 0x47], "{mode}.png is a PNG");
+        }
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), modes.len());
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), modes.len() + 1);
+        assert!(out.contains("mono320x240: 320x240 canvas, 1 planes"), "{out}");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_info_suppressed_when_quiet() {
+        let mut out = Vec::new();
+        info(&mut out, true, "Screenshot saved to out.png");
+        assert!(out.is_empty());
+    }
+}
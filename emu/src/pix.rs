@@ -3,22 +3,129 @@
 pub struct XramWrite {
     pub addr: u16,
     pub data: u8,
+    /// Per-RIA sequence number, increasing by one per write across both portals.
+    /// Not part of the PIX wire format.
+    pub seq: u64,
 }
 
 /// Register write to a PIX device.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PixRegWrite {
+    pub device: u8,
     pub channel: u8,
     pub register: u8,
     pub value: u16,
 }
 
 /// Events sent from RIA to VGA over the PIX channel.
+///
+/// Ordering: the RIA sends every event (portal 0 and portal 1 XRAM writes,
+/// register writes and FrameSync) through one channel from one thread, in the
+/// order the bus transactions are processed, so the VGA receives them in
+/// exactly that order. `XramWrite::seq` makes this checkable. A Shutdown sent
+/// after the RIA has finished therefore arrives after all of its events.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PixEvent {
     Xram(XramWrite),
     Reg(PixRegWrite),
     FrameSync,
+    /// Stop the VGA after rendering a final frame. Emulator-only, like FrameSync.
+    Shutdown,
+    /// Return the VGA to its power-on state (no planes, zeroed XRAM, 640x480
+    /// canvas) before replaying a new trace into it. Emulator-only.
+    #[allow(dead_code)]
+    Reset,
+}
+
+impl PixEvent {
+    /// Decode a raw 32-bit PIX word, e.g. from a bus capture.
+    ///
+    /// Device 0 channel 0 is an XRAM broadcast (register = data, value = addr,
+    /// as in `pix_pack_xram`) and device 1 a VGA register write. Everything
+    /// else, including the device 7 idle frames the bus sends between
+    /// messages, yields None, as do words without the framing bit, so a raw
+    /// capture only carries what the VGA acts on. `seq` is not on the wire
+    /// and is 0.
+    #[allow(dead_code)]
+    pub fn from_packed(raw: u32) -> Option<PixEvent> {
+        match pix_unpack(raw)? {
+            (0, 0, data, addr) => Some(PixEvent::Xram(XramWrite { addr, data, seq: 0 })),
+            (1, channel, register, value) => {
+                Some(PixEvent::Reg(PixRegWrite { device: 1, channel, register, value }))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// One line of a PIX event log (`--record-pix`), without the newline:
+///
+/// ```text
+/// xram 0100 FF        XRAM write: addr, data (hex)
+/// reg 1 0 01 0003     register write: device, channel, register, value (hex)
+/// sync                FrameSync
+/// shutdown
+/// reset
+/// ```
+///
+/// `XramWrite::seq` is not recorded; `parse_pix_log` renumbers writes.
+impl std::fmt::Display for PixEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PixEvent::Xram(w) => write!(f, "xram {:04X} {:02X}", w.addr, w.data),
+            PixEvent::Reg(r) => write!(f, "reg {} {} {:02X} {:04X}", r.device, r.channel, r.register, r.value),
+            PixEvent::FrameSync => f.write_str("sync"),
+            PixEvent::Shutdown => f.write_str("shutdown"),
+            PixEvent::Reset => f.write_str("reset"),
+        }
+    }
+}
+
+impl PixEvent {
+    /// Parse one PIX event log line (see `Display`). `seq` is 0.
+    pub fn from_log_line(line: &str) -> Option<PixEvent> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let hex8 = |s: &str| u8::from_str_radix(s, 16).ok();
+        let hex16 = |s: &str| u16::from_str_radix(s, 16).ok();
+        Some(match fields.as_slice() {
+            ["xram", addr, data] => PixEvent::Xram(XramWrite { addr: hex16(addr)?, data: hex8(data)?, seq: 0 }),
+            ["reg", device, channel, register, value] => PixEvent::Reg(PixRegWrite {
+                device: device.parse().ok().filter(|&d: &u8| d < 8)?,
+                channel: channel.parse().ok().filter(|&c: &u8| c < 16)?,
+                register: hex8(register)?,
+                value: hex16(value)?,
+            }),
+            ["sync"] => PixEvent::FrameSync,
+            ["shutdown"] => PixEvent::Shutdown,
+            ["reset"] => PixEvent::Reset,
+            _ => return None,
+        })
+    }
+}
+
+/// Parse a PIX event log, skipping blank lines. XRAM writes are numbered
+/// from 0 in log order (and again after each reset), as the RIA sent them.
+/// Returns the 1-based number of the first line that is not an event on failure.
+pub fn parse_pix_log(text: &str) -> Result<Vec<PixEvent>, usize> {
+    let mut events = Vec::new();
+    let mut seq = 0;
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut event = PixEvent::from_log_line(line).ok_or(i + 1)?;
+        match &mut event {
+            PixEvent::Xram(w) => {
+                w.seq = seq;
+                seq += 1;
+            }
+            // A reset RIA numbers its writes from 0 again
+            PixEvent::Reset => seq = 0,
+            _ => {}
+        }
+        events.push(event);
+    }
+    Ok(events)
 }
 
 /// Backchannel messages from VGA to RIA.
@@ -26,12 +133,29 @@ pub enum PixEvent {
 pub enum Backchannel {
     Vsync(u8),
     Ack,
+    /// Ack carrying a result, e.g. the plane slot a MODE write programmed.
+    AckPayload(u16),
     Nak,
+    /// Timing of a rendered frame, sent when `Vga::send_stats` is set.
+    /// Emulator-only; not a reply to any register write.
+    Stats(crate::vga::FrameStats),
+}
+
+/// Vsync backchannel byte: bit 7 flags vsync, bits 3:0 carry the frame counter.
+/// Bits 6:4 are unused and always zero when encoded.
+pub fn encode_vsync(active: bool, frame: u8) -> u8 {
+    (if active { 0x80 } else { 0 }) | (frame & 0x0F)
+}
+
+/// Split a vsync backchannel byte into (vsync_active, frame_field).
+/// Unused bits 6:4 are ignored.
+#[allow(dead_code)]
+pub fn decode_vsync(byte: u8) -> (bool, u8) {
+    (byte & 0x80 != 0, byte & 0x0F)
 }
 
 /// Pack a PIX message into the 32-bit hardware format.
 /// Format: [31:29]=device, [28]=1 (framing), [27:24]=channel, [23:16]=register, [15:0]=value
-#[allow(dead_code)]
 pub fn pix_pack(device: u8, channel: u8, register: u8, value: u16) -> u32 {
     debug_assert!(device < 8, "PIX device must be 0-7");
     debug_assert!(channel < 16, "PIX channel must be 0-15");
@@ -58,7 +182,6 @@ pub fn pix_unpack(raw: u32) -> Option<(u8, u8, u8, u16)> {
 /// Pack an XRAM write into PIX format.
 /// Matches firmware: PIX_SEND_XRAM(addr, data) = PIX_MESSAGE(0, 0, data, addr)
 /// Note: data goes in the register field (bits 23:16), addr in value field (bits 15:0).
-#[allow(dead_code)]
 pub fn pix_pack_xram(addr: u16, data: u8) -> u32 {
     pix_pack(0, 0, data, addr)
 }
@@ -97,6 +220,65 @@ mod tests {
         assert_eq!(pix_unpack(0x0000_0000), None);
     }
 
+    #[test]
+    fn test_from_packed_xram() {
+        let raw = pix_pack_xram(0x1234, 0xAB);
+        assert_eq!(
+            PixEvent::from_packed(raw),
+            Some(PixEvent::Xram(XramWrite { addr: 0x1234, data: 0xAB, seq: 0 }))
+        );
+    }
+
+    #[test]
+    fn test_from_packed_reg() {
+        let raw = pix_pack(1, 0, 1, 3);
+        assert_eq!(
+            PixEvent::from_packed(raw),
+            Some(PixEvent::Reg(PixRegWrite { device: 1, channel: 0, register: 1, value: 3 }))
+        );
+        // Device 0 on a non-zero channel is not an XRAM write
+        assert_eq!(PixEvent::from_packed(pix_pack(0, 1, 0, 0)), None);
+        assert_eq!(PixEvent::from_packed(0x0000_1234), None);
+    }
+
+    #[test]
+    fn test_from_packed_skips_idle_frames() {
+        assert_eq!(PixEvent::from_packed(pix_pack(7, 0, 0, 0)), None);
+        assert_eq!(PixEvent::from_packed(pix_pack(2, 0, 1, 3)), None, "not the VGA");
+    }
+
+    #[test]
+    fn test_vsync_roundtrip() {
+        for byte in 0..=255u8 {
+            let (active, frame) = decode_vsync(byte);
+            assert_eq!(encode_vsync(active, frame), byte & 0x8F, "byte {byte:#04X}");
+            assert_eq!(decode_vsync(encode_vsync(active, frame)), (active, frame));
+        }
+        assert_eq!(encode_vsync(true, 0x11), 0x81, "frame wraps to 4 bits");
+    }
+
+    #[test]
+    fn test_pix_log_round_trip() {
+        let events = [
+            PixEvent::Xram(XramWrite { addr: 0x0100, data: 0xFF, seq: 0 }),
+            PixEvent::Reg(PixRegWrite { device: 1, channel: 15, register: 0x10, value: 0x0005 }),
+            PixEvent::FrameSync,
+            PixEvent::Xram(XramWrite { addr: 0xFFFF, data: 0x01, seq: 1 }),
+            PixEvent::Reset,
+            PixEvent::Shutdown,
+        ];
+        let log: String = events.iter().map(|e| format!("{e}\n")).collect();
+        assert!(log.starts_with("xram 0100 FF\nreg 1 15 10 0005\nsync\n"));
+        assert_eq!(parse_pix_log(&log), Ok(events.to_vec()));
+    }
+
+    #[test]
+    fn test_pix_log_rejects_bad_lines() {
+        assert_eq!(parse_pix_log("sync\n\nxram 0100\n"), Err(3));
+        assert_eq!(parse_pix_log("reg 8 0 00 0000"), Err(1), "device 0-7");
+        assert_eq!(parse_pix_log("vsync"), Err(1));
+    }
+
     #[test]
     fn test_pix_pack_idle() {
         // Device 7 idle frame
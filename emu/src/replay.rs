@@ -0,0 +1,296 @@
+use std::sync::{Arc, Mutex};
+use crossbeam_channel::{unbounded, Receiver};
+use crate::bus::BusTransaction;
+use crate::pix::PixEvent;
+use crate::ria::Ria;
+use crate::vga::Vga;
+
+/// Control commands accepted by a replay thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayCommand {
+    /// Replay freely until the trace ends or the program exits.
+    Run,
+    /// Stop replaying until the next Run or StepCycles.
+    Pause,
+    /// Process exactly N bus transactions, then pause.
+    StepCycles(u64),
+    /// Have the VGA render the current state now, without changing run state.
+    PresentFrame,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Running,
+    Paused,
+    Stepping(u64),
+}
+
+/// Feeds a bus trace into the RIA one transaction at a time, under command control.
+pub struct Replay {
+    trace: Vec<BusTransaction>,
+    pos: usize,
+    state: State,
+    /// A PresentFrame command is waiting to be sent to the RIA.
+    present_requested: bool,
+    /// Stop after this many transactions even if the program has not exited.
+    max_transactions: Option<usize>,
+}
+
+impl Replay {
+    /// Create a replay that starts running immediately.
+    pub fn new(trace: Vec<BusTransaction>) -> Self {
+        Self { trace, pos: 0, state: State::Running, present_requested: false, max_transactions: None }
+    }
+
+    /// Stop after `n` transactions, regardless of the exit op.
+    pub fn with_max_transactions(mut self, n: usize) -> Self {
+        self.max_transactions = Some(n);
+        self
+    }
+
+    /// Number of transactions processed so far.
+    #[allow(dead_code)]
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state == State::Paused
+    }
+
+    pub fn handle_command(&mut self, cmd: ReplayCommand) {
+        self.state = match cmd {
+            ReplayCommand::Run => State::Running,
+            ReplayCommand::Pause => State::Paused,
+            ReplayCommand::StepCycles(0) => State::Paused,
+            ReplayCommand::StepCycles(n) => State::Stepping(n),
+            ReplayCommand::PresentFrame => {
+                self.present_requested = true;
+                self.state
+            }
+        };
+    }
+
+    /// Send a pending PresentFrame to the RIA.
+    fn flush_present(&mut self, ria: &mut Ria) {
+        if std::mem::take(&mut self.present_requested) {
+            ria.present_frame();
+        }
+    }
+
+    /// True once the trace is exhausted, the transaction limit is reached,
+    /// or the program has exited.
+    pub fn finished(&self, ria: &Ria) -> bool {
+        self.pos >= self.trace.len()
+            || self.max_transactions.is_some_and(|max| self.pos >= max)
+            || !ria.running
+    }
+
+    /// Process the next transaction unless paused or finished.
+    /// Returns false if nothing was processed.
+    pub fn step(&mut self, ria: &mut Ria) -> bool {
+        if self.is_paused() || self.finished(ria) {
+            return false;
+        }
+        ria.process(&self.trace[self.pos]);
+        self.pos += 1;
+        if let State::Stepping(n) = self.state {
+            self.state = if n > 1 { State::Stepping(n - 1) } else { State::Paused };
+        }
+        true
+    }
+
+    /// Replay loop for a dedicated thread. Blocks on `commands` while paused and
+    /// returns when the trace finishes or the command sender is dropped while paused.
+    pub fn run(&mut self, ria: &mut Ria, commands: &Receiver<ReplayCommand>) {
+        while !self.finished(ria) {
+            if self.is_paused() {
+                match commands.recv() {
+                    Ok(cmd) => self.handle_command(cmd),
+                    Err(_) => return,
+                }
+                self.flush_present(ria);
+                continue;
+            }
+            while let Ok(cmd) = commands.try_recv() {
+                self.handle_command(cmd);
+            }
+            self.flush_present(ria);
+            self.step(ria);
+        }
+    }
+}
+
+/// Replay `trace` through a RIA and a VGA on the calling thread and return
+/// the final 640x480 RGBA display buffer.
+///
+/// The VGA handles every PIX event the RIA emits before the next transaction
+/// is processed, and its replies wait for the RIA's next backchannel poll, so
+/// the result does not depend on thread scheduling. The channels are only
+/// used as queues between the two.
+#[allow(dead_code)]
+pub fn run_pipeline_sync(trace: &[BusTransaction]) -> Vec<u8> {
+    let framebuffer = Arc::new(Mutex::new(vec![0u8; 640 * 480 * 4]));
+    let (pix_tx, pix_rx) = unbounded();
+    let (back_tx, back_rx) = unbounded();
+    let mut vga = Vga::new(pix_rx.clone(), back_tx, framebuffer.clone());
+    let mut ria = Ria::new(pix_tx, back_rx);
+
+    let mut replay = Replay::new(trace.to_vec());
+    while replay.step(&mut ria) {
+        for event in pix_rx.try_iter() {
+            vga.handle_event(event);
+        }
+    }
+    vga.handle_event(PixEvent::Shutdown);
+
+    let fb = framebuffer.lock().unwrap_or_else(|e| e.into_inner());
+    fb.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam_channel::unbounded;
+
+    fn make_ria() -> Ria {
+        let (pix_tx, _) = unbounded();
+        let (_, back_rx) = unbounded();
+        Ria::new(pix_tx, back_rx)
+    }
+
+    fn make_trace(n: u64) -> Vec<BusTransaction> {
+        (0..n).map(|i| BusTransaction::write(i, 0x1000, 0)).collect()
+    }
+
+    #[test]
+    fn test_step_cycles_processes_exactly_n() {
+        let mut ria = make_ria();
+        let mut replay = Replay::new(make_trace(100));
+        replay.handle_command(ReplayCommand::StepCycles(10));
+        while replay.step(&mut ria) {}
+        assert_eq!(replay.position(), 10);
+        assert!(replay.is_paused());
+        assert_eq!(ria.cycle_count, 9);
+    }
+
+    #[test]
+    fn test_run_thread_with_commands() {
+        let mut ria = make_ria();
+        let mut replay = Replay::new(make_trace(100));
+        let (cmd_tx, cmd_rx) = unbounded();
+        replay.handle_command(ReplayCommand::Pause);
+        cmd_tx.send(ReplayCommand::StepCycles(10)).unwrap();
+        drop(cmd_tx);
+        // Steps 10 transactions, pauses, then returns once the sender is gone
+        replay.run(&mut ria, &cmd_rx);
+        assert_eq!(replay.position(), 10);
+    }
+
+    #[test]
+    fn test_present_frame_while_paused() {
+        let (pix_tx, pix_rx) = unbounded();
+        let (_back_tx, back_rx) = unbounded();
+        let mut ria = Ria::new(pix_tx, back_rx);
+        let mut replay = Replay::new(make_trace(100));
+        let (cmd_tx, cmd_rx) = unbounded();
+        replay.handle_command(ReplayCommand::Pause);
+        cmd_tx.send(ReplayCommand::PresentFrame).unwrap();
+        drop(cmd_tx);
+        replay.run(&mut ria, &cmd_rx);
+        assert_eq!(replay.position(), 0, "presenting does not step");
+        assert!(replay.is_paused());
+        assert_eq!(pix_rx.try_iter().collect::<Vec<_>>(), vec![crate::pix::PixEvent::FrameSync]);
+    }
+
+    #[test]
+    fn test_run_stops_on_exit() {
+        let mut ria = make_ria();
+        let mut trace = make_trace(5);
+        trace.push(BusTransaction::write(5, 0xFFEF, 0xFF));
+        trace.extend(make_trace(5));
+        let mut replay = Replay::new(trace);
+        let (_cmd_tx, cmd_rx) = unbounded();
+        replay.run(&mut ria, &cmd_rx);
+        assert_eq!(replay.position(), 6);
+    }
+
+    #[test]
+    fn test_max_transactions_stops_early() {
+        let mut ria = make_ria();
+        let mut replay = Replay::new(make_trace(100)).with_max_transactions(5);
+        let mut processed = 0;
+        while replay.step(&mut ria) {
+            processed += 1;
+        }
+        assert_eq!(processed, 5);
+        assert_eq!(replay.position(), 5);
+        assert!(replay.finished(&ria));
+        assert!(ria.running, "limit stops replay without the exit op");
+    }
+
+    #[test]
+    fn test_run_pipeline_sync_mandelbrot() {
+        use crate::test_harness::{generate_test_trace, TestMode};
+        use crate::vga::palette::PALETTE_256;
+
+        let trace = generate_test_trace(TestMode::Mandelbrot);
+        let display = run_pipeline_sync(&trace);
+        // Canvas (200, 120) is c = -0.375 + 0i, inside the main cardioid: all
+        // 16 iterations, color 15. 2x upscale puts it at display (400, 240).
+        let idx = (240 * 640 + 400) * 4;
+        assert_eq!(display[idx..idx + 4], PALETTE_256[15].to_be_bytes());
+
+        assert_eq!(run_pipeline_sync(&trace), display, "same trace, same frame");
+    }
+
+    #[test]
+    fn test_reset_clears_previous_trace() {
+        use crate::pix::PixEvent;
+        use crate::ria_api::TraceBuilder;
+        use crate::test_harness::{generate_test_trace, TestMode};
+        use crate::vga::mode3::Mode3Config;
+
+        // A 4x4 8bpp square at the top left of a 320x240 canvas
+        let mut tb = TraceBuilder::new();
+        tb.xreg_vga_canvas(1);
+        tb.xram0_write(0x0100, &[9; 16]);
+        let cfg = Mode3Config {
+            x_wrap: false,
+            y_wrap: false,
+            x_pos_px: 0,
+            y_pos_px: 0,
+            width_px: 4,
+            height_px: 4,
+            xram_data_ptr: 0x0100,
+            xram_palette_ptr: 0xFFFF,
+        };
+        tb.program_mode3(0x0000, &cfg, 3, 0, 0, 0);
+        tb.wait_frames(1);
+        let simple = tb.trace;
+
+        let framebuffer = Arc::new(Mutex::new(vec![0u8; 640 * 480 * 4]));
+        let (pix_tx, pix_rx) = unbounded();
+        let (back_tx, back_rx) = unbounded();
+        let mut vga = Vga::new(pix_rx.clone(), back_tx, framebuffer.clone());
+        // A fresh RIA per trace, as when the RIA thread is restarted
+        for (i, trace) in [generate_test_trace(TestMode::MultiPlane), simple.clone()].iter().enumerate() {
+            if i > 0 {
+                vga.handle_event(PixEvent::Reset);
+            }
+            let mut ria = Ria::new(pix_tx.clone(), back_rx.clone());
+            let mut replay = Replay::new(trace.clone());
+            while replay.step(&mut ria) {
+                for event in pix_rx.try_iter() {
+                    vga.handle_event(event);
+                }
+            }
+        }
+        vga.handle_event(PixEvent::Shutdown);
+
+        assert_eq!(vga.active_plane_count(), 1);
+        assert_eq!(vga.xram_seq_errors, 0, "sequence restarts with the new RIA");
+        let fb = framebuffer.lock().unwrap().clone();
+        assert!(fb == run_pipeline_sync(&simple), "no pixels left from the multi-plane trace");
+    }
+}
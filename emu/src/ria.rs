@@ -1,18 +1,101 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use crossbeam_channel::{Receiver, Sender, TryRecvError};
 use crate::bus::BusTransaction;
 use crate::pix::{Backchannel, PixEvent, PixRegWrite, XramWrite};
 
 const XSTACK_SIZE: usize = 0x200;
 
+/// ERRNO values reported in $FFED/$FFEE, matching cc65's `errno.h`.
+pub mod errno {
+    /// Invalid argument, e.g. a malformed xreg call.
+    pub const EINVAL: u16 = 7;
+    /// OS operation not implemented.
+    pub const ENOSYS: u16 = 13;
+    /// Push onto a full XSTACK. cc65 has no EOVERFLOW, so this is an
+    /// emulator-specific value following its last code (EUNKNOWN = 18).
+    pub const EOVERFLOW: u16 = 19;
+}
+
+/// Callback receiving each bus transaction and a description of what the RIA did with it.
+pub type TraceLog = Box<dyn FnMut(&BusTransaction, &str) + Send>;
+
+/// RIA construction options.
+#[derive(Debug, Clone, Copy)]
+pub struct RiaConfig {
+    /// PHI2 frequency in Hz.
+    pub phi2_freq: u64,
+    /// Display refresh rate in Hz. A FrameSync is sent every phi2_freq / refresh_hz cycles.
+    pub refresh_hz: u64,
+    /// Sleep at frame boundaries so emulated frames track wall-clock time.
+    /// Off by default so headless runs replay as fast as possible.
+    pub real_time: bool,
+    /// Mask applied to XRAM addresses. 0xFFFF is the full 64K; 0x7FFF models a
+    /// 32K board where the upper half mirrors the lower.
+    pub xram_mask: u16,
+    /// Emulator-only: enable `OP_STEP16`, which gives a portal a 16-bit step.
+    /// Off matches hardware, where the op fails with ENOSYS.
+    pub extended_step: bool,
+    /// Watchdog: stop the RIA at the first transaction past this cycle, so a
+    /// trace that never issues the exit op cannot run forever.
+    pub max_cycles: Option<u64>,
+}
+
+impl RiaConfig {
+    /// PHI2 cycles between FrameSyncs.
+    pub fn cycles_per_frame(&self) -> u64 {
+        self.phi2_freq / self.refresh_hz
+    }
+}
+
+impl Default for RiaConfig {
+    fn default() -> Self {
+        Self {
+            phi2_freq: 8_000_000,
+            refresh_hz: 60,
+            real_time: false,
+            xram_mask: 0xFFFF,
+            extended_step: false,
+            max_cycles: None,
+        }
+    }
+}
+
+/// Emulator-only frame barrier: a write here forces a FrameSync at the
+/// current cycle. $FFF1 is the BRA opcode of the read-only fastcall return
+/// stub, so hardware ignores the write and traces stay portable.
+pub const FRAME_BARRIER_ADDR: u16 = 0xFFF1;
+
+/// Emulator-only OS operation: return the payload of the most recent VGA
+/// reply in AX (e.g. the plane a MODE write installed), or fail with EINVAL
+/// if that reply carried none. Replies are only latched, never written to AX
+/// when they arrive, so they cannot overwrite another op's result.
+pub const OP_ACK_PAYLOAD: u8 = 0xF0;
+
+/// Emulator-only OS operation (needs `RiaConfig::extended_step`): give a
+/// portal a 16-bit step, e.g. a 640-byte row stride. XSTACK holds the portal
+/// (0 or 1) pushed first, then the step as int16. The step applies until the
+/// portal's STEP register is next written, so every 8-bit STEP value keeps
+/// its hardware meaning.
+pub const OP_STEP16: u8 = 0xF1;
+
 pub struct Ria {
     /// Register file: $FFE0-$FFFF mapped to indices 0-31.
     pub regs: [u8; 32],
     /// 64KB extended RAM.
     pub xram: Box<[u8; 65536]>,
+    /// Mask applied to portal addresses before indexing `xram`.
+    pub xram_mask: u16,
     /// 512-byte stack + 1 zero byte for cstring safety.
     pub xstack: [u8; XSTACK_SIZE + 1],
     /// Stack pointer. Starts at XSTACK_SIZE (empty), decrements on push.
     pub xstack_ptr: usize,
+    /// Set when a push is attempted on a full XSTACK; cleared by zxstack.
+    pub xstack_overflow: bool,
+    /// Emulator-only 16-bit steps; see `RiaConfig::extended_step`.
+    pub extended_step: bool,
+    /// 16-bit steps set by `OP_STEP16`, cleared by a write to STEP0/STEP1.
+    step0_ext: Option<i16>,
+    step1_ext: Option<i16>,
     /// IRQ enable register (bit 0 enables VSYNC IRQ).
     pub irq_enabled: u8,
     /// IRQ pin state. true = high (inactive), false = low (asserted).
@@ -20,16 +103,39 @@ pub struct Ria {
     /// Current PHI2 cycle count.
     pub cycle_count: u64,
     /// PHI2 frequency in Hz (default 8 MHz).
-    #[allow(dead_code)]
     pub phi2_freq: u64,
     /// Cycles per frame (phi2_freq / 60).
     cycles_per_frame: u64,
     /// Cycle count of next frame boundary.
     next_frame_cycle: u64,
+    /// Pace frame boundaries against wall-clock time.
+    real_time: bool,
+    /// Wall-clock time corresponding to cycle 0, set on the first transaction.
+    epoch: Option<Instant>,
     /// PIX transmit channel (RIA -> VGA).
     pix_tx: Sender<PixEvent>,
     /// Backchannel receive (VGA -> RIA).
     backchannel_rx: Receiver<Backchannel>,
+    /// Sequence number for the next XRAM broadcast.
+    xram_seq: u64,
+    /// xorshift32 state for the lrand op. Never zero.
+    rng_state: u32,
+    /// Payload of the most recent VGA ack; None after a plain Ack or Nak.
+    pub ack_payload: Option<u16>,
+    /// Outcome of the most recent VGA reply: Some(true) for Ack, Some(false) for Nak.
+    last_ack: Option<bool>,
+    /// Number of Acks (with or without payload) received.
+    ack_count: u64,
+    /// Number of Naks received.
+    nak_count: u64,
+    /// Debug hook called from `process` with a description of each action.
+    trace_log: Option<TraceLog>,
+    /// Descriptions collected while processing the current transaction.
+    trace_notes: Vec<String>,
+    /// See `RiaConfig::max_cycles`.
+    pub max_cycles: Option<u64>,
+    /// Set when `max_cycles` stopped the RIA; cleared by reset.
+    pub watchdog_fired: bool,
     /// Whether the emulator is running.
     pub running: bool,
 }
@@ -39,27 +145,93 @@ impl Ria {
         pix_tx: Sender<PixEvent>,
         backchannel_rx: Receiver<Backchannel>,
     ) -> Self {
-        let phi2_freq = 8_000_000;
-        let cycles_per_frame = phi2_freq / 60;
+        Self::with_config(pix_tx, backchannel_rx, RiaConfig::default())
+    }
+
+    pub fn with_config(
+        pix_tx: Sender<PixEvent>,
+        backchannel_rx: Receiver<Backchannel>,
+        config: RiaConfig,
+    ) -> Self {
+        let phi2_freq = config.phi2_freq;
+        let cycles_per_frame = config.cycles_per_frame();
         let mut ria = Self {
             regs: [0; 32],
             xram: Box::new([0; 65536]),
             xstack: [0; XSTACK_SIZE + 1],
             xstack_ptr: XSTACK_SIZE,
+            xstack_overflow: false,
+            extended_step: config.extended_step,
+            step0_ext: None,
+            step1_ext: None,
             irq_enabled: 0,
             irq_pin: true,
             cycle_count: 0,
             phi2_freq,
             cycles_per_frame,
             next_frame_cycle: cycles_per_frame,
+            real_time: config.real_time,
+            xram_mask: config.xram_mask,
+            epoch: None,
             pix_tx,
             backchannel_rx,
+            xram_seq: 0,
+            rng_state: time_seed(),
+            ack_payload: None,
+            last_ack: None,
+            ack_count: 0,
+            nak_count: 0,
+            trace_log: None,
+            trace_notes: Vec::new(),
+            max_cycles: config.max_cycles,
+            watchdog_fired: false,
             running: true,
         };
         ria.reset();
         ria
     }
 
+    /// Like `new`, but with a fixed random seed so lrand is reproducible.
+    #[allow(dead_code)]
+    pub fn with_seed(
+        pix_tx: Sender<PixEvent>,
+        backchannel_rx: Receiver<Backchannel>,
+        seed: u32,
+    ) -> Self {
+        let mut ria = Self::new(pix_tx, backchannel_rx);
+        ria.seed(seed);
+        ria
+    }
+
+    /// Install or remove the debug trace hook. When unset, no descriptions are built.
+    #[allow(dead_code)]
+    pub fn set_trace_log(&mut self, log: Option<TraceLog>) {
+        self.trace_log = log;
+    }
+
+    /// Record a description of the current action for the trace hook, if installed.
+    fn note(&mut self, describe: impl FnOnce() -> String) {
+        if self.trace_log.is_some() {
+            self.trace_notes.push(describe());
+        }
+    }
+
+    /// Reseed the random number generator.
+    pub fn seed(&mut self, seed: u32) {
+        // xorshift gets stuck at zero
+        self.rng_state = if seed == 0 { 0x2545_F491 } else { seed };
+    }
+
+    /// Next xorshift32 value.
+    fn next_rand(&mut self) -> u32 {
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.rng_state = x;
+        x
+    }
+
     /// Reset registers to power-on defaults.
     /// Matches api_run() in firmware/src/ria/api/api.c lines 99-111.
     pub fn reset(&mut self) {
@@ -77,10 +249,14 @@ impl Ria {
         self.regs[0x09] = 1;
         // RW1 = xram[0]
         self.regs[0x08] = self.xram[0];
+        self.step0_ext = None;
+        self.step1_ext = None;
         // Reset xstack
         self.xstack_ptr = XSTACK_SIZE;
+        self.xstack_overflow = false;
         self.irq_enabled = 0;
         self.irq_pin = true;
+        self.watchdog_fired = false;
         self.running = true;
     }
 
@@ -90,14 +266,17 @@ impl Ria {
         u16::from_le_bytes([self.regs[0x06], self.regs[0x07]])
     }
 
+    /// Portal accesses advance ADDR0 by `step0()` modulo 0x10000, so a STEP0
+    /// of 2 from 0xFFFF lands on 0x0001 and -2 from 0x0001 on 0xFFFF, as the
+    /// firmware's 16-bit `xram_addr0 += step0` does.
     fn set_addr0(&mut self, val: u16) {
         let bytes = val.to_le_bytes();
         self.regs[0x06] = bytes[0];
         self.regs[0x07] = bytes[1];
     }
 
-    fn step0(&self) -> i8 {
-        self.regs[0x05] as i8
+    fn step0(&self) -> i16 {
+        self.step(self.regs[0x05], self.step0_ext)
     }
 
     fn addr1(&self) -> u16 {
@@ -110,28 +289,55 @@ impl Ria {
         self.regs[0x0B] = bytes[1];
     }
 
-    fn step1(&self) -> i8 {
-        self.regs[0x09] as i8
+    fn step1(&self) -> i16 {
+        self.step(self.regs[0x09], self.step1_ext)
+    }
+
+    /// Effective portal step: the 16-bit step from `OP_STEP16` if one is
+    /// set, else the signed STEP byte.
+    fn step(&self, reg: u8, ext: Option<i16>) -> i16 {
+        ext.unwrap_or(reg as i8 as i16)
     }
 
     /// Refresh RW0 and RW1 from XRAM.
     /// Matches act_loop lines 249-250: RIA_RW0 = xram[RIA_ADDR0]; RIA_RW1 = xram[RIA_ADDR1];
     fn refresh_rw(&mut self) {
-        let addr0 = self.addr0();
+        let addr0 = self.addr0() & self.xram_mask;
         self.regs[0x04] = self.xram[addr0 as usize];
-        let addr1 = self.addr1();
+        let addr1 = self.addr1() & self.xram_mask;
         self.regs[0x08] = self.xram[addr1 as usize];
     }
 
+    /// Send a FrameSync now so the VGA renders the current state, without
+    /// moving the cycle-based frame boundary.
+    pub fn present_frame(&mut self) {
+        let _ = self.pix_tx.send(PixEvent::FrameSync);
+        self.note(|| "FrameSync (forced)".to_string());
+        self.poll_backchannel();
+    }
+
     /// Process a single bus transaction.
     /// Returns the data byte for reads (value placed on data bus).
     pub fn process(&mut self, txn: &BusTransaction) -> u8 {
         self.cycle_count = txn.cycle;
+        if self.max_cycles.is_some_and(|max| txn.cycle > max) {
+            // Watchdog: leave the transaction unprocessed and stop as exit would
+            self.running = false;
+            self.watchdog_fired = true;
+            return txn.data;
+        }
+        if self.real_time && self.epoch.is_none() {
+            self.epoch = Some(Instant::now());
+        }
 
         // Check for frame boundary
         if self.cycle_count >= self.next_frame_cycle {
+            if self.real_time {
+                self.pace_frame();
+            }
             self.next_frame_cycle += self.cycles_per_frame;
             let _ = self.pix_tx.send(PixEvent::FrameSync);
+            self.note(|| "FrameSync".to_string());
             // Process backchannel
             self.poll_backchannel();
         }
@@ -139,15 +345,85 @@ impl Ria {
         // Refresh RW0/RW1 before processing (matches act_loop continuous refresh)
         self.refresh_rw();
 
-        if !txn.hits_ria() {
-            return txn.data;
-        }
-
-        if txn.rw {
+        let result = if !txn.hits_ria() {
+            txn.data
+        } else if txn.rw {
             self.handle_read(txn)
         } else {
             self.handle_write(txn);
             txn.data
+        };
+
+        if let Some(log) = self.trace_log.as_mut() {
+            for note in self.trace_notes.drain(..) {
+                log(txn, &note);
+            }
+        }
+        result
+    }
+
+    /// Process transactions in order, returning (cycle, data) for every read.
+    /// Stops before the next transaction once the RIA has stopped running,
+    /// e.g. after the exit op.
+    #[allow(dead_code)]
+    pub fn process_all(&mut self, txns: &[BusTransaction]) -> Vec<(u64, u8)> {
+        let mut reads = Vec::new();
+        for txn in txns {
+            if !self.running {
+                break;
+            }
+            let data = self.process(txn);
+            if txn.rw {
+                reads.push((txn.cycle, data));
+            }
+        }
+        reads
+    }
+
+    /// Sleep until the wall-clock time of the frame boundary at `next_frame_cycle`.
+    fn pace_frame(&mut self) {
+        let epoch = *self.epoch.get_or_insert_with(Instant::now);
+        let target = epoch + Duration::from_secs_f64(
+            self.next_frame_cycle as f64 / self.phi2_freq as f64,
+        );
+        let now = Instant::now();
+        if target > now {
+            std::thread::sleep(target - now);
+        }
+    }
+
+    /// Outcome of the most recent VGA reply: Some(true) for Ack, Some(false)
+    /// for Nak, None if the VGA has not replied yet.
+    #[allow(dead_code)]
+    pub fn last_ack(&self) -> Option<bool> {
+        self.last_ack
+    }
+
+    /// Number of Acks received from the VGA.
+    #[allow(dead_code)]
+    pub fn ack_count(&self) -> u64 {
+        self.ack_count
+    }
+
+    /// Number of Naks received from the VGA.
+    #[allow(dead_code)]
+    pub fn nak_count(&self) -> u64 {
+        self.nak_count
+    }
+
+    /// Occupied XSTACK bytes, top of stack (last pushed) first, without popping.
+    #[allow(dead_code)]
+    pub fn xstack_view(&self) -> &[u8] {
+        &self.xstack[self.xstack_ptr..XSTACK_SIZE]
+    }
+
+    /// Handle a VSYNC exactly as if `Backchannel::Vsync(frame)` had arrived:
+    /// `frame` becomes the VSYNC register value (see `encode_vsync`), and the
+    /// IRQ pin is asserted if the VSYNC IRQ is enabled.
+    pub fn force_vsync(&mut self, frame: u8) {
+        self.regs[0x03] = frame;
+        if self.irq_enabled & 0x01 != 0 {
+            self.irq_pin = false;
         }
     }
 
@@ -155,15 +431,25 @@ impl Ria {
     pub fn poll_backchannel(&mut self) {
         loop {
             match self.backchannel_rx.try_recv() {
-                Ok(Backchannel::Vsync(frame)) => {
-                    self.regs[0x03] = frame;
-                    if self.irq_enabled & 0x01 != 0 {
-                        self.irq_pin = false;
-                    }
+                Ok(Backchannel::Vsync(frame)) => self.force_vsync(frame),
+                Ok(Backchannel::AckPayload(payload)) => {
+                    // Read by OP_ACK_PAYLOAD; AX belongs to the last op run
+                    self.ack_payload = Some(payload);
+                    self.last_ack = Some(true);
+                    self.ack_count += 1;
+                }
+                Ok(Backchannel::Ack) => {
+                    self.ack_payload = None;
+                    self.last_ack = Some(true);
+                    self.ack_count += 1;
                 }
-                Ok(Backchannel::Ack) | Ok(Backchannel::Nak) => {
-                    // For MVP, we don't track ack/nak state
+                Ok(Backchannel::Nak) => {
+                    self.ack_payload = None;
+                    self.last_ack = Some(false);
+                    self.nak_count += 1;
                 }
+                // Profiling data for the host, not visible to the 6502
+                Ok(Backchannel::Stats(_)) => {}
                 Err(TryRecvError::Empty) => break,
                 Err(TryRecvError::Disconnected) => {
                     self.running = false;
@@ -189,16 +475,17 @@ impl Ria {
             // $FFE4: Write XRAM via portal 0
             0x04 => {
                 let addr = self.addr0();
-                self.xram[addr as usize] = data;
-                let _ = self.pix_tx.send(PixEvent::Xram(XramWrite { addr, data }));
+                self.write_xram(addr, data);
                 // Fallthrough: auto-increment addr0
-                let new_addr = addr.wrapping_add(self.step0() as i16 as u16);
+                let new_addr = addr.wrapping_add(self.step0() as u16);
                 self.set_addr0(new_addr);
+                self.note(|| format!("XRAM[0x{addr:04X}] <- 0x{data:02X}, ADDR0 now 0x{new_addr:04X}"));
             }
 
             // $FFE5: STEP0
             0x05 => {
                 self.regs[0x05] = data;
+                self.step0_ext = None;
             }
 
             // $FFE6: ADDR0 low
@@ -214,16 +501,17 @@ impl Ria {
             // $FFE8: Write XRAM via portal 1
             0x08 => {
                 let addr = self.addr1();
-                self.xram[addr as usize] = data;
-                let _ = self.pix_tx.send(PixEvent::Xram(XramWrite { addr, data }));
+                self.write_xram(addr, data);
                 // Fallthrough: auto-increment addr1
-                let new_addr = addr.wrapping_add(self.step1() as i16 as u16);
+                let new_addr = addr.wrapping_add(self.step1() as u16);
                 self.set_addr1(new_addr);
+                self.note(|| format!("XRAM[0x{addr:04X}] <- 0x{data:02X}, ADDR1 now 0x{new_addr:04X}"));
             }
 
             // $FFE9: STEP1
             0x09 => {
                 self.regs[0x09] = data;
+                self.step1_ext = None;
             }
 
             // $FFEA: ADDR1 low
@@ -241,6 +529,13 @@ impl Ria {
                 if self.xstack_ptr > 0 {
                     self.xstack_ptr -= 1;
                     self.xstack[self.xstack_ptr] = data;
+                    let depth = XSTACK_SIZE - self.xstack_ptr;
+                    self.note(|| format!("XSTACK push 0x{data:02X}, depth {depth}"));
+                } else {
+                    // Stack full: drop the byte and report EOVERFLOW
+                    self.xstack_overflow = true;
+                    self.set_errno(errno::EOVERFLOW);
+                    self.note(|| format!("XSTACK overflow, 0x{data:02X} dropped"));
                 }
                 self.regs[0x0C] = self.xstack[self.xstack_ptr];
             }
@@ -267,6 +562,11 @@ impl Ria {
                 self.irq_pin = true; // clear interrupt
             }
 
+            // $FFF1: frame barrier (emulator extension, see FRAME_BARRIER_ADDR)
+            0x11 => {
+                self.present_frame();
+            }
+
             // $FFF4: A register
             0x14 => { self.regs[0x14] = data; }
 
@@ -286,6 +586,25 @@ impl Ria {
         }
     }
 
+    /// Store an XRAM byte and broadcast it to PIX devices with the next sequence number.
+    /// The address is masked by `xram_mask` first.
+    fn write_xram(&mut self, addr: u16, data: u8) {
+        let addr = addr & self.xram_mask;
+        self.xram[addr as usize] = data;
+        let seq = self.xram_seq;
+        self.xram_seq += 1;
+        let _ = self.pix_tx.send(PixEvent::Xram(XramWrite { addr, data, seq }));
+    }
+
+    /// Copy a memory image into XRAM from address 0, broadcasting every byte
+    /// to the VGA. Bytes past 64K are ignored.
+    pub fn load_xram(&mut self, image: &[u8]) {
+        for (addr, &data) in image.iter().take(0x10000).enumerate() {
+            self.write_xram(addr as u16, data);
+        }
+        self.refresh_rw();
+    }
+
     /// Handle a 6502 read from RIA register space.
     /// Matches act_loop switch cases for CASE_READ.
     fn handle_read(&mut self, txn: &BusTransaction) -> u8 {
@@ -308,12 +627,19 @@ impl Ria {
                 0
             }
 
+            // $FFE3: VSYNC, the last `Backchannel::Vsync` byte: bit 7 set, bits 3:0
+            // the VGA's frame counter (see `encode_vsync`). Firmware waits for a
+            // frame with `v = RIA.vsync; while (v == RIA.vsync);`, so only a
+            // change matters. Reading has no side effects.
+            0x03 => self.regs[0x03],
+
             // $FFE4: Read XRAM via portal 0 (auto-increment after)
             0x04 => {
                 let val = self.regs[0x04]; // already refreshed
                 let addr = self.addr0();
-                let new_addr = addr.wrapping_add(self.step0() as i16 as u16);
+                let new_addr = addr.wrapping_add(self.step0() as u16);
                 self.set_addr0(new_addr);
+                self.note(|| format!("XRAM[0x{addr:04X}] -> 0x{val:02X}, ADDR0 now 0x{new_addr:04X}"));
                 val
             }
 
@@ -321,8 +647,9 @@ impl Ria {
             0x08 => {
                 let val = self.regs[0x08]; // already refreshed
                 let addr = self.addr1();
-                let new_addr = addr.wrapping_add(self.step1() as i16 as u16);
+                let new_addr = addr.wrapping_add(self.step1() as u16);
                 self.set_addr1(new_addr);
+                self.note(|| format!("XRAM[0x{addr:04X}] -> 0x{val:02X}, ADDR1 now 0x{new_addr:04X}"));
                 val
             }
 
@@ -335,6 +662,7 @@ impl Ria {
                     self.xstack_ptr += 1;
                 }
                 self.regs[0x0C] = self.xstack[self.xstack_ptr];
+                self.note(|| format!("XSTACK pop 0x{val:02X}"));
                 val
             }
 
@@ -344,7 +672,14 @@ impl Ria {
                 self.regs[0x10]
             }
 
-            // All other reads: return current register value
+            // All other reads return the last value stored, with no side
+            // effects: UART TX, STEP and ADDR, ERRNO, OP, SREG, the 6502
+            // vectors at $FFFA-$FFFF, and $FFF1-$FFF7, the rest of the return
+            // stub the 6502 jumps into after an OP, as `api_return_ax` leaves
+            // it: BRA ($FFF1), its offset ($FFF2, BUSY: firmware spins on 0xFE
+            // while an op runs; ops here finish before the next bus cycle, so
+            // it is always the released 0x00), LDA # ($FFF3), A, LDX # ($FFF5),
+            // X and RTS ($FFF7)
             _ => self.regs[reg as usize],
         }
     }
@@ -356,7 +691,9 @@ impl Ria {
             0x00 => {
                 self.regs[0x0C] = 0; // API_STACK = 0
                 self.xstack_ptr = XSTACK_SIZE;
+                self.xstack_overflow = false;
                 self.api_return_ax(0);
+                self.note(|| "OP zxstack".to_string());
             }
 
             // 0x01: xreg - send extended register to PIX device
@@ -364,14 +701,39 @@ impl Ria {
                 self.handle_xreg();
             }
 
+            // 0x04: lrand - 31-bit random long in SREG:AX
+            0x04 => {
+                let val = self.next_rand() & 0x7FFF_FFFF;
+                self.api_return_axsreg(val);
+                self.note(|| format!("OP lrand -> 0x{val:08X}"));
+            }
+
+            // 0xF0: emulator extension, see OP_ACK_PAYLOAD
+            OP_ACK_PAYLOAD => {
+                self.poll_backchannel();
+                let payload = self.ack_payload;
+                match payload {
+                    Some(payload) => self.api_return_ax(payload),
+                    None => self.api_return_errno(errno::EINVAL),
+                }
+                self.note(|| format!("OP ack_payload -> {payload:?}"));
+            }
+
+            // 0xF1: emulator extension, see OP_STEP16
+            OP_STEP16 if self.extended_step => {
+                self.handle_step16();
+            }
+
             // 0xFF: exit - stop CPU
             0xFF => {
                 self.running = false;
+                self.note(|| "OP exit".to_string());
             }
 
             // All others: return ENOSYS (not implemented)
             _ => {
-                self.api_return_ax(0xFFFF); // -1
+                self.api_return_errno(errno::ENOSYS);
+                self.note(|| format!("OP 0x{op:02X} not implemented"));
             }
         }
     }
@@ -389,7 +751,8 @@ impl Ria {
     /// Data mapping: first-pushed (highest offset) -> lowest register, last-pushed (lowest offset) -> highest register.
     fn handle_xreg(&mut self) {
         if self.xstack_ptr >= XSTACK_SIZE - 3 {
-            self.api_return_ax(0xFFFF);
+            self.api_return_errno(errno::EINVAL);
+            self.note(|| "OP xreg failed: missing header".to_string());
             return;
         }
 
@@ -399,11 +762,13 @@ impl Ria {
         let data_bytes = XSTACK_SIZE - self.xstack_ptr - 3;
 
         if data_bytes < 2 || !data_bytes.is_multiple_of(2) || device > 7 || channel > 15 {
-            self.api_return_ax(0xFFFF);
+            self.api_return_errno(errno::EINVAL);
+            self.note(|| format!("OP xreg failed: device={device} ch={channel} data_bytes={data_bytes}"));
             return;
         }
 
         let count = data_bytes / 2;
+        self.note(|| format!("OP xreg device={device} ch={channel} addr=0x{start_addr:02X} count={count}"));
 
         // Send in order: first-pushed data (at highest offset) -> lowest register (start_addr+0),
         // last-pushed data (at xstack_ptr) -> highest register (start_addr + count - 1).
@@ -421,6 +786,7 @@ impl Ria {
             ]);
             let register = start_addr + i as u8;
             let _ = self.pix_tx.send(PixEvent::Reg(PixRegWrite {
+                device,
                 channel,
                 register,
                 value,
@@ -431,6 +797,52 @@ impl Ria {
         self.api_return_ax(0);
     }
 
+    /// Handle the emulator-only `OP_STEP16`.
+    ///
+    /// Xstack layout (pushed by 6502, top-down):
+    ///   [XSTACK_SIZE-1] = portal (0 or 1)
+    ///   [XSTACK_SIZE-3..XSTACK_SIZE-1] = int16 step
+    fn handle_step16(&mut self) {
+        let portal = self.xstack[XSTACK_SIZE - 1];
+        if self.xstack_ptr != XSTACK_SIZE - 3 || portal > 1 {
+            let depth = XSTACK_SIZE - self.xstack_ptr;
+            self.xstack_ptr = XSTACK_SIZE;
+            self.api_return_errno(errno::EINVAL);
+            self.note(|| format!("OP step16 failed: portal={portal} xstack bytes={depth}"));
+            return;
+        }
+        let step = i16::from_le_bytes([self.xstack[self.xstack_ptr], self.xstack[self.xstack_ptr + 1]]);
+        if portal == 0 {
+            self.step0_ext = Some(step);
+        } else {
+            self.step1_ext = Some(step);
+        }
+        self.xstack_ptr = XSTACK_SIZE;
+        self.api_return_ax(0);
+        self.note(|| format!("OP step16 portal={portal} step={step}"));
+    }
+
+    /// Set return registers with a 32-bit value: high word in SREG ($FFF8-$FFF9), low in AX.
+    /// Matches api_return_axsreg() in firmware/src/ria/api/api.h.
+    fn api_return_axsreg(&mut self, val: u32) {
+        self.regs[0x18] = (val >> 16) as u8;
+        self.regs[0x19] = (val >> 24) as u8;
+        self.api_return_ax(val as u16);
+    }
+
+    /// Set ERRNO ($FFED/$FFEE).
+    fn set_errno(&mut self, errno: u16) {
+        self.regs[0x0D] = (errno & 0xFF) as u8;
+        self.regs[0x0E] = (errno >> 8) as u8;
+    }
+
+    /// Fail an OS operation: set ERRNO and return -1 in AX.
+    /// Matches api_return_errno() in firmware/src/ria/api/api.c.
+    fn api_return_errno(&mut self, errno: u16) {
+        self.set_errno(errno);
+        self.api_return_ax(0xFFFF);
+    }
+
     /// Set return registers to unblocked state with AX return value.
     /// Matches api_return_ax() in firmware/src/ria/api/api.h.
     fn api_return_ax(&mut self, val: u16) {
@@ -448,11 +860,20 @@ impl Ria {
     }
 }
 
+/// Seed for a fresh RIA when none is given.
+fn time_seed() -> u32 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u32);
+    nanos | 1
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crossbeam_channel::unbounded;
     use crate::bus::BusTransaction;
+    use crate::ria_api::{vga_mode3_config_t, TraceBuilder};
 
     fn make_ria() -> (Ria, crossbeam_channel::Receiver<PixEvent>, crossbeam_channel::Sender<Backchannel>) {
         let (pix_tx, pix_rx) = unbounded();
@@ -484,7 +905,219 @@ mod tests {
 
         // Check PIX event was emitted
         let evt = pix_rx.try_recv().unwrap();
-        assert_eq!(evt, PixEvent::Xram(XramWrite { addr: 0x0100, data: 0x42 }));
+        assert_eq!(evt, PixEvent::Xram(XramWrite { addr: 0x0100, data: 0x42, seq: 0 }));
+    }
+
+    #[test]
+    fn test_xram_seq_monotonic_across_portals() {
+        let (mut ria, pix_rx, _back_tx) = make_ria();
+        // ADDR0 = 0x0000, ADDR1 = 0x8000
+        ria.process(&BusTransaction::write(0, 0xFFEB, 0x80));
+        for i in 0..8u8 {
+            let portal = if i % 2 == 0 { 0xFFE4 } else { 0xFFE8 };
+            ria.process(&BusTransaction::write(i as u64, portal, i));
+        }
+        let writes: Vec<XramWrite> = pix_rx
+            .try_iter()
+            .filter_map(|e| match e {
+                PixEvent::Xram(w) => Some(w),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(writes.len(), 8);
+        for (i, w) in writes.iter().enumerate() {
+            assert_eq!(w.seq, i as u64);
+            assert_eq!(w.data, i as u8, "writes arrive in bus order");
+        }
+        assert_eq!(writes[1].addr, 0x8000);
+    }
+
+    #[test]
+    fn test_load_xram_image() {
+        let (mut ria, pix_rx, _back_tx) = make_ria();
+        ria.load_xram(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(ria.xram[..5], [0xDE, 0xAD, 0xBE, 0xEF, 0x00]);
+        let broadcast: Vec<(u16, u8)> = pix_rx
+            .try_iter()
+            .filter_map(|e| match e {
+                PixEvent::Xram(w) => Some((w.addr, w.data)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(broadcast, vec![(0, 0xDE), (1, 0xAD), (2, 0xBE), (3, 0xEF)]);
+        assert_eq!(ria.regs[0x04], 0xDE, "RW0 reflects the loaded byte at ADDR0");
+    }
+
+    #[test]
+    fn test_xram_mask_mirrors_upper_half() {
+        let (pix_tx, pix_rx) = unbounded();
+        let (_back_tx, back_rx) = unbounded();
+        let config = RiaConfig { xram_mask: 0x7FFF, ..Default::default() };
+        let mut ria = Ria::with_config(pix_tx, back_rx, config);
+
+        // Write 0x5A at 0x8000 via portal 0
+        ria.process(&BusTransaction::write(0, 0xFFE7, 0x80));
+        ria.process(&BusTransaction::write(1, 0xFFE4, 0x5A));
+        assert_eq!(ria.xram[0x0000], 0x5A);
+        assert!(matches!(pix_rx.try_recv(), Ok(PixEvent::Xram(w)) if w.addr == 0x0000));
+
+        // Read it back at 0x0000 via portal 1
+        ria.process(&BusTransaction::write(2, 0xFFEB, 0x00));
+        assert_eq!(ria.process(&BusTransaction::read(3, 0xFFE8, 0)), 0x5A);
+    }
+
+    #[test]
+    fn test_extended_step_row_stride() {
+        let (pix_tx, _pix_rx) = unbounded();
+        let (_back_tx, back_rx) = unbounded();
+        let config = RiaConfig { extended_step: true, ..Default::default() };
+        let mut ria = Ria::with_config(pix_tx, back_rx, config);
+
+        // Portal 0, then step 640 (0x0280) high byte first
+        for (i, b) in [0x00, 0x02, 0x80].into_iter().enumerate() {
+            ria.process(&BusTransaction::write(i as u64, 0xFFEC, b));
+        }
+        ria.process(&BusTransaction::write(3, 0xFFEF, OP_STEP16));
+        assert_eq!(ria.regs[0x10], 0xEA, "op complete");
+        ria.process(&BusTransaction::write(4, 0xFFE4, 0x11));
+        ria.process(&BusTransaction::write(5, 0xFFE4, 0x22));
+        assert_eq!(ria.xram[0], 0x11);
+        assert_eq!(ria.xram[640], 0x22);
+        assert_eq!(ria.addr0(), 1280);
+
+        // Writing STEP0 drops back to the 8-bit step, including -128
+        ria.process(&BusTransaction::write(6, 0xFFE5, 0x80));
+        ria.process(&BusTransaction::write(7, 0xFFE4, 0x33));
+        assert_eq!(ria.addr0(), 1280 - 128);
+    }
+
+    #[test]
+    fn test_step0_two_wraps_across_xram_end() {
+        let (mut ria, _pix_rx, _back_tx) = make_ria();
+        // ADDR0 = 0xFFFE, STEP0 = 2
+        ria.process(&BusTransaction::write(0, 0xFFE6, 0xFE));
+        ria.process(&BusTransaction::write(1, 0xFFE7, 0xFF));
+        ria.process(&BusTransaction::write(2, 0xFFE5, 2));
+        for (i, data) in [0x11, 0x22, 0x33].into_iter().enumerate() {
+            ria.process(&BusTransaction::write(3 + i as u64, 0xFFE4, data));
+        }
+        assert_eq!(ria.xram[0xFFFE], 0x11);
+        assert_eq!(ria.xram[0x0000], 0x22);
+        assert_eq!(ria.xram[0x0002], 0x33);
+        assert_eq!(ria.xram[0xFFFF], 0, "odd bytes skipped");
+        assert_eq!(ria.addr0(), 0x0004);
+
+        // Reads advance the same way: 0x0001 -> 0xFFFF -> 0xFFFD
+        ria.xram[0x0001] = 0xA1;
+        ria.xram[0xFFFF] = 0xB2;
+        ria.process(&BusTransaction::write(6, 0xFFE6, 0x01));
+        ria.process(&BusTransaction::write(7, 0xFFE7, 0x00));
+        ria.process(&BusTransaction::write(8, 0xFFE5, (-2i8) as u8));
+        assert_eq!(ria.process(&BusTransaction::read(9, 0xFFE4, 0)), 0xA1);
+        assert_eq!(ria.addr0(), 0xFFFF);
+        assert_eq!(ria.process(&BusTransaction::read(10, 0xFFE4, 0)), 0xB2);
+        assert_eq!(ria.addr0(), 0xFFFD);
+    }
+
+    #[test]
+    fn test_step0_minus_two_wraps_below_zero() {
+        let (mut ria, _pix_rx, _back_tx) = make_ria();
+        // ADDR0 = 0x0002, STEP0 = -2
+        ria.process(&BusTransaction::write(0, 0xFFE6, 0x02));
+        ria.process(&BusTransaction::write(1, 0xFFE7, 0x00));
+        ria.process(&BusTransaction::write(2, 0xFFE5, (-2i8) as u8));
+        for (i, data) in [0x11, 0x22, 0x33].into_iter().enumerate() {
+            ria.process(&BusTransaction::write(3 + i as u64, 0xFFE4, data));
+        }
+        assert_eq!(ria.xram[0x0002], 0x11);
+        assert_eq!(ria.xram[0x0000], 0x22);
+        assert_eq!(ria.xram[0xFFFE], 0x33);
+        assert_eq!(ria.addr0(), 0xFFFC);
+    }
+
+    #[test]
+    fn test_watchdog_stops_trace_without_exit() {
+        let (pix_tx, _pix_rx) = unbounded();
+        let (_back_tx, back_rx) = unbounded();
+        let config = RiaConfig { max_cycles: Some(99), ..Default::default() };
+        let mut ria = Ria::with_config(pix_tx, back_rx, config);
+
+        // XRAM writes through portal 0 at cycles 0..1000, no exit op
+        let trace: Vec<_> = (0..1000).map(|i| BusTransaction::write(i, 0xFFE4, 0x55)).collect();
+        ria.process_all(&trace);
+        assert!(!ria.running);
+        assert!(ria.watchdog_fired);
+        assert_eq!(ria.addr0(), 100, "cycles 0..=99 processed");
+        assert_eq!(ria.xram[100], 0);
+    }
+
+    #[test]
+    fn test_extended_step_off_by_default() {
+        let (mut ria, _pix_rx, _back_tx) = make_ria();
+        for (i, b) in [0x00, 0x02, 0x80].into_iter().enumerate() {
+            ria.process(&BusTransaction::write(i as u64, 0xFFEC, b));
+        }
+        ria.process(&BusTransaction::write(3, 0xFFEF, OP_STEP16));
+        assert_eq!(u16::from_le_bytes([ria.regs[0x0D], ria.regs[0x0E]]), errno::ENOSYS);
+        ria.process(&BusTransaction::write(4, 0xFFE4, 0x11));
+        assert_eq!(ria.addr0(), 1, "hardware step of 1");
+    }
+
+    #[test]
+    fn test_trace_log_descriptions() {
+        use std::sync::{Arc, Mutex};
+
+        let (mut ria, _pix_rx, _back_tx) = make_ria();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let sink = log.clone();
+        ria.set_trace_log(Some(Box::new(move |txn: &BusTransaction, desc: &str| {
+            sink.lock().unwrap().push(format!("{:04X}: {desc}", txn.addr));
+        })));
+
+        ria.process(&BusTransaction::write(0, 0xFFE6, 0x00)); // ADDR0 low: not described
+        ria.process(&BusTransaction::write(1, 0xFFE7, 0x01)); // ADDR0 high
+        ria.process(&BusTransaction::write(2, 0xFFE4, 0x42));
+        for b in [1u8, 0, 1, 3, 0] {
+            ria.process(&BusTransaction::write(3, 0xFFEC, b));
+        }
+        ria.process(&BusTransaction::write(4, 0xFFEF, 0x01));
+        ria.process(&BusTransaction::write(5, 0xFFEF, 0xFF));
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "FFE4: XRAM[0x0100] <- 0x42, ADDR0 now 0x0101",
+                "FFEC: XSTACK push 0x01, depth 1",
+                "FFEC: XSTACK push 0x00, depth 2",
+                "FFEC: XSTACK push 0x01, depth 3",
+                "FFEC: XSTACK push 0x03, depth 4",
+                "FFEC: XSTACK push 0x00, depth 5",
+                "FFEF: OP xreg device=1 ch=0 addr=0x01 count=1",
+                "FFEF: OP exit",
+            ]
+        );
+
+        // Removing the hook stops logging
+        ria.set_trace_log(None);
+        ria.process(&BusTransaction::write(6, 0xFFE4, 0x43));
+        assert_eq!(log.lock().unwrap().len(), 8);
+    }
+
+    #[test]
+    fn test_process_all_returns_reads() {
+        let (mut ria, _pix_rx, _back_tx) = make_ria();
+        let trace = [
+            BusTransaction::write(0, 0xFFE6, 0x00), // ADDR0 = $0200
+            BusTransaction::write(1, 0xFFE7, 0x02),
+            BusTransaction::write(2, 0xFFE4, 0xA5),
+            BusTransaction::read(3, 0xFFE6, 0),     // ADDR0 low, advanced past the write
+            BusTransaction::read(4, 0x1234, 0x77),  // outside the RIA: bus data passes through
+            BusTransaction::write(5, 0xFFEF, 0xFF), // exit
+            BusTransaction::read(6, 0xFFE6, 0),     // not processed
+        ];
+        let reads = ria.process_all(&trace);
+        assert_eq!(reads, vec![(3, 0x01), (4, 0x77)]);
+        assert!(!ria.running);
     }
 
     #[test]
@@ -514,6 +1147,18 @@ mod tests {
         assert_eq!(ria.addr0(), 0x000F);
     }
 
+    #[test]
+    fn test_xstack_view() {
+        let (mut ria, _, _) = make_ria();
+        assert!(ria.xstack_view().is_empty());
+        for (i, byte) in [0x01, 0x02, 0x03].into_iter().enumerate() {
+            ria.process(&BusTransaction::write(i as u64, 0xFFEC, byte));
+        }
+        // Stack grows down, so the last push comes first
+        assert_eq!(ria.xstack_view(), &[0x03, 0x02, 0x01]);
+        assert_eq!(ria.xstack_ptr, XSTACK_SIZE - 3, "viewing does not pop");
+    }
+
     #[test]
     fn test_xstack_push_pop() {
         let (mut ria, _, _) = make_ria();
@@ -542,6 +1187,27 @@ mod tests {
         assert_eq!(ria.regs[0x0C], 0);
     }
 
+    #[test]
+    fn test_xstack_overflow() {
+        let (mut ria, _pix_rx, _back_tx) = make_ria();
+        for i in 0..XSTACK_SIZE {
+            ria.process(&BusTransaction::write(0, 0xFFEC, i as u8));
+        }
+        assert!(!ria.xstack_overflow);
+
+        ria.process(&BusTransaction::write(0, 0xFFEC, 0xAA)); // byte 513
+        assert!(ria.xstack_overflow);
+        assert_eq!(u16::from_le_bytes([ria.regs[0x0D], ria.regs[0x0E]]), errno::EOVERFLOW);
+        // First 512 pushes intact, last-pushed on top
+        for i in 0..XSTACK_SIZE {
+            assert_eq!(ria.xstack[XSTACK_SIZE - 1 - i], i as u8);
+        }
+        assert_eq!(ria.xstack_ptr, 0);
+
+        ria.process(&BusTransaction::write(0, 0xFFEF, 0x00)); // zxstack
+        assert!(!ria.xstack_overflow);
+    }
+
     #[test]
     fn test_op_zxstack() {
         let (mut ria, _, _) = make_ria();
@@ -554,6 +1220,29 @@ mod tests {
         assert_eq!(ria.regs[0x0C], 0); // API_STACK = 0
     }
 
+    #[test]
+    fn test_unknown_op_sets_enosys() {
+        let (mut ria, _, _) = make_ria();
+        ria.process(&BusTransaction::write(1, 0xFFEF, 0x7E));
+        // Read ERRNO back over the bus, as a 6502 program would
+        let lo = ria.process(&BusTransaction::read(2, 0xFFED, 0));
+        let hi = ria.process(&BusTransaction::read(3, 0xFFEE, 0));
+        assert_eq!(u16::from_le_bytes([lo, hi]), errno::ENOSYS);
+        assert_eq!((ria.regs[0x14], ria.regs[0x16]), (0xFF, 0xFF), "AX = -1");
+    }
+
+    #[test]
+    fn test_malformed_xreg_sets_einval() {
+        let (mut ria, pix_rx, _) = make_ria();
+        // Header only, no data values
+        for (cycle, byte) in [1u8, 0, 1].into_iter().enumerate() {
+            ria.process(&BusTransaction::write(cycle as u64, 0xFFEC, byte));
+        }
+        ria.process(&BusTransaction::write(3, 0xFFEF, 0x01));
+        assert_eq!(u16::from_le_bytes([ria.regs[0x0D], ria.regs[0x0E]]), errno::EINVAL);
+        assert!(pix_rx.try_iter().all(|e| !matches!(e, PixEvent::Reg(_))));
+    }
+
     #[test]
     fn test_op_exit() {
         let (mut ria, _, _) = make_ria();
@@ -561,6 +1250,18 @@ mod tests {
         assert!(!ria.running);
     }
 
+    #[test]
+    fn test_vsync_read_follows_backchannel() {
+        let (mut ria, _pix_rx, back_tx) = make_ria();
+        let vsync = |ria: &mut Ria| ria.process(&BusTransaction::read(0, 0xFFE3, 0));
+        for frame in 1..=2 {
+            back_tx.send(Backchannel::Vsync(crate::pix::encode_vsync(true, frame))).unwrap();
+            ria.poll_backchannel();
+            assert_eq!(vsync(&mut ria), 0x80 | frame);
+            assert_eq!(vsync(&mut ria), 0x80 | frame, "reading does not change it");
+        }
+    }
+
     #[test]
     fn test_irq_enable_and_ack() {
         let (mut ria, _, back_tx) = make_ria();
@@ -579,6 +1280,186 @@ mod tests {
         assert!(ria.irq_pin); // cleared
     }
 
+    #[test]
+    fn test_return_stub_reads() {
+        let (mut ria, _pix_rx, _back_tx) = make_ria();
+        ria.api_return_ax(0x1234);
+        let read_stub = |ria: &mut Ria| -> Vec<u8> {
+            (0xFFF0..=0xFFF7).map(|addr| ria.process(&BusTransaction::read(0, addr, 0))).collect()
+        };
+        // NOP; BRA +0; LDA #$34; LDX #$12; RTS
+        let released = vec![0xEA, 0x80, 0x00, 0xA9, 0x34, 0xA2, 0x12, 0x60];
+        assert_eq!(read_stub(&mut ria), released);
+        assert_eq!(read_stub(&mut ria), released, "reading has no side effects");
+
+        // A and X are writable
+        ria.process(&BusTransaction::write(1, 0xFFF4, 0x78));
+        ria.process(&BusTransaction::write(1, 0xFFF6, 0x56));
+        assert_eq!(read_stub(&mut ria), vec![0xEA, 0x80, 0x00, 0xA9, 0x78, 0xA2, 0x56, 0x60]);
+    }
+
+    #[test]
+    fn test_force_vsync_asserts_irq_only_when_enabled() {
+        let (mut ria, _, _back_tx) = make_ria();
+        ria.force_vsync(0x81);
+        assert!(ria.irq_pin, "IRQ disabled: pin stays high");
+        assert_eq!(ria.process(&BusTransaction::read(0, 0xFFE3, 0)), 0x81);
+
+        ria.process(&BusTransaction::write(1, 0xFFF0, 0x01));
+        ria.force_vsync(0x82);
+        assert!(!ria.irq_pin, "IRQ enabled: pin drops");
+        assert_eq!(ria.process(&BusTransaction::read(2, 0xFFE3, 0)), 0x82);
+
+        ria.process(&BusTransaction::read(3, 0xFFF0, 0));
+        assert!(ria.irq_pin, "acknowledged");
+    }
+
+    #[test]
+    fn test_ack_payload_keeps_ax_of_last_op() {
+        let (mut ria, _pix_rx, back_tx) = make_ria();
+        let ax = |ria: &Ria| (ria.regs[0x14], ria.regs[0x16]);
+        ria.process(&BusTransaction::write(0, 0xFFEF, 0x04)); // lrand
+        let lrand = ax(&ria);
+
+        // A MODE ack arriving at a later frame boundary only latches its payload
+        back_tx.send(Backchannel::AckPayload(2)).unwrap();
+        ria.poll_backchannel();
+        assert_eq!(ria.ack_payload, Some(2));
+        assert_eq!(ax(&ria), lrand, "lrand result survives the ack");
+
+        ria.process(&BusTransaction::write(1, 0xFFEF, OP_ACK_PAYLOAD));
+        assert_eq!(ax(&ria), (2, 0));
+
+        back_tx.send(Backchannel::Nak).unwrap();
+        ria.process(&BusTransaction::write(2, 0xFFEF, OP_ACK_PAYLOAD));
+        assert_eq!(ria.ack_payload, None);
+        assert_eq!(ax(&ria), (0xFF, 0xFF), "no payload: -1, ERRNO EINVAL");
+    }
+
+    #[test]
+    fn test_ack_nak_tracking() {
+        let (mut ria, pix_rx, _) = make_ria();
+        let (vga_back_tx, vga_back_rx) = unbounded();
+        ria.backchannel_rx = vga_back_rx;
+        let (_vga_pix_tx, vga_pix_rx) = unbounded();
+        let fb = std::sync::Arc::new(std::sync::Mutex::new(vec![0u8; 640 * 480 * 4]));
+        let mut vga = crate::vga::Vga::new(vga_pix_rx, vga_back_tx, fb);
+
+        // Run a trace through the RIA, hand its PIX events to the VGA, then poll
+        let mut exchange = |ria: &mut Ria, tb: TraceBuilder| {
+            for txn in &tb.trace {
+                ria.process(txn);
+            }
+            for event in pix_rx.try_iter() {
+                vga.handle_event(event);
+            }
+            ria.poll_backchannel();
+        };
+        assert_eq!(ria.last_ack(), None);
+
+        let mut tb = TraceBuilder::new();
+        tb.xreg_vga_canvas(1);
+        tb.xreg_vga_mode(&[3, 3, 0x0000, 0]);
+        exchange(&mut ria, tb);
+        assert_eq!(ria.last_ack(), Some(true), "valid Mode 3 is accepted");
+        assert_eq!((ria.ack_count(), ria.nak_count()), (2, 0));
+
+        let mut tb = TraceBuilder::new();
+        tb.xreg_vga_mode(&[9]);
+        exchange(&mut ria, tb);
+        assert_eq!(ria.last_ack(), Some(false), "unknown mode is rejected");
+        assert_eq!((ria.ack_count(), ria.nak_count()), (2, 1));
+    }
+
+    #[test]
+    fn test_present_frame_renders_without_frame_boundary() {
+        let (mut ria, pix_rx, _) = make_ria();
+        let (vga_back_tx, vga_back_rx) = unbounded();
+        ria.backchannel_rx = vga_back_rx;
+        let (_vga_pix_tx, vga_pix_rx) = unbounded();
+        let fb = std::sync::Arc::new(std::sync::Mutex::new(vec![0u8; 640 * 480 * 4]));
+        let mut vga = crate::vga::Vga::new(vga_pix_rx, vga_back_tx, fb.clone());
+
+        // 1x1 8bpp Mode 3 bitmap at 0x0100 on a 640x480 canvas
+        let mut tb = TraceBuilder::new();
+        tb.xram0_struct_set(0, vga_mode3_config_t::WIDTH_PX, &1i16.to_le_bytes());
+        tb.xram0_struct_set(0, vga_mode3_config_t::HEIGHT_PX, &1i16.to_le_bytes());
+        tb.xram0_struct_set(0, vga_mode3_config_t::XRAM_DATA_PTR, &0x0100u16.to_le_bytes());
+        tb.xram0_struct_set(0, vga_mode3_config_t::XRAM_PALETTE_PTR, &0xFFFFu16.to_le_bytes());
+        tb.xreg_vga_canvas(3);
+        tb.xreg_vga_mode(&[3, 3, 0x0000, 0]);
+        tb.xram0_write(0x0100, &[9]); // bright red
+        for txn in &tb.trace {
+            ria.process(txn);
+        }
+        let boundary = ria.next_frame_cycle;
+        assert!(ria.cycle_count < boundary, "still inside the first frame");
+
+        ria.present_frame();
+        for event in pix_rx.try_iter() {
+            vga.handle_event(event);
+        }
+        assert_eq!(fb.lock().unwrap()[0..4], crate::vga::palette::PALETTE_256[9].to_be_bytes());
+        assert_eq!(ria.next_frame_cycle, boundary, "frame timing untouched");
+    }
+
+    /// Run the lrand op `n` times and collect SREG:AX results.
+    fn lrand_sequence(ria: &mut Ria, n: usize) -> Vec<u32> {
+        (0..n)
+            .map(|_| {
+                ria.process(&BusTransaction::write(0, 0xFFEF, 0x04));
+                u32::from_le_bytes([ria.regs[0x14], ria.regs[0x16], ria.regs[0x18], ria.regs[0x19]])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lrand_seeded_sequence() {
+        let (pix_tx, _pix_rx) = unbounded();
+        let (_back_tx, back_rx) = unbounded();
+        let mut a = Ria::with_seed(pix_tx.clone(), back_rx.clone(), 1234);
+        let mut b = Ria::with_seed(pix_tx.clone(), back_rx.clone(), 1234);
+        let mut c = Ria::with_seed(pix_tx, back_rx, 5678);
+
+        let seq_a = lrand_sequence(&mut a, 8);
+        assert_eq!(seq_a, lrand_sequence(&mut b, 8));
+        assert_ne!(seq_a, lrand_sequence(&mut c, 8));
+        assert!(seq_a.iter().all(|&v| v <= 0x7FFF_FFFF));
+        assert!(seq_a.windows(2).any(|w| w[0] != w[1]));
+    }
+
+    #[test]
+    fn test_real_time_pacing() {
+        let (pix_tx, pix_rx) = unbounded();
+        let (_back_tx, back_rx) = unbounded();
+        let config = RiaConfig { real_time: true, ..RiaConfig::default() };
+        let mut ria = Ria::with_config(pix_tx, back_rx, config);
+        let cycles_per_frame = config.cycles_per_frame();
+
+        // 6 frames at 60Hz should take ~100ms of wall time
+        let start = Instant::now();
+        for frame in 0..=6u64 {
+            ria.process(&BusTransaction::write(frame * (cycles_per_frame + 1), 0x1000, 0));
+        }
+        let elapsed = start.elapsed();
+
+        assert_eq!(pix_rx.try_iter().filter(|e| *e == PixEvent::FrameSync).count(), 6);
+        // Lower bound only: a loaded machine can oversleep arbitrarily
+        assert!(elapsed >= Duration::from_millis(95), "too fast: {elapsed:?}");
+    }
+
+    #[test]
+    fn test_unthrottled_by_default() {
+        let (mut ria, pix_rx, _) = make_ria();
+        for frame in 0..=6u64 {
+            ria.process(&BusTransaction::write(frame * 200_000, 0x1000, 0));
+        }
+        assert_eq!(pix_rx.try_iter().filter(|e| *e == PixEvent::FrameSync).count(), 6);
+        // Real-time pacing sets its wall-clock epoch before it ever sleeps
+        assert!(!ria.real_time);
+        assert_eq!(ria.epoch, None);
+    }
+
     #[test]
     fn test_vsync_preserved_across_reset() {
         let (mut ria, _, _) = make_ria();
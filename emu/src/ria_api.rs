@@ -1,4 +1,9 @@
+use crossbeam_channel::unbounded;
 use crate::bus::BusTransaction;
+use crate::pix::{pix_pack, pix_pack_xram, PixEvent};
+use crate::ria::{Ria, RiaConfig, FRAME_BARRIER_ADDR};
+use crate::vga::mode1::Mode1Config;
+use crate::vga::mode3::Mode3Config;
 
 /// Field offsets for `vga_mode3_config_t` from `cc65/include/rp6502.h`.
 /// Mirrors the C struct layout for use with `xram0_struct_set`.
@@ -28,14 +33,25 @@ pub mod vga_mode1_config_t {
     pub const XRAM_FONT_PTR: u16 = 14;    // u16
 }
 
+/// Cycles between `$FFE3` reads in `wait_vsync`'s polling loop.
+const VSYNC_POLL_CYCLES: u64 = 1_000;
+
 pub struct TraceBuilder {
     pub trace: Vec<BusTransaction>,
     pub cycle: u64,
+    /// Frame length `wait_vsync` polls across, from the `RiaConfig` the
+    /// trace is meant for.
+    cycles_per_frame: u64,
 }
 
 impl TraceBuilder {
     pub fn new() -> Self {
-        Self { trace: Vec::new(), cycle: 0 }
+        Self::for_config(&RiaConfig::default())
+    }
+
+    /// Builder whose `wait_vsync` matches the frame timing of `config`.
+    pub fn for_config(config: &RiaConfig) -> Self {
+        Self { trace: Vec::new(), cycle: 0, cycles_per_frame: config.cycles_per_frame() }
     }
 
     /// Single bus write — mirrors `RIA.reg = val`.
@@ -51,17 +67,20 @@ impl TraceBuilder {
     }
 
     /// Set XRAM portal 0 step — mirrors `RIA.step0 = step`.
+    #[allow(dead_code)]
     pub fn set_step0(&mut self, step: i8) {
         self.write(0xFFE5, step as u8);
     }
 
     /// Set XRAM portal 1 address — mirrors `RIA.addr1 = addr`.
+    #[allow(dead_code)]
     pub fn set_addr1(&mut self, addr: u16) {
         self.write(0xFFEA, (addr & 0xFF) as u8);
         self.write(0xFFEB, (addr >> 8) as u8);
     }
 
     /// Set XRAM portal 1 step — mirrors `RIA.step1 = step`.
+    #[allow(dead_code)]
     pub fn set_step1(&mut self, step: i8) {
         self.write(0xFFE9, step as u8);
     }
@@ -95,6 +114,37 @@ impl TraceBuilder {
         self.cycle += n as u64 * 200_000;
     }
 
+    /// Mark a frame boundary: the RIA sends a FrameSync when it reaches this
+    /// write, so XRAM changes before and after land in separate frames.
+    pub fn frame_barrier(&mut self) {
+        self.write(FRAME_BARRIER_ADDR, 0);
+    }
+
+    /// Poll the VSYNC counter until `n` frame boundaries have passed — mirrors
+    /// `v = RIA.vsync; while (v == RIA.vsync);` repeated `n` times.
+    ///
+    /// Emits `$FFE3` reads every `VSYNC_POLL_CYCLES`, with a final read after
+    /// the last boundary (the poll that sees the counter change). Boundaries
+    /// follow the `RiaConfig` given to `for_config`, the default for `new`.
+    #[allow(dead_code)]
+    pub fn wait_vsync(&mut self, n: u32) {
+        if n == 0 {
+            return;
+        }
+        let frame = self.cycles_per_frame;
+        let next_boundary = (self.cycle / frame + 1) * frame;
+        let target = next_boundary + (n as u64 - 1) * frame;
+        loop {
+            let done = self.cycle >= target;
+            self.trace.push(BusTransaction::read(self.cycle, 0xFFE3, 0));
+            if done {
+                self.cycle += 1;
+                break;
+            }
+            self.cycle = (self.cycle + VSYNC_POLL_CYCLES).min(target);
+        }
+    }
+
     /// Send xreg operation — mirrors `xreg(device, channel, addr, ...)`.
     /// Pushes header and values to xstack, then triggers OP_XREG (0x01).
     pub fn xreg(&mut self, device: u8, channel: u8, addr: u8, values: &[u16]) {
@@ -117,15 +167,129 @@ impl TraceBuilder {
     pub fn xreg_vga_mode(&mut self, values: &[u16]) {
         self.xreg(1, 0, 1, values);
     }
+
+    /// Write every field of a `vga_mode3_config_t` at `config_ptr`, in struct order.
+    pub fn write_mode3_config(&mut self, config_ptr: u16, cfg: &Mode3Config) {
+        use vga_mode3_config_t::*;
+        self.xram0_struct_set(config_ptr, X_WRAP, &[cfg.x_wrap as u8]);
+        self.xram0_struct_set(config_ptr, Y_WRAP, &[cfg.y_wrap as u8]);
+        self.xram0_struct_set(config_ptr, X_POS_PX, &cfg.x_pos_px.to_le_bytes());
+        self.xram0_struct_set(config_ptr, Y_POS_PX, &cfg.y_pos_px.to_le_bytes());
+        self.xram0_struct_set(config_ptr, WIDTH_PX, &cfg.width_px.to_le_bytes());
+        self.xram0_struct_set(config_ptr, HEIGHT_PX, &cfg.height_px.to_le_bytes());
+        self.xram0_struct_set(config_ptr, XRAM_DATA_PTR, &cfg.xram_data_ptr.to_le_bytes());
+        self.xram0_struct_set(config_ptr, XRAM_PALETTE_PTR, &cfg.xram_palette_ptr.to_le_bytes());
+    }
+
+    /// Write every field of a `vga_mode1_config_t` at `config_ptr`, in struct order.
+    /// `advance_table` is not part of the struct and is not written.
+    pub fn write_mode1_config(&mut self, config_ptr: u16, cfg: &Mode1Config) {
+        use vga_mode1_config_t::*;
+        self.xram0_struct_set(config_ptr, X_WRAP, &[cfg.x_wrap as u8]);
+        self.xram0_struct_set(config_ptr, Y_WRAP, &[cfg.y_wrap as u8]);
+        self.xram0_struct_set(config_ptr, X_POS_PX, &cfg.x_pos_px.to_le_bytes());
+        self.xram0_struct_set(config_ptr, Y_POS_PX, &cfg.y_pos_px.to_le_bytes());
+        self.xram0_struct_set(config_ptr, WIDTH_CHARS, &cfg.width_chars.to_le_bytes());
+        self.xram0_struct_set(config_ptr, HEIGHT_CHARS, &cfg.height_chars.to_le_bytes());
+        self.xram0_struct_set(config_ptr, XRAM_DATA_PTR, &cfg.xram_data_ptr.to_le_bytes());
+        self.xram0_struct_set(config_ptr, XRAM_PALETTE_PTR, &cfg.xram_palette_ptr.to_le_bytes());
+        self.xram0_struct_set(config_ptr, XRAM_FONT_PTR, &cfg.xram_font_ptr.to_le_bytes());
+    }
+
+    /// Write a Mode 3 config struct and program it into `plane_idx` —
+    /// `write_mode3_config` followed by `xreg_vga_mode(3, attr, ...)`.
+    #[allow(dead_code)]
+    pub fn program_mode3(
+        &mut self,
+        config_ptr: u16,
+        cfg: &Mode3Config,
+        attr: u16,
+        plane_idx: u16,
+        scanline_begin: u16,
+        scanline_end: u16,
+    ) {
+        self.write_mode3_config(config_ptr, cfg);
+        self.xreg_vga_mode(&[3, attr, config_ptr, plane_idx, scanline_begin, scanline_end]);
+    }
+
+    /// Write a Mode 1 config struct and program it into `plane_idx` —
+    /// `write_mode1_config` followed by `xreg_vga_mode(1, attr, ...)`. A
+    /// `cfg.advance_table` is sent as MODE xreg 7.
+    #[allow(dead_code)]
+    pub fn program_mode1(
+        &mut self,
+        config_ptr: u16,
+        cfg: &Mode1Config,
+        attr: u16,
+        plane_idx: u16,
+        scanline_begin: u16,
+        scanline_end: u16,
+    ) {
+        self.write_mode1_config(config_ptr, cfg);
+        let mut values = vec![1, attr, config_ptr, plane_idx, scanline_begin, scanline_end];
+        values.extend(cfg.advance_table);
+        self.xreg_vga_mode(&values);
+    }
+
+    /// Raw 32-bit PIX wire stream the accumulated trace would put on the PIX bus.
+    ///
+    /// Replays the trace through a `Ria` so XRAM portal writes and xreg ops are
+    /// simulated exactly, then packs each emitted event with `pix_pack_xram` /
+    /// `pix_pack`. FrameSync is an emulator timing event, not a PIX message, and
+    /// is omitted. Replay stops at the exit op.
+    #[allow(dead_code)]
+    pub fn pix_stream(&self) -> Vec<u32> {
+        let (pix_tx, pix_rx) = unbounded();
+        // Keep the backchannel sender alive: a disconnect would stop the RIA.
+        let (_back_tx, back_rx) = unbounded();
+        let mut ria = Ria::new(pix_tx, back_rx);
+        for txn in &self.trace {
+            if !ria.running {
+                break;
+            }
+            ria.process(txn);
+        }
+        drop(ria);
+
+        pix_rx
+            .iter()
+            .filter_map(|event| match event {
+                PixEvent::Xram(w) => Some(pix_pack_xram(w.addr, w.data)),
+                PixEvent::Reg(r) => Some(pix_pack(r.device, r.channel, r.register, r.value)),
+                PixEvent::FrameSync | PixEvent::Shutdown | PixEvent::Reset => None,
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_frame_barrier_forces_frame_sync() {
+        let mut tb = TraceBuilder::new();
+        tb.xram0_write(0x0000, &[0x11]);
+        tb.frame_barrier();
+        tb.xram0_write(0x0000, &[0x22]);
+        assert_eq!(tb.trace[3], BusTransaction::write(3, FRAME_BARRIER_ADDR, 0));
+
+        let (pix_tx, pix_rx) = unbounded();
+        let (_back_tx, back_rx) = unbounded();
+        let mut ria = Ria::new(pix_tx, back_rx);
+        for txn in &tb.trace {
+            ria.process(txn);
+        }
+        drop(ria);
+        let events: Vec<PixEvent> = pix_rx.iter().collect();
+        let sync = events.iter().position(|e| *e == PixEvent::FrameSync).expect("FrameSync");
+        assert!(matches!(events[sync - 1], PixEvent::Xram(w) if w.data == 0x11));
+        assert!(matches!(events[sync + 1], PixEvent::Xram(w) if w.data == 0x22));
+    }
+
     #[test]
     fn test_write_appends_transaction() {
-        let mut tb = TraceBuilder { trace: Vec::new(), cycle: 0 };
+        let mut tb = TraceBuilder::new();
         tb.write(0xFFE4, 0x42);
         assert_eq!(tb.trace.len(), 1);
         assert_eq!(tb.trace[0], BusTransaction::write(0, 0xFFE4, 0x42));
@@ -250,6 +414,75 @@ mod tests {
         assert_eq!(tb.trace[2].data, 0);  // start_addr = 0 (CANVAS)
     }
 
+    #[test]
+    fn test_wait_vsync_polls_past_frame_boundary() {
+        let mut tb = TraceBuilder::new();
+        tb.wait_vsync(2);
+        let reads: Vec<_> = tb.trace.iter().filter(|t| t.rw && t.addr == 0xFFE3).collect();
+        assert_eq!(reads.len(), tb.trace.len(), "only VSYNC reads are emitted");
+        assert!(reads.len() > 2);
+        assert!(reads.windows(2).all(|w| w[0].cycle < w[1].cycle));
+        assert_eq!(reads.last().unwrap().cycle, 2 * RiaConfig::default().cycles_per_frame());
+
+        // Replaying crosses exactly two frame boundaries
+        let (pix_tx, pix_rx) = unbounded();
+        let (_back_tx, back_rx) = unbounded();
+        let mut ria = Ria::new(pix_tx, back_rx);
+        for txn in &tb.trace {
+            ria.process(txn);
+        }
+        let syncs = pix_rx.try_iter().filter(|e| *e == PixEvent::FrameSync).count();
+        assert_eq!(syncs, 2);
+    }
+
+    #[test]
+    fn test_wait_vsync_follows_config_refresh_rate() {
+        let config = RiaConfig { refresh_hz: 30, ..RiaConfig::default() };
+        let mut tb = TraceBuilder::for_config(&config);
+        tb.wait_vsync(1);
+        assert_eq!(tb.trace.last().unwrap().cycle, config.cycles_per_frame());
+
+        let (pix_tx, pix_rx) = unbounded();
+        let (_back_tx, back_rx) = unbounded();
+        let mut ria = Ria::with_config(pix_tx, back_rx, config);
+        for txn in &tb.trace {
+            ria.process(txn);
+        }
+        assert_eq!(pix_rx.try_iter().filter(|e| *e == PixEvent::FrameSync).count(), 1);
+    }
+
+    #[test]
+    fn test_pix_stream_canvas_and_mode() {
+        use crate::pix::pix_unpack;
+
+        let mut tb = TraceBuilder::new();
+        tb.xram0_write(0x0010, &[0xAB]);
+        tb.xreg_vga_canvas(1);
+        tb.xreg_vga_mode(&[3, 2, 0xFF00, 0, 0, 0]);
+        let stream = tb.pix_stream();
+
+        // 1 XRAM write + 1 canvas reg + 6 mode regs
+        assert_eq!(stream.len(), 8);
+        // XRAM convention: device 0, channel 0, register = data, value = addr
+        assert_eq!(pix_unpack(stream[0]), Some((0, 0, 0xAB, 0x0010)));
+        assert_eq!(pix_unpack(stream[1]), Some((1, 0, 0, 1)));
+        // Mode registers arrive highest first, MODE (reg 1) last
+        let regs: Vec<_> = stream[2..].iter().map(|&w| pix_unpack(w).unwrap()).collect();
+        assert_eq!(regs, vec![
+            (1, 0, 6, 0),
+            (1, 0, 5, 0),
+            (1, 0, 4, 0),
+            (1, 0, 3, 0xFF00),
+            (1, 0, 2, 2),
+            (1, 0, 1, 3),
+        ]);
+        // Every word round-trips through unpack/pack
+        for &w in &stream {
+            let (dev, ch, reg, val) = pix_unpack(w).unwrap();
+            assert_eq!(pix_pack(dev, ch, reg, val), w);
+        }
+    }
+
     #[test]
     fn test_xreg_vga_mode() {
         let mut tb = TraceBuilder::new();
@@ -257,4 +490,82 @@ mod tests {
         assert_eq!(tb.trace.len(), 16); // 3 header + 6*2 values + 1 trigger
         assert_eq!(tb.trace[2].data, 1);  // start_addr = 1 (MODE)
     }
+
+    /// The `vga_mode3_config_t` written by `pico-examples/src/mandelbrot.c`.
+    fn mandelbrot_config() -> Mode3Config {
+        Mode3Config {
+            x_wrap: false,
+            y_wrap: false,
+            x_pos_px: 0,
+            y_pos_px: 0,
+            width_px: 320,
+            height_px: 240,
+            xram_data_ptr: 0x0000,
+            xram_palette_ptr: 0xFFFF,
+        }
+    }
+
+    #[test]
+    fn test_program_mode3_matches_manual_sequence() {
+        let config_ptr = 0xFF00;
+        let mut manual = TraceBuilder::new();
+        {
+            use vga_mode3_config_t::*;
+            manual.xram0_struct_set(config_ptr, X_WRAP, &[0]);
+            manual.xram0_struct_set(config_ptr, Y_WRAP, &[0]);
+            manual.xram0_struct_set(config_ptr, X_POS_PX, &0i16.to_le_bytes());
+            manual.xram0_struct_set(config_ptr, Y_POS_PX, &0i16.to_le_bytes());
+            manual.xram0_struct_set(config_ptr, WIDTH_PX, &320i16.to_le_bytes());
+            manual.xram0_struct_set(config_ptr, HEIGHT_PX, &240i16.to_le_bytes());
+            manual.xram0_struct_set(config_ptr, XRAM_DATA_PTR, &0x0000u16.to_le_bytes());
+            manual.xram0_struct_set(config_ptr, XRAM_PALETTE_PTR, &0xFFFFu16.to_le_bytes());
+        }
+        manual.xreg_vga_mode(&[3, 10, config_ptr, 0, 0, 0]);
+
+        let mut tb = TraceBuilder::new();
+        tb.program_mode3(config_ptr, &mandelbrot_config(), 10, 0, 0, 0);
+        assert_eq!(tb.trace, manual.trace);
+        assert_eq!(tb.cycle, manual.cycle);
+    }
+
+    #[test]
+    fn test_program_mode1_round_trips_config() {
+        let cfg = Mode1Config {
+            x_wrap: true,
+            y_wrap: false,
+            x_pos_px: -8,
+            y_pos_px: 4,
+            width_chars: 40,
+            height_chars: 15,
+            xram_data_ptr: 0x0100,
+            xram_palette_ptr: 0xFFFF,
+            xram_font_ptr: 0x8000,
+            advance_table: Some(0x9000),
+        };
+        let mut tb = TraceBuilder::new();
+        tb.program_mode1(0x0020, &cfg, 8, 1, 0, 0);
+
+        let (pix_tx, pix_rx) = unbounded();
+        let (_back_tx, back_rx) = unbounded();
+        let mut ria = Ria::new(pix_tx, back_rx);
+        for txn in &tb.trace {
+            ria.process(txn);
+        }
+        let read = Mode1Config::from_xram(&ria.xram, 0x0020);
+        assert_eq!((read.x_wrap, read.y_wrap, read.x_pos_px, read.y_pos_px), (true, false, -8, 4));
+        assert_eq!((read.width_chars, read.height_chars), (40, 15));
+        assert_eq!((read.xram_data_ptr, read.xram_palette_ptr, read.xram_font_ptr), (0x0100, 0xFFFF, 0x8000));
+        drop(ria);
+
+        let modes: Vec<crate::pix::PixRegWrite> = pix_rx
+            .iter()
+            .filter_map(|e| match e {
+                PixEvent::Reg(r) => Some(r),
+                _ => None,
+            })
+            .collect();
+        // xregs arrive highest register first; xreg 7 carries the advance table
+        assert_eq!(modes.first().map(|r| (r.register, r.value)), Some((7, 0x9000)));
+        assert_eq!(modes.last().map(|r| (r.register, r.value)), Some((1, 1)));
+    }
 }
@@ -1,9 +1,84 @@
+use std::fmt;
 use std::fs;
-use std::io::BufWriter;
+use std::io::{BufWriter, Write};
 use std::path::Path;
 
+/// Errors returned by `save_png`, `save_png_indexed`, `write_raw_frame` and `load_png`.
+#[derive(Debug)]
+pub enum ScreenshotError {
+    /// Creating or writing the output file (or stream) failed.
+    Io(std::io::Error),
+    /// The PNG encoder rejected the image.
+    Encode(png::EncodingError),
+    /// The file is not a PNG the decoder can read.
+    Decode(png::DecodingError),
+    /// `rgba_data` is not `width * height * 4` bytes long (or a canvas is
+    /// not `width * height` pixels).
+    BadDimensions { expected: usize, got: usize },
+    /// An indexed PNG palette must have 1 to 256 entries.
+    BadPalette { len: usize },
+    /// Strict indexed export found a pixel that is not a palette color.
+    NotInPalette { x: u32, y: u32, rgba: u32 },
+}
+
+impl fmt::Display for ScreenshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScreenshotError::Io(e) => write!(f, "I/O error: {}", e),
+            ScreenshotError::Encode(e) => write!(f, "PNG encoding error: {}", e),
+            ScreenshotError::Decode(e) => write!(f, "PNG decoding error: {}", e),
+            ScreenshotError::BadDimensions { expected, got } => {
+                write!(f, "RGBA buffer is {} bytes, expected {}", got, expected)
+            }
+            ScreenshotError::BadPalette { len } => {
+                write!(f, "palette has {} entries, expected 1 to 256", len)
+            }
+            ScreenshotError::NotInPalette { x, y, rgba } => {
+                write!(f, "pixel ({}, {}) color 0x{:08X} is not in the palette", x, y, rgba)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScreenshotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ScreenshotError::Io(e) => Some(e),
+            ScreenshotError::Encode(e) => Some(e),
+            ScreenshotError::Decode(e) => Some(e),
+            ScreenshotError::BadDimensions { .. }
+            | ScreenshotError::BadPalette { .. }
+            | ScreenshotError::NotInPalette { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ScreenshotError {
+    fn from(e: std::io::Error) -> Self {
+        ScreenshotError::Io(e)
+    }
+}
+
+impl From<png::EncodingError> for ScreenshotError {
+    fn from(e: png::EncodingError) -> Self {
+        ScreenshotError::Encode(e)
+    }
+}
+
+impl From<png::DecodingError> for ScreenshotError {
+    fn from(e: png::DecodingError) -> Self {
+        ScreenshotError::Decode(e)
+    }
+}
+
 /// Encode an RGBA framebuffer as a PNG file.
-pub fn save_png(path: &Path, rgba_data: &[u8], width: u32, height: u32) -> Result<(), Box<dyn std::error::Error>> {
+///
+/// `rgba_data` must hold exactly `width * height * 4` bytes.
+pub fn save_png(path: &Path, rgba_data: &[u8], width: u32, height: u32) -> Result<(), ScreenshotError> {
+    let expected = width as usize * height as usize * 4;
+    if rgba_data.len() != expected {
+        return Err(ScreenshotError::BadDimensions { expected, got: rgba_data.len() });
+    }
     let file = fs::File::create(path)?;
     let w = BufWriter::new(file);
     let mut encoder = png::Encoder::new(w, width, height);
@@ -14,6 +89,132 @@ pub fn save_png(path: &Path, rgba_data: &[u8], width: u32, height: u32) -> Resul
     Ok(())
 }
 
+/// Write one RGBA frame as raw bytes and flush, e.g. to a video encoder's
+/// stdin. Calling this once per frame streams the frames back to back.
+///
+/// `rgba_data` must hold exactly `width * height * 4` bytes.
+pub fn write_raw_frame(out: &mut dyn Write, rgba_data: &[u8], width: u32, height: u32) -> Result<(), ScreenshotError> {
+    let expected = width as usize * height as usize * 4;
+    if rgba_data.len() != expected {
+        return Err(ScreenshotError::BadDimensions { expected, got: rgba_data.len() });
+    }
+    out.write_all(rgba_data)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// What `save_png_indexed` does with a pixel that is not a palette color.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantize {
+    /// Fail with `ScreenshotError::NotInPalette`.
+    Strict,
+    /// Use the palette color nearest in RGBA space.
+    Nearest,
+}
+
+/// Index of the palette color closest to `rgba`, exact matches first.
+fn nearest_index(palette: &[u32], rgba: u32) -> usize {
+    let distance = |c: u32| -> u32 {
+        c.to_be_bytes()
+            .iter()
+            .zip(rgba.to_be_bytes())
+            .map(|(&a, b)| (a as i32 - b as i32).pow(2) as u32)
+            .sum()
+    };
+    (0..palette.len()).min_by_key(|&i| distance(palette[i])).unwrap_or(0)
+}
+
+/// Encode a canvas as an indexed-color PNG with `palette` as its PLTE chunk.
+///
+/// `canvas` holds `width * height` RGBA u32 pixels (R in bits 31:24), as in
+/// `Vga::canvas_snapshot`. Each pixel is stored as its palette index, at the
+/// smallest bit depth that fits the palette (2 colors: 1 bit, 16 colors:
+/// 4 bits). Palette alpha goes in a tRNS chunk unless every entry is opaque.
+#[allow(dead_code)]
+pub fn save_png_indexed(
+    path: &Path,
+    canvas: &[u32],
+    width: u32,
+    height: u32,
+    palette: &[u32],
+    quantize: Quantize,
+) -> Result<(), ScreenshotError> {
+    let expected = width as usize * height as usize;
+    if canvas.len() != expected {
+        return Err(ScreenshotError::BadDimensions { expected, got: canvas.len() });
+    }
+    if palette.is_empty() || palette.len() > 256 {
+        return Err(ScreenshotError::BadPalette { len: palette.len() });
+    }
+    let depth: u8 = match palette.len() {
+        1..=2 => 1,
+        3..=4 => 2,
+        5..=16 => 4,
+        _ => 8,
+    };
+
+    // Rows are packed MSB-first and padded to a whole byte
+    let row_bytes = (width as usize * depth as usize).div_ceil(8);
+    let mut data = vec![0u8; row_bytes * height as usize];
+    for (i, &rgba) in canvas.iter().enumerate() {
+        let index = match palette.iter().position(|&c| c == rgba) {
+            Some(index) => index,
+            None if quantize == Quantize::Nearest => nearest_index(palette, rgba),
+            None => {
+                let (x, y) = (i as u32 % width, i as u32 / width);
+                return Err(ScreenshotError::NotInPalette { x, y, rgba });
+            }
+        };
+        let (x, y) = (i % width as usize, i / width as usize);
+        let bit = x * depth as usize;
+        data[y * row_bytes + bit / 8] |= (index as u8) << (8 - depth as usize - bit % 8);
+    }
+
+    let file = fs::File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(match depth {
+        1 => png::BitDepth::One,
+        2 => png::BitDepth::Two,
+        4 => png::BitDepth::Four,
+        _ => png::BitDepth::Eight,
+    });
+    let plte: Vec<u8> = palette.iter().flat_map(|c| {
+        let [r, g, b, _] = c.to_be_bytes();
+        [r, g, b]
+    }).collect();
+    encoder.set_palette(plte);
+    if palette.iter().any(|&c| c & 0xFF != 0xFF) {
+        encoder.set_trns(palette.iter().map(|&c| c as u8).collect::<Vec<u8>>());
+    }
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    Ok(())
+}
+
+/// Decode a PNG file to 8-bit RGBA, returning (width, height, pixels).
+///
+/// Grayscale, RGB and indexed images are expanded; pixels without an alpha
+/// channel are opaque.
+pub fn load_png(path: &Path) -> Result<(u32, u32, Vec<u8>), ScreenshotError> {
+    let mut decoder = png::Decoder::new(fs::File::open(path)?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    buf.truncate(info.buffer_size());
+
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::Rgb => buf.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 0xFF]).collect(),
+        png::ColorType::GrayscaleAlpha => buf.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        // Indexed is expanded to RGB(A) by the transformation
+        png::ColorType::Grayscale | png::ColorType::Indexed => buf.iter().flat_map(|&v| [v, v, v, 0xFF]).collect(),
+    };
+    Ok((info.width, info.height, rgba))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -40,4 +241,109 @@ mod tests {
 
         fs::remove_file(&path).ok();
     }
+
+    #[test]
+    fn test_save_png_rejects_short_buffer() {
+        let path = std::env::temp_dir().join("rp6502_test_short_buffer.png");
+        let data = [0u8; 12]; // 2x2 needs 16 bytes
+
+        let err = save_png(&path, &data, 2, 2).unwrap_err();
+        assert!(matches!(err, ScreenshotError::BadDimensions { expected: 16, got: 12 }));
+        assert!(!path.exists(), "no file should be created");
+    }
+
+    #[test]
+    fn test_load_png_round_trips_save_png() {
+        let path = std::env::temp_dir().join("rp6502_test_load.png");
+        let data: [u8; 8] = [255, 0, 0, 255, 0, 0, 255, 0];
+        save_png(&path, &data, 2, 1).unwrap();
+
+        let (w, h, pixels) = load_png(&path).unwrap();
+        assert_eq!((w, h), (2, 1));
+        assert_eq!(pixels, data);
+
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_load_png_expands_rgb() {
+        let path = std::env::temp_dir().join("rp6502_test_load_rgb.png");
+        let mut encoder = png::Encoder::new(BufWriter::new(fs::File::create(&path).unwrap()), 1, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.write_header().unwrap().write_image_data(&[1, 2, 3]).unwrap();
+
+        assert_eq!(load_png(&path).unwrap(), (1, 1, vec![1, 2, 3, 255]));
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_load_png_rejects_non_png() {
+        let path = std::env::temp_dir().join("rp6502_test_not_a.png");
+        fs::write(&path, b"not a png").unwrap();
+        assert!(matches!(load_png(&path), Err(ScreenshotError::Decode(_))));
+        fs::remove_file(&path).ok();
+
+        let missing = std::env::temp_dir().join("rp6502_test_missing.png");
+        assert!(matches!(load_png(&missing), Err(ScreenshotError::Io(_))));
+    }
+
+    #[test]
+    fn test_save_png_indexed_round_trip() {
+        use crate::vga::palette::PALETTE_256;
+
+        let path = std::env::temp_dir().join("rp6502_test_indexed.png");
+        let palette = &PALETTE_256[..16];
+        let canvas: Vec<u32> = (0..15).map(|i| palette[(i * 7) % 16]).collect();
+        save_png_indexed(&path, &canvas, 5, 3, palette, Quantize::Strict).unwrap();
+
+        // Stored as 4-bit palette indices
+        let decoder = png::Decoder::new(fs::File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!((info.color_type, info.bit_depth), (png::ColorType::Indexed, png::BitDepth::Four));
+        assert_eq!(info.palette.as_ref().unwrap().len(), 16 * 3);
+        let mut buf = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buf).unwrap();
+        assert_eq!(buf[0], 0x07, "row 0 starts with indices 0 and 7");
+
+        let (w, h, pixels) = load_png(&path).unwrap();
+        assert_eq!((w, h), (5, 3));
+        let expected: Vec<u8> = canvas.iter().flat_map(|c| c.to_be_bytes()).collect();
+        assert_eq!(pixels, expected);
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_save_png_indexed_strict_and_nearest() {
+        use crate::vga::palette::PALETTE_2;
+
+        let path = std::env::temp_dir().join("rp6502_test_indexed_quantize.png");
+        let off = PALETTE_2[1] - 0x0101_0100; // almost palette color 1
+        let canvas = [PALETTE_2[0], off];
+
+        let err = save_png_indexed(&path, &canvas, 2, 1, &PALETTE_2, Quantize::Strict).unwrap_err();
+        assert!(matches!(err, ScreenshotError::NotInPalette { x: 1, y: 0, rgba } if rgba == off));
+        assert!(!path.exists(), "no file should be created");
+
+        save_png_indexed(&path, &canvas, 2, 1, &PALETTE_2, Quantize::Nearest).unwrap();
+        let (_, _, pixels) = load_png(&path).unwrap();
+        assert_eq!(pixels[4..], PALETTE_2[1].to_be_bytes());
+        fs::remove_file(&path).ok();
+
+        let err = save_png_indexed(&path, &canvas, 2, 1, &[], Quantize::Nearest).unwrap_err();
+        assert!(matches!(err, ScreenshotError::BadPalette { len: 0 }));
+    }
+
+    #[test]
+    fn test_write_raw_frame() {
+        let frame = vec![0xAB; 640 * 480 * 4];
+        let mut sink = Vec::new();
+        write_raw_frame(&mut sink, &frame, 640, 480).unwrap();
+        assert_eq!(sink.len(), 640 * 480 * 4);
+        write_raw_frame(&mut sink, &frame, 640, 480).unwrap();
+        assert_eq!(sink.len(), 2 * 640 * 480 * 4, "frames are streamed back to back");
+
+        let err = write_raw_frame(&mut sink, &frame[4..], 640, 480).unwrap_err();
+        assert!(matches!(err, ScreenshotError::BadDimensions { .. }));
+    }
 }
@@ -1,9 +1,11 @@
 use crate::bus::BusTransaction;
 use crate::ria_api::{self, TraceBuilder};
+use crate::vga::mode1::Mode1Config;
+use crate::vga::mode3::Mode3Config;
 
 /// Valid canvas + color depth combinations that fit in 64KB XRAM.
 /// Each variant encodes both the canvas size and the bits-per-pixel.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TestMode {
     /// 640x480 canvas, 1bpp = 38,400 bytes
     Mono640x480,
@@ -35,6 +37,8 @@ pub enum TestMode {
     Mandelbrot,
     /// 320x240 canvas, two planes: Mode 3 1bpp checkerboard (plane 0) + Mode 1 8bpp rainbow text on right half (plane 1)
     MultiPlane,
+    /// 320x240 canvas, Mode 3 1bpp wrapping stripes, x_pos_px stepped across frame barriers
+    ScrollDemo,
 }
 
 impl std::fmt::Display for TestMode {
@@ -55,6 +59,7 @@ impl std::fmt::Display for TestMode {
             TestMode::Text8bpp320x240 => "text8bpp320x240",
             TestMode::Mandelbrot => "mandelbrot",
             TestMode::MultiPlane => "multi_plane",
+            TestMode::ScrollDemo => "scroll_demo",
         };
         write!(f, "{}", name)
     }
@@ -80,6 +85,7 @@ impl std::str::FromStr for TestMode {
             "text8bpp320x240" => Ok(TestMode::Text8bpp320x240),
             "mandelbrot" => Ok(TestMode::Mandelbrot),
             "multi_plane" => Ok(TestMode::MultiPlane),
+            "scroll_demo" => Ok(TestMode::ScrollDemo),
             _ => Err(format!(
                 "unknown mode '{}'. Valid modes: {}",
                 s,
@@ -108,6 +114,7 @@ impl TestMode {
             TestMode::Text8bpp320x240,
             TestMode::Mandelbrot,
             TestMode::MultiPlane,
+            TestMode::ScrollDemo,
         ]
     }
 
@@ -121,7 +128,8 @@ impl TestMode {
             TestMode::Mono640x480 => 3,  // 640x480
             TestMode::Mono640x360 | TestMode::Color2bpp640x360 => 4,  // 640x360
             TestMode::Text1bpp320x240 | TestMode::Text8bpp320x240
-            | TestMode::Mandelbrot | TestMode::MultiPlane => unreachable!(),
+            | TestMode::Mandelbrot | TestMode::MultiPlane
+            | TestMode::ScrollDemo => unreachable!(),
         }
     }
 
@@ -147,20 +155,14 @@ impl TestMode {
             TestMode::Color8bpp320x180 => 8,
             TestMode::Color16bpp320 => 16,
             TestMode::Text1bpp320x240 | TestMode::Text8bpp320x240
-            | TestMode::Mandelbrot | TestMode::MultiPlane => unreachable!(),
+            | TestMode::Mandelbrot | TestMode::MultiPlane
+            | TestMode::ScrollDemo => unreachable!(),
         }
     }
 
     /// Mode 3 attribute value.
     fn attr(&self) -> u16 {
-        match self.bpp() {
-            1 => 0,   // Bpp1Msb
-            2 => 1,   // Bpp2Msb
-            4 => 2,   // Bpp4Msb
-            8 => 3,   // Bpp8
-            16 => 4,  // Bpp16
-            _ => unreachable!(),
-        }
+        mode3_attr(self.bpp()).expect("TestMode bpp is always valid")
     }
 
     /// Bitmap dimensions. For 16bpp the height is limited by XRAM capacity.
@@ -177,6 +179,108 @@ impl TestMode {
     }
 }
 
+/// Mode 3 MSB-first attribute value for a color depth, or None if unsupported.
+fn mode3_attr(bpp: u16) -> Option<u16> {
+    match bpp {
+        1 => Some(0),   // Bpp1Msb
+        2 => Some(1),   // Bpp2Msb
+        4 => Some(2),   // Bpp4Msb
+        8 => Some(3),   // Bpp8
+        16 => Some(4),  // Bpp16
+        _ => None,
+    }
+}
+
+/// Unwrapped Mode 3 config at the canvas origin.
+fn mode3_config(width_px: i16, height_px: i16, xram_data_ptr: u16, xram_palette_ptr: u16) -> Mode3Config {
+    Mode3Config {
+        x_wrap: false,
+        y_wrap: false,
+        x_pos_px: 0,
+        y_pos_px: 0,
+        width_px,
+        height_px,
+        xram_data_ptr,
+        xram_palette_ptr,
+    }
+}
+
+/// Unwrapped Mode 1 config at `x_pos_px` with the built-in palette and font.
+fn mode1_config(x_pos_px: i16, width_chars: i16, height_chars: i16, xram_data_ptr: u16) -> Mode1Config {
+    Mode1Config {
+        x_wrap: false,
+        y_wrap: false,
+        x_pos_px,
+        y_pos_px: 0,
+        width_chars,
+        height_chars,
+        xram_data_ptr,
+        xram_palette_ptr: 0xFFFF,
+        xram_font_ptr: 0xFFFF,
+        advance_table: None,
+    }
+}
+
+/// Bytes of packed pixel data in a `width` x `height` bitmap at `bpp`
+/// (each row is padded to a whole byte).
+pub fn bitmap_data_len(width: u16, height: u16, bpp: u16) -> usize {
+    (width as usize * bpp as usize).div_ceil(8) * height as usize
+}
+
+/// Generate a bus trace that displays raw packed pixel data with Mode 3.
+///
+/// `data` uses the Mode 3 MSB-first packing (16bpp is PICO_SCANVIDEO) and
+/// must be exactly `bitmap_data_len(width, height, bpp)` bytes. The smallest
+/// canvas that fits the bitmap is selected; larger bitmaps use 640x480 and
+/// are clipped. Config goes at 0x0000 and pixel data at 0x0100, so the data
+/// must fit in the remaining XRAM. Palettes are the built-in ones.
+pub fn generate_bitmap_trace(
+    data: &[u8],
+    width: u16,
+    height: u16,
+    bpp: u16,
+) -> Result<Vec<BusTransaction>, String> {
+    let config_ptr: u16 = 0x0000;
+    let data_ptr: u16 = 0x0100;
+
+    let attr = mode3_attr(bpp).ok_or_else(|| format!("bpp must be 1, 2, 4, 8 or 16, got {bpp}"))?;
+    if width == 0 || height == 0 || width > i16::MAX as u16 || height > i16::MAX as u16 {
+        return Err(format!("bitmap size {width}x{height} out of range"));
+    }
+    let expected = bitmap_data_len(width, height, bpp);
+    if data.len() != expected {
+        return Err(format!(
+            "pixel data is {} bytes, expected {} for {}x{} at {}bpp",
+            data.len(), expected, width, height, bpp
+        ));
+    }
+    if data_ptr as usize + expected > 0x10000 {
+        return Err(format!(
+            "pixel data is {} bytes, XRAM only has room for {}",
+            expected, 0x10000 - data_ptr as usize
+        ));
+    }
+
+    // Smallest canvas first: 320x180, 320x240, 640x360, 640x480
+    let canvas = [(2, 320, 180), (1, 320, 240), (4, 640, 360)]
+        .iter()
+        .find(|&&(_, w, h)| width <= w && height <= h)
+        .map_or(3, |&(reg, _, _)| reg);
+
+    let mut tb = TraceBuilder::new();
+
+    tb.write_mode3_config(config_ptr, &mode3_config(width as i16, height as i16, data_ptr, 0xFFFF));
+
+    tb.xram0_write(data_ptr, data);
+
+    tb.xreg_vga_canvas(canvas);
+    tb.xreg_vga_mode(&[3, attr, config_ptr, 0, 0, 0]);
+
+    tb.wait_frames(1);
+    tb.op_exit();
+    Ok(tb.trace)
+}
+
 /// Generate a bus trace that programs Mode 1 with a test pattern.
 ///
 /// The trace:
@@ -196,16 +300,7 @@ fn generate_mode1_test_trace(mode: TestMode) -> Vec<BusTransaction> {
     };
 
     // --- Write Mode1Config fields to XRAM ---
-    use ria_api::vga_mode1_config_t::*;
-    tb.xram0_struct_set(config_ptr, X_WRAP, &[0]);
-    tb.xram0_struct_set(config_ptr, Y_WRAP, &[0]);
-    tb.xram0_struct_set(config_ptr, X_POS_PX, &0i16.to_le_bytes());
-    tb.xram0_struct_set(config_ptr, Y_POS_PX, &0i16.to_le_bytes());
-    tb.xram0_struct_set(config_ptr, WIDTH_CHARS, &width_chars.to_le_bytes());
-    tb.xram0_struct_set(config_ptr, HEIGHT_CHARS, &height_chars.to_le_bytes());
-    tb.xram0_struct_set(config_ptr, XRAM_DATA_PTR, &data_ptr.to_le_bytes());
-    tb.xram0_struct_set(config_ptr, XRAM_PALETTE_PTR, &0xFFFFu16.to_le_bytes());
-    tb.xram0_struct_set(config_ptr, XRAM_FONT_PTR, &0xFFFFu16.to_le_bytes());
+    tb.write_mode1_config(config_ptr, &mode1_config(0, width_chars, height_chars, data_ptr));
 
     // --- Write character data ---
     tb.set_addr0(data_ptr);
@@ -248,15 +343,7 @@ fn generate_mandelbrot_test_trace() -> Vec<BusTransaction> {
     let data_ptr: u16 = 0x0000;
 
     // --- Write Mode3Config fields to XRAM at 0xFF00 ---
-    use ria_api::vga_mode3_config_t::*;
-    tb.xram0_struct_set(config_ptr, X_WRAP, &[0]);
-    tb.xram0_struct_set(config_ptr, Y_WRAP, &[0]);
-    tb.xram0_struct_set(config_ptr, X_POS_PX, &0i16.to_le_bytes());
-    tb.xram0_struct_set(config_ptr, Y_POS_PX, &0i16.to_le_bytes());
-    tb.xram0_struct_set(config_ptr, WIDTH_PX, &320i16.to_le_bytes());
-    tb.xram0_struct_set(config_ptr, HEIGHT_PX, &240i16.to_le_bytes());
-    tb.xram0_struct_set(config_ptr, XRAM_DATA_PTR, &data_ptr.to_le_bytes());
-    tb.xram0_struct_set(config_ptr, XRAM_PALETTE_PTR, &0xFFFFu16.to_le_bytes());
+    tb.write_mode3_config(config_ptr, &mode3_config(320, 240, data_ptr, 0xFFFF));
 
     // --- Write pixel data at 0x0000 (4bpp LSB-first: 160 bytes/row, 38400 total) ---
     let mut pixel_data = Vec::with_capacity(160 * 240);
@@ -275,7 +362,7 @@ fn generate_mandelbrot_test_trace() -> Vec<BusTransaction> {
 
     // --- Configure VGA ---
     tb.xreg_vga_canvas(1);                              // 320x240
-    tb.xreg_vga_mode(&[3, 10, config_ptr as u16, 0, 0, 0]); // attr=10 = Bpp4Lsb
+    tb.xreg_vga_mode(&[3, 10, config_ptr, 0, 0, 0]); // attr=10 = Bpp4Lsb
 
     tb.wait_frames(1);
     tb.op_exit();
@@ -334,17 +421,7 @@ fn generate_multi_plane_test_trace() -> Vec<BusTransaction> {
     let m1_data_ptr: u16 = 0x2700;
 
     // --- Plane 0: Mode 3, 1bpp MSB, full-screen checkerboard ---
-    {
-        use ria_api::vga_mode3_config_t::*;
-        tb.xram0_struct_set(m3_config_ptr, X_WRAP, &[0]);
-        tb.xram0_struct_set(m3_config_ptr, Y_WRAP, &[0]);
-        tb.xram0_struct_set(m3_config_ptr, X_POS_PX, &0i16.to_le_bytes());
-        tb.xram0_struct_set(m3_config_ptr, Y_POS_PX, &0i16.to_le_bytes());
-        tb.xram0_struct_set(m3_config_ptr, WIDTH_PX, &320i16.to_le_bytes());
-        tb.xram0_struct_set(m3_config_ptr, HEIGHT_PX, &240i16.to_le_bytes());
-        tb.xram0_struct_set(m3_config_ptr, XRAM_DATA_PTR, &m3_data_ptr.to_le_bytes());
-        tb.xram0_struct_set(m3_config_ptr, XRAM_PALETTE_PTR, &0xFFFFu16.to_le_bytes());
-    }
+    tb.write_mode3_config(m3_config_ptr, &mode3_config(320, 240, m3_data_ptr, 0xFFFF));
 
     // 1bpp MSB: each byte covers 8 pixels; 40 bytes/row, 240 rows = 9600 bytes.
     // 8x8 pixel squares: block_x = byte index, block_y = row / 8.
@@ -363,18 +440,7 @@ fn generate_multi_plane_test_trace() -> Vec<BusTransaction> {
     // width_chars = 20 * 8px = 160px, covering pixels 160-319.
     let width_chars: i16 = 20;
     let height_chars: i16 = 30;
-    {
-        use ria_api::vga_mode1_config_t::*;
-        tb.xram0_struct_set(m1_config_ptr, X_WRAP, &[0]);
-        tb.xram0_struct_set(m1_config_ptr, Y_WRAP, &[0]);
-        tb.xram0_struct_set(m1_config_ptr, X_POS_PX, &160i16.to_le_bytes());
-        tb.xram0_struct_set(m1_config_ptr, Y_POS_PX, &0i16.to_le_bytes());
-        tb.xram0_struct_set(m1_config_ptr, WIDTH_CHARS, &width_chars.to_le_bytes());
-        tb.xram0_struct_set(m1_config_ptr, HEIGHT_CHARS, &height_chars.to_le_bytes());
-        tb.xram0_struct_set(m1_config_ptr, XRAM_DATA_PTR, &m1_data_ptr.to_le_bytes());
-        tb.xram0_struct_set(m1_config_ptr, XRAM_PALETTE_PTR, &0xFFFFu16.to_le_bytes());
-        tb.xram0_struct_set(m1_config_ptr, XRAM_FONT_PTR, &0xFFFFu16.to_le_bytes());
-    }
+    tb.write_mode1_config(m1_config_ptr, &mode1_config(160, width_chars, height_chars, m1_data_ptr));
 
     // Character data: 3 bytes per cell [glyph, fg_index, bg_index].
     // fg cycles through bright ANSI colors per column for a rainbow effect:
@@ -402,6 +468,50 @@ fn generate_multi_plane_test_trace() -> Vec<BusTransaction> {
     tb.trace
 }
 
+/// Frames rendered by the scroll demo, one per `frame_barrier`.
+const SCROLL_DEMO_FRAMES: i16 = 8;
+
+/// Generate a bus trace that scrolls a Mode 3 bitmap across several frames.
+///
+/// 320x240 canvas, Mode 3 1bpp MSB, x_wrap on. The bitmap holds 16px-wide
+/// diagonal stripes. After the mode is programmed, each frame moves x_pos_px
+/// 4px left and ends with a frame barrier, so every step is rendered.
+fn generate_scroll_demo_test_trace() -> Vec<BusTransaction> {
+    let mut tb = TraceBuilder::new();
+    let config_ptr: u16 = 0x0000;
+    let data_ptr: u16 = 0x0100;
+
+    tb.write_mode3_config(config_ptr, &Mode3Config { x_wrap: true, ..mode3_config(320, 240, data_ptr, 0xFFFF) });
+
+    // 1bpp MSB, 40 bytes/row: a pixel is set when (x + y) / 16 is odd.
+    let mut stripes = Vec::with_capacity(40 * 240);
+    for y in 0..240u32 {
+        for bx in 0..40u32 {
+            let mut byte = 0u8;
+            for bit in 0..8u32 {
+                if ((bx * 8 + bit + y) / 16) % 2 != 0 {
+                    byte |= 0x80 >> bit;
+                }
+            }
+            stripes.push(byte);
+        }
+    }
+    tb.xram0_write(data_ptr, &stripes);
+
+    tb.xreg_vga_canvas(1);                                  // 320x240
+    tb.xreg_vga_mode(&[3, 0, config_ptr, 0, 0, 0]);         // Mode 3, 1bpp MSB
+
+    for frame in 1..=SCROLL_DEMO_FRAMES {
+        use ria_api::vga_mode3_config_t::X_POS_PX;
+        tb.xram0_struct_set(config_ptr, X_POS_PX, &(-4 * frame).to_le_bytes());
+        tb.frame_barrier();
+    }
+
+    tb.wait_frames(1);
+    tb.op_exit();
+    tb.trace
+}
+
 /// Generate a bus trace that programs Mode 3 with a test pattern.
 ///
 /// The trace:
@@ -420,6 +530,9 @@ pub fn generate_test_trace(mode: TestMode) -> Vec<BusTransaction> {
         TestMode::MultiPlane => {
             return generate_multi_plane_test_trace();
         }
+        TestMode::ScrollDemo => {
+            return generate_scroll_demo_test_trace();
+        }
         _ => {}
     }
 
@@ -430,15 +543,7 @@ pub fn generate_test_trace(mode: TestMode) -> Vec<BusTransaction> {
     let bpp = mode.bpp();
 
     // --- Write Mode3Config fields to XRAM ---
-    use ria_api::vga_mode3_config_t::*;
-    tb.xram0_struct_set(config_ptr, X_WRAP, &[0]);
-    tb.xram0_struct_set(config_ptr, Y_WRAP, &[0]);
-    tb.xram0_struct_set(config_ptr, X_POS_PX, &0i16.to_le_bytes());
-    tb.xram0_struct_set(config_ptr, Y_POS_PX, &0i16.to_le_bytes());
-    tb.xram0_struct_set(config_ptr, WIDTH_PX, &bmp_w.to_le_bytes());
-    tb.xram0_struct_set(config_ptr, HEIGHT_PX, &bmp_h.to_le_bytes());
-    tb.xram0_struct_set(config_ptr, XRAM_DATA_PTR, &data_ptr.to_le_bytes());
-    tb.xram0_struct_set(config_ptr, XRAM_PALETTE_PTR, &0u16.to_le_bytes());
+    tb.write_mode3_config(config_ptr, &mode3_config(bmp_w, bmp_h, data_ptr, 0));
 
     // --- Write pixel data ---
     let bytes_per_row = (bmp_w as u32 * bpp as u32).div_ceil(8);
@@ -459,6 +564,15 @@ pub fn generate_test_trace(mode: TestMode) -> Vec<BusTransaction> {
     tb.trace
 }
 
+/// Strip XRAM portal traffic ($FFE4-$FFEB: RW, STEP and ADDR for both
+/// portals) from a trace, keeping its register programming, waits and exit.
+///
+/// Used with `screenshot --xram`, where XRAM comes from a memory image and
+/// only the canvas and mode setup of a test mode should run.
+pub fn without_xram_writes(trace: &[BusTransaction]) -> Vec<BusTransaction> {
+    trace.iter().filter(|t| !(0xFFE4..=0xFFEB).contains(&t.addr)).copied().collect()
+}
+
 /// Generate one byte of test pattern data at position (byte_x, y) in a bitmap.
 ///
 /// Pixel packing follows Mode 3 MSB-first convention:
@@ -519,10 +633,11 @@ fn pattern_byte(byte_x: u32, y: u32, bpp: u16, width: u32) -> u8 {
             // PICO_SCANVIDEO format: R5[4:0], alpha[5], G5[10:6], B5[15:11]
             // Alpha bit MUST be set for pixel to be visible.
             // byte_x counts individual bytes; each pixel is 2 bytes (little-endian).
+            // Reduce before adding so px + y cannot overflow for any byte_x/y.
             let px = byte_x / 2;
             let r5 = (px % 32) as u16;
             let g5 = (y % 32) as u16;
-            let b5 = ((px + y) % 32) as u16;
+            let b5 = (r5 + g5) % 32;
             let alpha = 1u16 << 5;
             let color: u16 = (b5 << 11) | (g5 << 6) | alpha | r5;
             if byte_x.is_multiple_of(2) {
@@ -538,6 +653,24 @@ fn pattern_byte(byte_x: u32, y: u32, bpp: u16, width: u32) -> u8 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Replay a trace through the RIA and VGA and return the 640x480 RGBA display.
+    fn render_trace(trace: &[BusTransaction]) -> Vec<u8> {
+        let framebuffer = Arc::new(Mutex::new(vec![0u8; 640 * 480 * 4]));
+        let (pix_tx, pix_rx) = crossbeam_channel::unbounded();
+        let (back_tx, back_rx) = crossbeam_channel::unbounded();
+        let mut vga = crate::vga::Vga::new(pix_rx, back_tx, framebuffer.clone());
+        {
+            let mut ria = crate::ria::Ria::new(pix_tx, back_rx);
+            for txn in trace {
+                ria.process(txn);
+            }
+        }
+        vga.run(); // pix_tx dropped with the RIA, so this drains and returns
+        let fb = framebuffer.lock().unwrap();
+        fb.clone()
+    }
 
     #[test]
     fn test_all_modes_produce_traces() {
@@ -547,6 +680,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_every_mode_has_golden_image() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+        for mode in TestMode::all() {
+            let path = dir.join(format!("{mode}.png"));
+            assert!(path.exists(), "missing golden {} (add the mode to tests/golden.rs)", path.display());
+        }
+    }
+
+    #[test]
+    fn test_without_xram_writes_keeps_programming() {
+        let trace = generate_test_trace(TestMode::Mono320x240);
+        let stripped = without_xram_writes(&trace);
+        assert!(stripped.len() < trace.len());
+        assert!(stripped.iter().all(|t| !(0xFFE4..=0xFFEB).contains(&t.addr)));
+        assert_eq!(stripped.last(), trace.last(), "exit op kept");
+        assert_eq!(stripped.iter().filter(|t| t.addr == 0xFFEF).count(), trace.iter().filter(|t| t.addr == 0xFFEF).count());
+    }
+
+    #[test]
+    fn test_scroll_demo_frame_boundaries() {
+        use crate::pix::PixEvent;
+        use crate::ria::FRAME_BARRIER_ADDR;
+
+        let trace = generate_test_trace(TestMode::ScrollDemo);
+        let barriers = trace.iter().filter(|t| !t.rw && t.addr == FRAME_BARRIER_ADDR).count();
+        assert_eq!(barriers, SCROLL_DEMO_FRAMES as usize);
+
+        let (pix_tx, pix_rx) = crossbeam_channel::unbounded();
+        let (_back_tx, back_rx) = crossbeam_channel::unbounded();
+        {
+            let mut ria = crate::ria::Ria::new(pix_tx, back_rx);
+            for txn in &trace {
+                ria.process(txn);
+            }
+        }
+        let syncs = pix_rx.iter().filter(|e| *e == PixEvent::FrameSync).count();
+        // One per barrier, plus the cycle-based boundary crossed by wait_frames(1)
+        assert_eq!(syncs, SCROLL_DEMO_FRAMES as usize + 1);
+    }
+
     #[test]
     fn test_trace_ends_with_exit() {
         for mode in TestMode::all() {
@@ -582,6 +756,136 @@ mod tests {
         assert!("invalid".parse::<TestMode>().is_err());
     }
 
+    #[test]
+    fn test_pattern_byte_16bpp_round_trips_through_renderer() {
+        use crate::vga::mode3::{render_mode3, ColorFormat, Mode3Config, Mode3Plane};
+
+        let mode = TestMode::Color16bpp320;
+        let (w, h) = mode.bitmap_size();
+        let (w, h) = (w as usize, h as usize);
+        let config_ptr = 0x0000usize;
+        let data_ptr = 0x0100usize;
+
+        let mut xram = Box::new([0u8; 65536]);
+        xram[config_ptr + 6..config_ptr + 8].copy_from_slice(&(w as i16).to_le_bytes());
+        xram[config_ptr + 8..config_ptr + 10].copy_from_slice(&(h as i16).to_le_bytes());
+        xram[config_ptr + 10..config_ptr + 12].copy_from_slice(&(data_ptr as u16).to_le_bytes());
+        for y in 0..h {
+            for byte_x in 0..w * 2 {
+                xram[data_ptr + y * w * 2 + byte_x] = pattern_byte(byte_x as u32, y as u32, 16, w as u32);
+            }
+        }
+
+        let plane = Mode3Plane {
+            config: Mode3Config::from_xram(&xram, config_ptr as u16),
+            format: ColorFormat::Bpp16,
+            scanline_begin: 0,
+            scanline_end: h as u16,
+            config_ptr: config_ptr as u16,
+            rgb16_layout: Default::default(),
+            scroll_table_ptr: None,
+            v_scale: 1,
+            transparent_index: None,
+            row_stride_bytes: None,
+            config_latch: Default::default(),
+        };
+        let mut fb = vec![0u32; w * h];
+        render_mode3(&plane, &xram, &mut fb, w as u16, h as u16);
+
+        // Expand 5-bit channels the way the renderer is expected to
+        let expand = |c5: u32| (c5 << 3) | (c5 >> 2);
+        for y in 0..h {
+            for px in 0..w {
+                let (r5, g5) = (px as u32 % 32, y as u32 % 32);
+                let b5 = (px as u32 + y as u32) % 32;
+                let expected = (expand(r5) << 24) | (expand(g5) << 16) | (expand(b5) << 8) | 0xFF;
+                assert_eq!(fb[y * w + px], expected, "pixel ({px},{y})");
+            }
+        }
+    }
+
+    #[test]
+    fn test_pattern_byte_16bpp_extreme_coordinates() {
+        // Must not overflow in debug builds
+        let lo = pattern_byte(u32::MAX - 1, u32::MAX, 16, u32::MAX);
+        let hi = pattern_byte(u32::MAX, u32::MAX, 16, u32::MAX);
+        let color = u16::from_le_bytes([lo, hi]);
+        assert_ne!(color & (1 << 5), 0, "alpha bit must be set");
+    }
+
+    /// Exhaustive match: adding a `TestMode` variant fails to compile until it
+    /// is given an index here, which in turn makes the `all()` length check fail
+    /// until `all()` is updated too.
+    fn variant_index(mode: TestMode) -> usize {
+        match mode {
+            TestMode::Mono640x480 => 0,
+            TestMode::Mono640x360 => 1,
+            TestMode::Mono320x240 => 2,
+            TestMode::Mono320x180 => 3,
+            TestMode::Color2bpp640x360 => 4,
+            TestMode::Color2bpp320x240 => 5,
+            TestMode::Color2bpp320x180 => 6,
+            TestMode::Color4bpp320x240 => 7,
+            TestMode::Color4bpp320x180 => 8,
+            TestMode::Color8bpp320x180 => 9,
+            TestMode::Color16bpp320 => 10,
+            TestMode::Text1bpp320x240 => 11,
+            TestMode::Text8bpp320x240 => 12,
+            TestMode::Mandelbrot => 13,
+            TestMode::MultiPlane => 14,
+            TestMode::ScrollDemo => 15,
+        }
+    }
+    const VARIANT_COUNT: usize = 16;
+
+    #[test]
+    fn test_all_covers_every_variant() {
+        let all = TestMode::all();
+        assert_eq!(all.len(), VARIANT_COUNT);
+        let mut seen = [false; VARIANT_COUNT];
+        for mode in all {
+            let i = variant_index(*mode);
+            assert!(!seen[i], "{:?} listed twice in all()", mode);
+            seen[i] = true;
+        }
+    }
+
+    #[test]
+    fn test_display_from_str_round_trip() {
+        for mode in TestMode::all() {
+            let name = mode.to_string();
+            assert_eq!(name.parse::<TestMode>(), Ok(*mode), "round trip of '{}'", name);
+        }
+    }
+
+    #[test]
+    fn test_bitmap_trace_1bpp_checkerboard() {
+        // 16x8 1bpp checkerboard: 2 bytes per row, alternating 0xAA / 0x55
+        let data: Vec<u8> = (0..8).flat_map(|y| [if y % 2 == 0 { 0xAA } else { 0x55 }; 2]).collect();
+        let trace = generate_bitmap_trace(&data, 16, 8, 1).expect("valid bitmap");
+        let display = render_trace(&trace);
+
+        // 320x180 canvas, 2x upscale; PALETTE_2 is transparent black / light grey
+        for y in 0..8usize {
+            for x in 0..16usize {
+                let idx = (y * 2 * 640 + x * 2) * 4;
+                let on = (x + y) % 2 == 0;
+                assert_eq!(display[idx + 3] != 0, on, "pixel ({x},{y})");
+            }
+        }
+        // Nothing outside the bitmap
+        assert_eq!(display[(20 * 640 + 40) * 4 + 3], 0);
+    }
+
+    #[test]
+    fn test_bitmap_trace_validates_size() {
+        assert_eq!(bitmap_data_len(10, 3, 1), 6);
+        assert!(generate_bitmap_trace(&[0; 5], 10, 3, 1).is_err());
+        assert!(generate_bitmap_trace(&[0; 6], 10, 3, 3).is_err());
+        assert!(generate_bitmap_trace(&[0; 65536], 256, 128, 16).is_err()); // exceeds XRAM
+        assert!(generate_bitmap_trace(&[0; 6], 10, 3, 1).is_ok());
+    }
+
     #[test]
     fn test_color16bpp_partial_height() {
         let trace = generate_test_trace(TestMode::Color16bpp320);
@@ -0,0 +1,72 @@
+//! Shared helpers for unit tests.
+
+/// Maximum number of differing pixels listed in a mismatch report.
+const MAX_REPORTED_DIFFS: usize = 8;
+
+/// Compare two RGBA u32 framebuffers (R in bits 31:24, A in 7:0) of size w x h.
+/// Returns None if equal, otherwise a report listing the first differing
+/// coordinates with both RGBA values.
+pub fn fb_diff(actual: &[u32], expected: &[u32], w: usize, h: usize) -> Option<String> {
+    assert_eq!(actual.len(), w * h, "actual framebuffer is not {w}x{h}");
+    assert_eq!(expected.len(), w * h, "expected framebuffer is not {w}x{h}");
+
+    let diffs: Vec<usize> = (0..w * h).filter(|&i| actual[i] != expected[i]).collect();
+    if diffs.is_empty() {
+        return None;
+    }
+
+    let mut msg = format!("framebuffers differ at {} of {} pixels:\n", diffs.len(), w * h);
+    for &i in diffs.iter().take(MAX_REPORTED_DIFFS) {
+        msg.push_str(&format!(
+            "  ({}, {}): actual {:08X}, expected {:08X}\n",
+            i % w, i / w, actual[i], expected[i],
+        ));
+    }
+    if diffs.len() > MAX_REPORTED_DIFFS {
+        msg.push_str(&format!("  ... and {} more\n", diffs.len() - MAX_REPORTED_DIFFS));
+    }
+    Some(msg)
+}
+
+/// Assert two RGBA u32 framebuffers are equal, reporting differing pixels on failure.
+pub fn assert_fb_eq(actual: &[u32], expected: &[u32], w: usize, h: usize) {
+    if let Some(msg) = fb_diff(actual, expected, w, h) {
+        panic!("{msg}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assert_fb_eq_equal() {
+        let fb = vec![0xFF0000FFu32; 4 * 3];
+        assert_fb_eq(&fb, &fb.clone(), 4, 3);
+    }
+
+    #[test]
+    fn test_fb_diff_one_pixel() {
+        let expected = vec![0u32; 4 * 3];
+        let mut actual = expected.clone();
+        actual[2 + 4] = 0x00FF00FF; // (2, 1)
+
+        let msg = fb_diff(&actual, &expected, 4, 3).unwrap();
+        assert!(msg.contains("1 of 12 pixels"), "{msg}");
+        assert!(msg.contains("(2, 1): actual 00FF00FF, expected 00000000"), "{msg}");
+    }
+
+    #[test]
+    fn test_fb_diff_truncates_report() {
+        let expected = vec![0u32; 16];
+        let actual = vec![1u32; 16];
+        let msg = fb_diff(&actual, &expected, 4, 4).unwrap();
+        assert!(msg.contains("... and 8 more"), "{msg}");
+    }
+
+    #[test]
+    #[should_panic(expected = "(0, 0): actual 00000001, expected 00000000")]
+    fn test_assert_fb_eq_panics_on_mismatch() {
+        assert_fb_eq(&[1], &[0], 1, 1);
+    }
+}
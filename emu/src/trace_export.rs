@@ -0,0 +1,43 @@
+use std::fmt::Write;
+use crate::bus::BusTransaction;
+
+/// Render a trace's bus writes as 6502 assembly (`LDA #$xx` / `STA $xxxx`
+/// pairs) that reproduces them when pasted into a cc65/ca65 test program.
+///
+/// Reads are skipped: they have no effect a program needs to replay.
+pub fn trace_asm(trace: &[BusTransaction]) -> String {
+    let writes = trace.iter().filter(|t| !t.rw).count();
+    let mut out = String::new();
+    let _ = writeln!(out, "; Bus trace exported by rp6502-emu ({writes} writes)");
+    for txn in trace.iter().filter(|t| !t.rw) {
+        let _ = writeln!(out, "    LDA #${:02X}", txn.data);
+        let _ = writeln!(out, "    STA ${:04X}", txn.addr);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ria_api::TraceBuilder;
+
+    #[test]
+    fn test_set_addr0_pair() {
+        let mut tb = TraceBuilder::new();
+        tb.set_addr0(0x0100);
+        let asm = trace_asm(&tb.trace);
+        assert!(asm.starts_with("; Bus trace exported by rp6502-emu (2 writes)\n"));
+        assert!(asm.ends_with("    LDA #$00\n    STA $FFE6\n    LDA #$01\n    STA $FFE7\n"));
+    }
+
+    #[test]
+    fn test_reads_are_skipped() {
+        let trace = [
+            BusTransaction::read(0, 0xFFE3, 0x81),
+            BusTransaction::write(1, 0xFFEF, 0xFF),
+        ];
+        let asm = trace_asm(&trace);
+        assert!(!asm.contains("FFE3"));
+        assert!(asm.contains("    LDA #$FF\n    STA $FFEF\n"));
+    }
+}
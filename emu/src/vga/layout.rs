@@ -0,0 +1,178 @@
+use super::mode0::{Mode0Plane, Sprite, SPRITE_SIZE};
+use super::mode1::Mode1Plane;
+use super::mode3::Mode3Plane;
+use super::Plane;
+
+/// Bytes of each mode's config struct in XRAM.
+const MODE0_CONFIG_SIZE: u32 = 8;
+const MODE1_CONFIG_SIZE: u32 = 16;
+const MODE3_CONFIG_SIZE: u32 = 14;
+
+/// What a plane keeps in an XRAM region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    Config,
+    /// Mode 3 bitmap or Mode 1 character cells.
+    Data,
+    /// Mode 1 font (only when not the built-in font).
+    Font,
+    /// Mode 0 sprite list.
+    Sprites,
+    /// Mode 0 tile bitmaps used by the sprite list.
+    Tiles,
+}
+
+impl std::fmt::Display for RegionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RegionKind::Config => "config",
+            RegionKind::Data => "data",
+            RegionKind::Font => "font",
+            RegionKind::Sprites => "sprites",
+            RegionKind::Tiles => "tiles",
+        })
+    }
+}
+
+/// An XRAM byte range `start..end` used by one plane. `end` may be 0x10000.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub plane: usize,
+    pub kind: RegionKind,
+    pub start: u32,
+    pub end: u32,
+}
+
+impl std::fmt::Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "plane {} {} (0x{:04X}-0x{:04X})", self.plane, self.kind, self.start, self.end - 1)
+    }
+}
+
+/// A problem found by `Vga::validate_layout`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutWarning {
+    /// Two regions share XRAM bytes, so writing one corrupts the other.
+    Overlap { a: Region, b: Region },
+}
+
+impl std::fmt::Display for LayoutWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LayoutWarning::Overlap { a, b } => write!(f, "{a} overlaps {b}"),
+        }
+    }
+}
+
+/// Build a region clamped to XRAM, or None if it is empty.
+fn region(plane: usize, kind: RegionKind, start: u16, len: u32) -> Option<Region> {
+    let start = start as u32;
+    let end = (start + len).min(0x10000);
+    (end > start).then_some(Region { plane, kind, start, end })
+}
+
+/// XRAM regions a plane reads, from its current config.
+pub fn plane_regions(slot: usize, plane: &Plane, xram: &[u8; 65536]) -> Vec<Region> {
+    let regions = match plane {
+        Plane::Mode0(p) => mode0_regions(slot, p, xram),
+        Plane::Mode1(p) => mode1_regions(slot, p),
+        Plane::Mode3(p) => mode3_regions(slot, p),
+    };
+    regions.into_iter().flatten().collect()
+}
+
+fn mode3_regions(slot: usize, p: &Mode3Plane) -> Vec<Option<Region>> {
+    let cfg = &p.config;
+    let h = cfg.height_px.max(0) as u32;
+    vec![
+        region(slot, RegionKind::Config, p.config_ptr, MODE3_CONFIG_SIZE),
+        region(slot, RegionKind::Data, cfg.xram_data_ptr, p.sizeof_row() as u32 * h),
+    ]
+}
+
+fn mode1_regions(slot: usize, p: &Mode1Plane) -> Vec<Option<Region>> {
+    let cfg = &p.config;
+    let cells = cfg.width_chars.max(0) as u32 * cfg.height_chars.max(0) as u32;
+    // A font that does not fit in XRAM falls back to the built-in one
+    let font_size = 256 * p.format.font_height() as u32;
+    let font = if cfg.xram_font_ptr as u32 + font_size <= 0x10000 {
+        region(slot, RegionKind::Font, cfg.xram_font_ptr, font_size)
+    } else {
+        None
+    };
+    vec![
+        region(slot, RegionKind::Config, p.config_ptr, MODE1_CONFIG_SIZE),
+        region(slot, RegionKind::Data, cfg.xram_data_ptr, cells * p.format.cell_size() as u32),
+        font,
+    ]
+}
+
+fn mode0_regions(slot: usize, p: &Mode0Plane, xram: &[u8; 65536]) -> Vec<Option<Region>> {
+    let cfg = &p.config;
+    let list_len = cfg.sprite_count as u32 * SPRITE_SIZE as u32;
+    let sizeof_tile = (p.tile_size as u32 * p.format.bits_per_pixel()).div_ceil(8) * p.tile_size as u32;
+    // Tiles 0..=highest tile a visible sprite uses
+    let tiles = if cfg.xram_sprite_ptr as u32 + list_len <= 0x10000 {
+        (0..cfg.sprite_count as usize)
+            .map(|i| Sprite::from_xram(xram, cfg.xram_sprite_ptr as usize + i * SPRITE_SIZE))
+            .filter(|s| s.attributes & Sprite::HIDDEN == 0)
+            .map(|s| s.tile as u32 + 1)
+            .max()
+            .unwrap_or(0)
+    } else {
+        0
+    };
+    vec![
+        region(slot, RegionKind::Config, p.config_ptr, MODE0_CONFIG_SIZE),
+        region(slot, RegionKind::Sprites, cfg.xram_sprite_ptr, list_len),
+        region(slot, RegionKind::Tiles, cfg.xram_tile_ptr, tiles * sizeof_tile),
+    ]
+}
+
+/// Report every pair of overlapping regions.
+///
+/// Identical regions of the same kind on different planes are skipped: two
+/// planes showing the same bitmap or sharing a font is deliberate.
+pub fn overlaps(regions: &[Region]) -> Vec<LayoutWarning> {
+    let mut warnings = Vec::new();
+    for (i, a) in regions.iter().enumerate() {
+        for b in &regions[i + 1..] {
+            let shared = a.kind == b.kind && a.start == b.start && a.end == b.end;
+            if a.start < b.end && b.start < a.end && !shared {
+                warnings.push(LayoutWarning::Overlap { a: *a, b: *b });
+            }
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn r(plane: usize, kind: RegionKind, start: u32, end: u32) -> Region {
+        Region { plane, kind, start, end }
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let config = r(0, RegionKind::Config, 0x0000, 0x000E);
+        let data = r(0, RegionKind::Data, 0x0008, 0x0100);
+        let other = r(1, RegionKind::Data, 0x0100, 0x0200);
+        assert_eq!(overlaps(&[config, data, other]), vec![LayoutWarning::Overlap { a: config, b: data }]);
+    }
+
+    #[test]
+    fn test_shared_region_is_not_an_overlap() {
+        let a = r(0, RegionKind::Font, 0x8000, 0x8800);
+        let b = r(1, RegionKind::Font, 0x8000, 0x8800);
+        assert!(overlaps(&[a, b]).is_empty());
+    }
+
+    #[test]
+    fn test_region_clamped_to_xram() {
+        assert_eq!(region(2, RegionKind::Data, 0xFF00, 0x400), Some(r(2, RegionKind::Data, 0xFF00, 0x10000)));
+        assert_eq!(region(2, RegionKind::Data, 0x1000, 0), None);
+        assert_eq!(r(1, RegionKind::Tiles, 0x0100, 0x0180).to_string(), "plane 1 tiles (0x0100-0x017F)");
+    }
+}
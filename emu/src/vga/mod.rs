@@ -1,85 +1,467 @@
 pub mod font;
+pub mod layout;
+pub mod mode0;
 pub mod mode1;
 pub mod mode3;
 pub mod palette;
 
+use std::io::Write;
 use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 use crossbeam_channel::{Receiver, Sender};
-use crate::pix::{Backchannel, PixEvent, PixRegWrite};
-use mode1::{Mode1Config, Mode1Format, Mode1Plane, render_mode1};
-use mode3::{ColorFormat, Mode3Config, Mode3Plane, render_mode3};
+use crate::pix::{Backchannel, PixEvent, PixRegWrite, encode_vsync};
+#[cfg(test)]
+use crate::pix::XramWrite;
+use mode0::{Mode0Config, Mode0Plane, mode0_attr, render_mode0_with_palette};
+use mode1::{Mode1Config, Mode1Format, Mode1Plane, advance_table_ptr, render_mode1_with_palette};
+use mode3::{ColorFormat, Mode3Config, Mode3Plane, Rgb16Layout, render_mode3_with_palette, scroll_table_ptr, V_SCALE2_ATTR};
+use layout::{overlaps, plane_regions, LayoutWarning};
+use palette::{resolve_palette, xram_palette_range, ResolvedPalette};
 
 /// Display output is always 640x480.
 const DISPLAY_WIDTH: usize = 640;
 const DISPLAY_HEIGHT: usize = 480;
 
+/// Channel 15 register holding the plane enable mask: bit n set draws plane
+/// n. Disabled planes keep their config. Emulator extension.
+pub const PLANE_ENABLE_REG: u8 = 0x10;
+
+/// Canvas geometry behind the published framebuffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameInfo {
+    pub canvas_width: u16,
+    pub canvas_height: u16,
+}
+
+impl FrameInfo {
+    /// Map a 640x480 display pixel back to the canvas pixel it was upscaled
+    /// from. Returns None for letterbox rows below a 16:9 canvas.
+    pub fn canvas_coord(&self, display_x: u32, display_y: u32) -> Option<(u16, u16)> {
+        let cw = self.canvas_width.max(1) as u32;
+        let ch = self.canvas_height.max(1) as u32;
+        let sx = (DISPLAY_WIDTH as u32 / cw).max(1);
+        let sy = (DISPLAY_HEIGHT as u32 / ch).max(1);
+        let (cx, cy) = (display_x / sx, display_y / sy);
+        if cx >= cw || cy >= ch {
+            return None;
+        }
+        Some((cx as u16, cy as u16))
+    }
+}
+
+/// Canvas size for a CANVAS register value, or None for an unknown code.
+///
+/// Codes 0-4 match the firmware: 0 is the console (640x480), then 320x240,
+/// 320x180, 640x480 and 640x360. Code 5 (640x240) is an emulator extension.
+pub fn canvas_dimensions(code: u16) -> Option<(u16, u16)> {
+    match code {
+        0 => Some((640, 480)),
+        1 => Some((320, 240)),
+        2 => Some((320, 180)),
+        3 => Some((640, 480)),
+        4 => Some((640, 360)),
+        5 => Some((640, 240)),
+        _ => None,
+    }
+}
+
+/// How `upscale_canvas` fits the canvas to the display.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScaleMode {
+    /// Integer factor per axis, top-left aligned; 16:9 canvases leave the
+    /// bottom scanlines to the border (firmware behavior).
+    #[default]
+    IntegerTopLeft,
+    /// Nearest-neighbor to the whole display on both axes, ignoring aspect
+    /// ratio (320x180 becomes 2x by ~2.67x). Emulator extension.
+    StretchFill,
+}
+
+/// Output settings for `upscale_canvas`.
+#[derive(Debug, Clone, Copy, Default)]
+struct UpscaleOptions {
+    /// Output size, None for the 640x480 display.
+    output: Option<(usize, usize)>,
+    scale_mode: ScaleMode,
+    /// Color of output pixels the canvas does not cover.
+    border: [u8; 4],
+}
+
 /// Upscale canvas buffer to the 640x480 display buffer.
 ///
-/// Integer scale factors are derived from canvas dimensions:
+/// With `ScaleMode::IntegerTopLeft`, integer scale factors are derived from
+/// canvas dimensions:
 /// - 320-wide canvases: 2x horizontal and vertical
-/// - 640-wide canvases: 1x (direct copy)
+/// - 640-wide canvases: 1x horizontal; 640x240 doubles scanlines vertically
 /// - 16:9 canvases (height 180 or 360): top-aligned, black fills remaining scanlines
 ///
 /// The u32 pixel format is R in bits 31:24, G in 23:16, B in 15:8, A in 7:0.
 /// Manual shift-and-mask is used (NOT bytemuck::cast_slice, which would give
 /// wrong byte order on little-endian targets due to this u32 packing).
-fn upscale_canvas(canvas: &[u32], canvas_w: u16, canvas_h: u16, display: &mut [u8]) {
+///
+/// With `field` Some(0) or Some(1) only display scanlines of that parity are
+/// written, for interlaced output; the others keep their previous contents.
+///
+/// `opts.output` replaces the 640x480 display size, e.g. (320, 240) for a
+/// compact preview. With `IntegerTopLeft` an axis smaller than the canvas is
+/// box-filtered: each output pixel averages its block of canvas pixels, every
+/// RGBA channel rounded.
+///
+/// Output pixels the canvas does not cover are set to `opts.border`.
+fn upscale_canvas(
+    canvas: &[u32],
+    canvas_w: u16,
+    canvas_h: u16,
+    display: &mut [u8],
+    field: Option<usize>,
+    opts: &UpscaleOptions,
+) {
+    let (out_w, out_h) = opts.output.unwrap_or((DISPLAY_WIDTH, DISPLAY_HEIGHT));
+    let border = opts.border;
     let cw = canvas_w as usize;
     let ch = canvas_h as usize;
-    let sx = DISPLAY_WIDTH / cw;
-    let sy = DISPLAY_HEIGHT / ch.max(1);
-
-    // Clear entire display to black (handles letterbox regions for 16:9)
-    display.fill(0);
-
-    for cy in 0..ch {
-        for cx in 0..cw {
-            let pixel = canvas[cy * cw + cx];
-            let r = (pixel >> 24) as u8;
-            let g = (pixel >> 16) as u8;
-            let b = (pixel >> 8) as u8;
-            let a = (pixel & 0xFF) as u8;
-
-            for dy in 0..sy {
-                let display_y = cy * sy + dy;
-                if display_y >= DISPLAY_HEIGHT {
-                    break;
-                }
-                for dx in 0..sx {
-                    let display_x = cx * sx + dx;
-                    if display_x >= DISPLAY_WIDTH {
-                        break;
+    // Per axis: repeat each canvas pixel `up` times, or average `down` of them
+    let factors = |c: usize, out: usize| if out >= c { (out / c, 1) } else { (1, c / out) };
+    let (up_x, down_x) = factors(cw, out_w);
+    let (up_y, down_y) = factors(ch.max(1), out_h);
+    // Canvas pixels under output pixel `o` on one axis: first index and count
+    let span = |o: usize, c: usize, out: usize, up: usize, down: usize| match opts.scale_mode {
+        ScaleMode::IntegerTopLeft => (o / up * down, down),
+        ScaleMode::StretchFill => (o * c / out, 1),
+    };
+    let in_field = |y: usize| field.is_none_or(|f| y % 2 == f);
+
+    for (oy, row) in display.chunks_exact_mut(out_w * 4).take(out_h).enumerate() {
+        if !in_field(oy) {
+            continue;
+        }
+        // Start from the border (handles letterbox regions for 16:9)
+        for px in row.chunks_exact_mut(4) {
+            px.copy_from_slice(&border);
+        }
+        let (cy, ny) = span(oy, ch, out_h, up_y, down_y);
+        if cy + ny > ch {
+            continue;
+        }
+        for (ox, px) in row.chunks_exact_mut(4).enumerate() {
+            let (cx, nx) = span(ox, cw, out_w, up_x, down_x);
+            if cx + nx > cw {
+                continue;
+            }
+            let pixel = if nx * ny == 1 {
+                canvas[cy * cw + cx].to_be_bytes()
+            } else {
+                let mut sum = [0u32; 4];
+                for y in cy..cy + ny {
+                    for &p in &canvas[y * cw + cx..y * cw + cx + nx] {
+                        for (s, c) in sum.iter_mut().zip(p.to_be_bytes()) {
+                            *s += c as u32;
+                        }
                     }
-                    let idx = (display_y * DISPLAY_WIDTH + display_x) * 4;
-                    display[idx]     = r;
-                    display[idx + 1] = g;
-                    display[idx + 2] = b;
-                    display[idx + 3] = a;
                 }
-            }
+                let n = (nx * ny) as u32;
+                sum.map(|s| ((s + n / 2) / n) as u8)
+            };
+            px.copy_from_slice(&pixel);
+        }
+    }
+}
+
+/// Wrap a 640x480 RGBA display buffer as an `image::RgbaImage`.
+/// Returns None if the buffer is the wrong size.
+#[cfg(feature = "image")]
+#[allow(dead_code)]
+pub fn framebuffer_to_image(display: &[u8]) -> Option<image::RgbaImage> {
+    image::RgbaImage::from_raw(DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32, display.to_vec())
+}
+
+/// Premultiply each RGBA pixel's color channels by its alpha, in place.
+///
+/// Used when publishing the framebuffer for GPU pipelines that expect
+/// premultiplied RGBA. With the current binary alpha this only zeroes the
+/// color of transparent pixels.
+fn premultiply_alpha(display: &mut [u8]) {
+    for px in display.chunks_exact_mut(4) {
+        let a = px[3] as u32;
+        for c in &mut px[..3] {
+            *c = ((*c as u32 * a + 127) / 255) as u8;
         }
     }
 }
 
-/// A programmed display plane, which may be Mode 1 or Mode 3.
+/// Gamma correction applied to the display buffer after upscaling.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GammaConfig {
+    /// Exponent applied per channel; 1.0 leaves the output unchanged.
+    pub gamma: f32,
+}
+
+impl Default for GammaConfig {
+    fn default() -> Self {
+        Self { gamma: 1.0 }
+    }
+}
+
+impl GammaConfig {
+    /// `out = 255 * (in / 255)^gamma` for every 8-bit channel value.
+    pub fn lut(&self) -> [u8; 256] {
+        let mut lut = [0u8; 256];
+        for (i, out) in lut.iter_mut().enumerate() {
+            *out = (255.0 * (i as f32 / 255.0).powf(self.gamma)).round() as u8;
+        }
+        lut
+    }
+}
+
+/// Map each RGBA pixel's color channels through `lut`, in place. Alpha is unchanged.
+fn apply_gamma(display: &mut [u8], lut: &[u8; 256]) {
+    for px in display.chunks_exact_mut(4) {
+        for c in &mut px[..3] {
+            *c = lut[*c as usize];
+        }
+    }
+}
+
+/// When a plane's config struct is read from XRAM.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfigLatch {
+    /// Once, when MODE programs the plane; later writes to the struct are
+    /// ignored until the plane is programmed again.
+    AtProgram,
+    /// Before every render, so config writes show on the next frame (firmware behavior).
+    #[default]
+    Live,
+}
+
+/// A programmed display plane, which may be Mode 0, Mode 1 or Mode 3.
 #[derive(Debug, Clone)]
 pub enum Plane {
+    Mode0(Mode0Plane),
     Mode1(Mode1Plane),
     Mode3(Mode3Plane),
 }
 
+impl Plane {
+    fn config_latch(&self) -> ConfigLatch {
+        match self {
+            Plane::Mode0(p) => p.config_latch,
+            Plane::Mode1(p) => p.config_latch,
+            Plane::Mode3(p) => p.config_latch,
+        }
+    }
+
+    /// Copy of the plane with its config re-read from XRAM at `config_ptr`,
+    /// or unchanged if the config was latched when the plane was programmed.
+    fn with_current_config(&self, xram: &[u8; 65536]) -> Plane {
+        if self.config_latch() == ConfigLatch::AtProgram {
+            return self.clone();
+        }
+        match self {
+            Plane::Mode0(p) => {
+                Plane::Mode0(Mode0Plane { config: Mode0Config::from_xram(xram, p.config_ptr), ..p.clone() })
+            }
+            Plane::Mode1(p) => {
+                let mut config = Mode1Config::from_xram(xram, p.config_ptr);
+                config.advance_table = p.config.advance_table;
+                Plane::Mode1(Mode1Plane { config, ..p.clone() })
+            }
+            Plane::Mode3(p) => {
+                Plane::Mode3(Mode3Plane { config: Mode3Config::from_xram(xram, p.config_ptr), ..p.clone() })
+            }
+        }
+    }
+}
+
+/// What an installed plane is, for status displays and tooling.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaneSummary {
+    /// Plane slot (0-2).
+    pub slot: usize,
+    /// Mode number (0, 1 or 3).
+    pub mode: u8,
+    /// Color format name, e.g. "Bpp8" or "Bpp1_8x16"; Mode 0 adds the tile size.
+    pub format: String,
+    pub scanline_begin: u16,
+    /// 0 means the bottom of the canvas, as in the MODE xreg.
+    pub scanline_end: u16,
+}
+
+impl Plane {
+    /// Summary of this plane in `slot`.
+    fn summary(&self, slot: usize) -> PlaneSummary {
+        let (mode, format, scanline_begin, scanline_end) = match self {
+            Plane::Mode0(p) => {
+                let format = format!("{:?} {}x{}", p.format, p.tile_size, p.tile_size);
+                (0, format, p.scanline_begin, p.scanline_end)
+            }
+            Plane::Mode1(p) => (1, format!("{:?}", p.format), p.scanline_begin, p.scanline_end),
+            Plane::Mode3(p) => (3, format!("{:?}", p.format), p.scanline_begin, p.scanline_end),
+        };
+        PlaneSummary { slot, mode, format, scanline_begin, scanline_end }
+    }
+}
+
+/// Render time per stage, accumulated over frames while `Vga::profile` is set.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderProfile {
+    /// Frames rendered while profiling.
+    pub frames: u64,
+    /// Time in `resolve_palette`, all planes.
+    pub palette: Duration,
+    /// Time in `render_modeN`, indexed by mode number (index 2 is unused).
+    pub modes: [Duration; 4],
+    /// Time in `upscale_canvas` (and gamma and alpha premultiplication).
+    pub upscale: Duration,
+}
+
+impl RenderProfile {
+    /// Human-readable per-frame averages, one stage per line.
+    pub fn report(&self) -> String {
+        let frames = self.frames.max(1) as u32;
+        let mut lines = vec![format!("{} frame(s), average per frame:", self.frames)];
+        lines.push(format!("  resolve_palette  {:?}", self.palette / frames));
+        for (mode, total) in self.modes.iter().enumerate() {
+            if !total.is_zero() {
+                lines.push(format!("  render_mode{mode}     {:?}", *total / frames));
+            }
+        }
+        lines.push(format!("  upscale_canvas   {:?}", self.upscale / frames));
+        lines.join("\n")
+    }
+}
+
+/// Timing of one rendered frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Wall-clock time in `render_frame`, including publishing the framebuffer.
+    pub duration: Duration,
+    /// Planes drawn: programmed and enabled in the channel 15 plane mask.
+    pub planes: usize,
+}
+
+/// Min/avg/max frame render time, accumulated while `Vga::frame_times` is set.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameTimes {
+    pub frames: u64,
+    pub min: Duration,
+    pub max: Duration,
+    pub total: Duration,
+}
+
+impl FrameTimes {
+    fn record(&mut self, duration: Duration) {
+        self.min = if self.frames == 0 { duration } else { self.min.min(duration) };
+        self.max = self.max.max(duration);
+        self.total += duration;
+        self.frames += 1;
+    }
+
+    /// One-line summary of the recorded frames.
+    pub fn report(&self) -> String {
+        format!(
+            "{} frame(s): min {:?}, avg {:?}, max {:?}",
+            self.frames,
+            self.min,
+            self.total / self.frames.max(1) as u32,
+            self.max
+        )
+    }
+}
+
+/// A plane's resolved palette, reused by later frames until the plane's
+/// format or palette pointer changes or an XRAM write lands in `range`.
+#[derive(Debug, Clone)]
+struct CachedPalette {
+    bpp: u32,
+    palette_ptr: u16,
+    /// XRAM bytes the colors came from; None for a built-in palette.
+    range: Option<std::ops::Range<usize>>,
+    colors: Vec<u32>,
+}
+
 /// VGA state machine.
 pub struct Vga {
+    /// XRAM replica. Once frames are rendering, change it through PIX events:
+    /// direct edits do not invalidate cached palettes.
     pub xram: Box<[u8; 65536]>,
+    /// Mask applied to XRAM addresses. Writes are mirrored to every alias, so
+    /// config reads and renderers see mirrored XRAM without masking each access.
+    pub xram_mask: u16,
     pub planes: [Option<Plane>; 3],
+    /// Planes drawn by `render_frame`; set by MODE and `PLANE_ENABLE_REG`.
+    pub plane_enabled: [bool; 3],
+    /// Canvas size. Starts at 640x480, the firmware's power-on canvas, so a
+    /// MODE sent before any CANVAS is accepted and renders at 640x480.
     pub canvas_width: u16,
     pub canvas_height: u16,
     xregs: [u16; 8],
     pix_rx: Receiver<PixEvent>,
     backchannel_tx: Sender<Backchannel>,
     framebuffer: Arc<Mutex<Vec<u8>>>,
+    /// Canvas size of the frame in `framebuffer`, updated on each publish.
+    frame_info: Arc<Mutex<FrameInfo>>,
     frame_count: u8,
     canvas_buf: Vec<u32>,
+    /// Canvas size `canvas_buf` was last rendered at; (0, 0) before any render.
+    canvas_buf_size: (u16, u16),
+    /// Display color outside the upscaled canvas, e.g. below a 16:9 canvas.
+    /// Transparent black by default.
+    pub border_rgba: [u8; 4],
+    /// How the canvas is fitted to the 640x480 display.
+    pub scale_mode: ScaleMode,
+    /// Interlaced output: each render draws only the even (even frame count)
+    /// or odd display scanlines, keeping the other field from the last frame.
+    pub interlace: bool,
+    /// Render only every other FrameSync (30fps), halving render work.
+    pub half_rate: bool,
+    /// With `half_rate`, advance the VSYNC counter only on rendered frames.
+    /// When false, VSYNC still advances on every FrameSync (real 60Hz rate).
+    pub half_rate_vsync: bool,
+    /// Sequence number of the last XRAM write received.
+    last_xram_seq: Option<u64>,
+    /// XRAM writes that arrived with a sequence number not following the previous
+    /// one (out of order or dropped). Counted only; the CLI reports the total.
+    pub xram_seq_errors: u64,
+    /// MODE writes whose scanline_end exceeded the canvas height and was
+    /// clamped to it. Counted only; the CLI reports the total.
+    pub scanline_clamps: u64,
+    /// Number of FrameSync events received.
+    sync_count: u64,
+    /// Number of frames actually rendered.
+    pub frames_rendered: u64,
+    /// Stop `run` right after rendering this frame (0 is the first), leaving
+    /// it in the published framebuffer.
+    pub stop_after_frame: Option<u64>,
+    /// Config latch of planes programmed from now on.
+    pub config_latch: ConfigLatch,
+    /// Publish the framebuffer with color channels premultiplied by alpha.
+    pub premultiplied_alpha: bool,
+    /// Record each plane's resolved palette in `palettes` on every render.
+    pub capture_palettes: bool,
+    /// Resolved palette per plane from the last render (with `capture_palettes`).
+    pub palettes: [Option<ResolvedPalette>; 3],
+    /// Palette per plane slot from earlier renders.
+    palette_cache: [Option<CachedPalette>; 3],
+    /// Number of times a plane's palette was resolved from XRAM or the
+    /// built-in tables rather than taken from the cache.
+    #[allow(dead_code)]
+    pub palette_rebuilds: u64,
+    /// Stage timings, accumulated on every render while Some.
+    pub profile: Option<RenderProfile>,
+    /// Timing of the most recent `render_frame`.
+    last_frame_stats: Option<FrameStats>,
+    /// Also send each frame's stats over the backchannel as `Backchannel::Stats`.
+    pub send_stats: bool,
+    /// Frame render times, accumulated on every render while Some.
+    pub frame_times: Option<FrameTimes>,
+    /// Output gamma; change with `set_gamma` so the LUT is rebuilt.
+    gamma: GammaConfig,
+    /// Precomputed `gamma` LUT, None when gamma is 1.0.
+    gamma_lut: Option<[u8; 256]>,
+    /// Every PIX event handled, one line each (see `PixEvent`'s `Display`).
+    /// Dropped after the first write error.
+    pub pix_log: Option<Box<dyn std::io::Write + Send>>,
 }
 
 impl Vga {
@@ -92,40 +474,258 @@ impl Vga {
         let canvas_height = 480;
         Self {
             xram: Box::new([0; 65536]),
+            xram_mask: 0xFFFF,
             planes: [None, None, None],
+            plane_enabled: [true; 3],
             canvas_width,
             canvas_height,
             xregs: [0; 8],
             pix_rx,
             backchannel_tx,
             framebuffer,
+            frame_info: Arc::new(Mutex::new(FrameInfo { canvas_width, canvas_height })),
             frame_count: 0,
             canvas_buf: vec![0u32; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+            canvas_buf_size: (0, 0),
+            border_rgba: [0; 4],
+            scale_mode: ScaleMode::IntegerTopLeft,
+            interlace: false,
+            half_rate: false,
+            half_rate_vsync: false,
+            last_xram_seq: None,
+            xram_seq_errors: 0,
+            scanline_clamps: 0,
+            sync_count: 0,
+            frames_rendered: 0,
+            stop_after_frame: None,
+            config_latch: ConfigLatch::Live,
+            premultiplied_alpha: false,
+            capture_palettes: false,
+            palettes: [None, None, None],
+            palette_cache: [None, None, None],
+            palette_rebuilds: 0,
+            profile: None,
+            last_frame_stats: None,
+            send_stats: false,
+            frame_times: None,
+            gamma: GammaConfig::default(),
+            gamma_lut: None,
+            pix_log: None,
+        }
+    }
+
+    /// A VGA whose XRAM replica starts as `xram` instead of zeroes, so a
+    /// prepared memory image needs only register writes to be displayed.
+    #[allow(dead_code)]
+    pub fn with_xram(
+        pix_rx: Receiver<PixEvent>,
+        backchannel_tx: Sender<Backchannel>,
+        framebuffer: Arc<Mutex<Vec<u8>>>,
+        xram: Box<[u8; 65536]>,
+    ) -> Self {
+        Self { xram, ..Self::new(pix_rx, backchannel_tx, framebuffer) }
+    }
+
+    /// Number of installed planes.
+    pub fn active_plane_count(&self) -> usize {
+        self.planes.iter().flatten().count()
+    }
+
+    /// Mode, format and scanline range of each installed plane, by slot.
+    #[allow(dead_code)]
+    pub fn plane_summary(&self) -> Vec<PlaneSummary> {
+        self.planes
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, plane)| plane.as_ref().map(|p| p.summary(slot)))
+            .collect()
+    }
+
+    /// Check the installed planes' XRAM regions (config struct, and bitmap,
+    /// character, font, sprite or tile data) against the current XRAM
+    /// contents, reporting overlaps within a plane or between planes.
+    pub fn validate_layout(&self) -> Vec<LayoutWarning> {
+        let mut regions = Vec::new();
+        for (slot, plane) in self.planes.iter().enumerate() {
+            if let Some(plane) = plane {
+                regions.extend(plane_regions(slot, &plane.with_current_config(&self.xram), &self.xram));
+            }
+        }
+        overlaps(&regions)
+    }
+
+    /// The canvas of the last render at native resolution, before upscaling:
+    /// RGBA u32 pixels (R in bits 31:24) and the canvas width and height.
+    /// Empty before the first render.
+    pub fn canvas_snapshot(&self) -> (&[u32], u16, u16) {
+        let (w, h) = self.canvas_buf_size;
+        (&self.canvas_buf[..w as usize * h as usize], w, h)
+    }
+
+    /// Scale the most recently rendered canvas to an `out_w`x`out_h` RGBA
+    /// image, with the display's scale mode, border, gamma and alpha. Axes
+    /// smaller than the canvas are box-filtered, so a 640x480 canvas gives a
+    /// compact 320x240 preview. All zero before the first render.
+    pub fn render_preview(&self, out_w: usize, out_h: usize) -> Vec<u8> {
+        let mut preview = vec![0u8; out_w * out_h * 4];
+        let (canvas, w, h) = self.canvas_snapshot();
+        if canvas.is_empty() {
+            return preview;
         }
+        let opts = UpscaleOptions { output: Some((out_w, out_h)), scale_mode: self.scale_mode, border: self.border_rgba };
+        upscale_canvas(canvas, w, h, &mut preview, None, &opts);
+        for row in preview.chunks_exact_mut(out_w * 4) {
+            self.post_process(row);
+        }
+        preview
+    }
+
+    /// Apply output gamma and alpha premultiplication to upscaled pixels.
+    fn post_process(&self, pixels: &mut [u8]) {
+        if let Some(lut) = &self.gamma_lut {
+            apply_gamma(pixels, lut);
+        }
+        if self.premultiplied_alpha {
+            premultiply_alpha(pixels);
+        }
+    }
+
+    /// Timing of the most recent rendered frame, None before the first.
+    #[allow(dead_code)]
+    pub fn last_frame_stats(&self) -> Option<FrameStats> {
+        self.last_frame_stats
+    }
+
+    /// Output gamma applied after upscaling.
+    #[allow(dead_code)]
+    pub fn gamma(&self) -> GammaConfig {
+        self.gamma
+    }
+
+    /// Set the output gamma and precompute its LUT.
+    #[allow(dead_code)]
+    pub fn set_gamma(&mut self, gamma: GammaConfig) {
+        self.gamma = gamma;
+        self.gamma_lut = (gamma.gamma != 1.0).then(|| gamma.lut());
+    }
+
+    /// Copy the published framebuffer into an `image::RgbaImage`.
+    #[cfg(feature = "image")]
+    #[allow(dead_code)]
+    pub fn to_image(&self) -> image::RgbaImage {
+        let fb = self.framebuffer.lock().unwrap_or_else(|e| e.into_inner());
+        framebuffer_to_image(&fb).expect("framebuffer is always 640x480 RGBA")
+    }
+
+    /// Shared handle to the canvas geometry of the published framebuffer.
+    pub fn frame_info(&self) -> Arc<Mutex<FrameInfo>> {
+        self.frame_info.clone()
     }
 
     /// Run the VGA event loop. Call from a dedicated thread.
+    /// Handle PIX events until `PixEvent::Shutdown`, every sender is dropped
+    /// or the `stop_after_frame` frame has been rendered.
     pub fn run(&mut self) {
         while let Ok(event) = self.pix_rx.recv() {
+            let shutdown = event == PixEvent::Shutdown;
             self.handle_event(event);
+            if shutdown || self.stop_after_frame.is_some_and(|n| self.frames_rendered > n) {
+                break;
+            }
         }
     }
 
-    fn handle_event(&mut self, event: PixEvent) {
+    /// Run on a new thread. Joining returns the VGA, so its final state
+    /// (planes, palettes, profile) can be inspected once it has stopped.
+    pub fn spawn(mut self) -> JoinHandle<Vga> {
+        thread::spawn(move || {
+            self.run();
+            self
+        })
+    }
+
+    pub(crate) fn handle_event(&mut self, event: PixEvent) {
+        if let Some(log) = &mut self.pix_log {
+            let mut result = writeln!(log, "{event}");
+            if event == PixEvent::Shutdown {
+                result = result.and_then(|_| log.flush());
+            }
+            if let Err(e) = result {
+                eprintln!("warning: PIX log write failed, recording stopped: {e}");
+                self.pix_log = None;
+            }
+        }
         match event {
             PixEvent::Xram(write) => {
-                self.xram[write.addr as usize] = write.data;
+                if let Some(last) = self.last_xram_seq {
+                    if write.seq != last + 1 {
+                        self.xram_seq_errors += 1;
+                    }
+                }
+                self.last_xram_seq = Some(write.seq);
+                self.write_xram(write.addr, write.data);
             }
             PixEvent::Reg(reg) => {
                 self.handle_reg(reg);
             }
             PixEvent::FrameSync => {
+                // In half-rate mode only even-numbered syncs render.
+                let render = !self.half_rate || self.sync_count.is_multiple_of(2);
+                self.sync_count = self.sync_count.wrapping_add(1);
+                if render {
+                    self.render_frame();
+                    self.frames_rendered += 1;
+                }
+                if render || !self.half_rate_vsync {
+                    self.frame_count = self.frame_count.wrapping_add(1);
+                    let _ = self.backchannel_tx.send(
+                        Backchannel::Vsync(encode_vsync(true, self.frame_count))
+                    );
+                }
+            }
+            PixEvent::Shutdown => {
+                // Publish the final state, even if no FrameSync followed it.
+                // Not a display frame, so no vsync is reported.
                 self.render_frame();
-                self.frame_count = self.frame_count.wrapping_add(1);
-                let _ = self.backchannel_tx.send(
-                    Backchannel::Vsync(0x80 | (self.frame_count & 0x0F))
-                );
             }
+            PixEvent::Reset => self.reset(),
+        }
+    }
+
+    /// Drop everything a previous trace set up: planes, XRAM, canvas size,
+    /// pending xregs and the XRAM sequence, which a new RIA restarts at 0.
+    /// Settings such as `half_rate` or gamma are kept. Nothing is rendered.
+    fn reset(&mut self) {
+        self.xram.fill(0);
+        self.planes = [None, None, None];
+        self.plane_enabled = [true; 3];
+        self.palettes = [None, None, None];
+        self.palette_cache = [None, None, None];
+        self.canvas_width = 640;
+        self.canvas_height = 480;
+        self.xregs = [0; 8];
+        self.last_xram_seq = None;
+    }
+
+    /// Store an XRAM byte at every address that aliases `addr` under `xram_mask`,
+    /// dropping cached palettes read from those addresses.
+    fn write_xram(&mut self, addr: u16, data: u8) {
+        let base = addr & self.xram_mask;
+        let high = !self.xram_mask;
+        // Enumerate every subset of the unmasked bits
+        let mut sub = high;
+        loop {
+            let alias = (base | sub) as usize;
+            self.xram[alias] = data;
+            for cached in &mut self.palette_cache {
+                if cached.as_ref().is_some_and(|c| c.range.as_ref().is_some_and(|r| r.contains(&alias))) {
+                    *cached = None;
+                }
+            }
+            if sub == 0 {
+                break;
+            }
+            sub = (sub - 1) & high;
         }
     }
 
@@ -141,65 +741,76 @@ impl Vga {
             match reg.register {
                 0 => {
                     // CANVAS - configure canvas size
-                    match reg.value {
-                        1 => { self.canvas_width = 320; self.canvas_height = 240; }
-                        2 => { self.canvas_width = 320; self.canvas_height = 180; }
-                        3 => { self.canvas_width = 640; self.canvas_height = 480; }
-                        4 => { self.canvas_width = 640; self.canvas_height = 360; }
-                        _ => { self.canvas_width = 640; self.canvas_height = 480; }
-                    }
+                    self.xregs = [0; 8];
+                    let Some((width, height)) = canvas_dimensions(reg.value) else {
+                        // Unknown code: keep the current canvas and planes
+                        let _ = self.backchannel_tx.send(Backchannel::Nak);
+                        return;
+                    };
+                    self.canvas_width = width;
+                    self.canvas_height = height;
                     // Reset all planes
                     self.planes = [None, None, None];
-                    self.xregs = [0; 8];
                     let _ = self.backchannel_tx.send(Backchannel::Ack);
                 }
                 1 => {
                     // MODE - program a graphics mode
                     let mode = reg.value;
-                    match mode {
-                        1 => {
-                            self.program_mode1();
-                            let _ = self.backchannel_tx.send(Backchannel::Ack);
-                        }
-                        3 => {
-                            self.program_mode3();
-                            let _ = self.backchannel_tx.send(Backchannel::Ack);
-                        }
-                        _ => {
-                            let _ = self.backchannel_tx.send(Backchannel::Nak);
-                        }
-                    }
+                    let ok = match mode {
+                        0 => self.program_mode0(),
+                        1 => self.program_mode1(),
+                        3 => self.program_mode3(),
+                        _ => false,
+                    };
+                    // The programmed plane index is returned as the ack payload
+                    let reply = if ok {
+                        self.plane_enabled[self.xregs[4] as usize] = true;
+                        Backchannel::AckPayload(self.xregs[4])
+                    } else {
+                        Backchannel::Nak
+                    };
+                    let _ = self.backchannel_tx.send(reply);
                     self.xregs = [0; 8];
                 }
                 _ => {
                     // Registers 2-7: accumulate into xregs, no ack needed
                 }
             }
+        } else if reg.channel == 15 && reg.register == PLANE_ENABLE_REG {
+            for (slot, enabled) in self.plane_enabled.iter_mut().enumerate() {
+                *enabled = reg.value & (1 << slot) != 0;
+            }
         }
-        // Channel 15: display config, code page, backchannel control - ignored in MVP
+        // Rest of channel 15: display config, code page, backchannel control - ignored in MVP
     }
 
     /// Program Mode 3 from accumulated xregs.
     /// xregs layout for MODE command:
-    ///   xregs[2] = attributes (color format)
+    ///   xregs[2] = attributes (color format; bit 5 = v_scale 2, emulator extension)
     ///   xregs[3] = config_ptr (XRAM address of Mode3Config)
     ///   xregs[4] = plane index (0-2)
     ///   xregs[5] = scanline_begin
     ///   xregs[6] = scanline_end (0 = canvas height)
-    fn program_mode3(&mut self) {
+    ///   xregs[7] = scroll table ptr (emulator extension, 0xFFFF = none)
+    ///
+    /// Returns false (NAK) if the registers are invalid.
+    fn program_mode3(&mut self) -> bool {
         let attr = self.xregs[2];
         let config_ptr = self.xregs[3];
         let plane_idx = self.xregs[4] as usize;
-        let scanline_begin = self.xregs[5];
-        let scanline_end = self.xregs[6];
 
         if plane_idx >= 3 || config_ptr & 1 != 0 {
-            return;
+            return false;
         }
 
-        let format = match ColorFormat::from_attr(attr) {
+        let Some((scanline_begin, scanline_end)) = self.scanline_range() else {
+            return false;
+        };
+
+        let v_scale = if attr & V_SCALE2_ATTR != 0 { 2 } else { 1 };
+        let format = match ColorFormat::from_attr(attr & !V_SCALE2_ATTR) {
             Some(f) => f,
-            None => return,
+            None => return false,
         };
 
         let config = Mode3Config::from_xram(&self.xram, config_ptr);
@@ -210,7 +821,54 @@ impl Vga {
             scanline_begin,
             scanline_end,
             config_ptr,
+            rgb16_layout: Rgb16Layout::default(),
+            scroll_table_ptr: scroll_table_ptr(self.xregs[7]),
+            v_scale,
+            transparent_index: None,
+            row_stride_bytes: None,
+            config_latch: self.config_latch,
         }));
+        true
+    }
+
+    /// Program Mode 0 (sprites) from accumulated xregs.
+    /// Same xregs layout as Mode 3:
+    ///   xregs[2] = attributes (color format, bit 4 = 16x16 tiles)
+    ///   xregs[3] = config_ptr (XRAM address of Mode0Config)
+    ///   xregs[4] = plane index (0-2)
+    ///   xregs[5] = scanline_begin
+    ///   xregs[6] = scanline_end (0 = canvas height)
+    ///
+    /// Returns false (NAK) if the registers are invalid.
+    fn program_mode0(&mut self) -> bool {
+        let attr = self.xregs[2];
+        let config_ptr = self.xregs[3];
+        let plane_idx = self.xregs[4] as usize;
+
+        if plane_idx >= 3 || config_ptr & 1 != 0 || config_ptr as usize + 8 > 0x10000 {
+            return false;
+        }
+
+        let Some((scanline_begin, scanline_end)) = self.scanline_range() else {
+            return false;
+        };
+
+        let Some((format, tile_size)) = mode0_attr(attr) else {
+            return false;
+        };
+
+        let config = Mode0Config::from_xram(&self.xram, config_ptr);
+
+        self.planes[plane_idx] = Some(Plane::Mode0(Mode0Plane {
+            config,
+            format,
+            tile_size,
+            scanline_begin,
+            scanline_end,
+            config_ptr,
+            config_latch: self.config_latch,
+        }));
+        true
     }
 
     /// Program Mode 1 from accumulated xregs.
@@ -220,28 +878,34 @@ impl Vga {
     ///   xregs[4] = plane index (0-2)
     ///   xregs[5] = scanline_begin
     ///   xregs[6] = scanline_end (0 = canvas height)
-    fn program_mode1(&mut self) {
+    ///   xregs[7] = glyph advance table ptr (emulator extension, 0xFFFF = fixed width)
+    ///
+    /// Returns false (NAK) if the registers are invalid.
+    fn program_mode1(&mut self) -> bool {
         let attr = self.xregs[2];
         let config_ptr = self.xregs[3];
         let plane_idx = self.xregs[4] as usize;
-        let scanline_begin = self.xregs[5];
-        let scanline_end = self.xregs[6];
 
         if plane_idx >= 3 || config_ptr & 1 != 0 {
-            return;
+            return false;
         }
 
         // Additional firmware check: config_ptr must leave room for the 16-byte struct
         if config_ptr as usize + 16 > 0x10000 {
-            return;
+            return false;
         }
 
+        let Some((scanline_begin, scanline_end)) = self.scanline_range() else {
+            return false;
+        };
+
         let format = match Mode1Format::from_attr(attr) {
             Some(f) => f,
-            None => return,
+            None => return false,
         };
 
-        let config = Mode1Config::from_xram(&self.xram, config_ptr);
+        let mut config = Mode1Config::from_xram(&self.xram, config_ptr);
+        config.advance_table = advance_table_ptr(self.xregs[7]);
 
         self.planes[plane_idx] = Some(Plane::Mode1(Mode1Plane {
             config,
@@ -249,47 +913,695 @@ impl Vga {
             scanline_begin,
             scanline_end,
             config_ptr,
+            row_gap_px: 0,
+            nine_dot: false,
+            config_latch: self.config_latch,
         }));
+        true
     }
 
-    /// Render all planes to the framebuffer.
+    /// Validate the scanline range in xregs[5] (begin) and xregs[6] (end).
+    ///
+    /// An end of 0 means the full canvas height and is kept as 0. An end past the
+    /// canvas height is clamped to it and counted in `scanline_clamps`.
+    /// Returns None if the effective range is empty (begin >= end).
+    fn scanline_range(&mut self) -> Option<(u16, u16)> {
+        let begin = self.xregs[5];
+        let mut end = self.xregs[6];
+        if end > self.canvas_height {
+            self.scanline_clamps += 1;
+            end = self.canvas_height;
+        }
+        let effective_end = if end == 0 { self.canvas_height } else { end };
+        if begin >= effective_end {
+            return None;
+        }
+        Some((begin, end))
+    }
+
+    /// Render all planes and publish the result to the shared framebuffer.
     fn render_frame(&mut self) {
+        let started = Instant::now();
+        let mut display = if self.interlace {
+            // Start from the published frame so the other field persists
+            self.framebuffer.lock().map_or_else(|e| e.into_inner().clone(), |fb| fb.clone())
+        } else {
+            vec![0u8; DISPLAY_WIDTH * DISPLAY_HEIGHT * 4]
+        };
+        let planes = self.render_frame_into(&mut display);
+
+        if let Ok(mut fb) = self.framebuffer.lock() {
+            *fb = display;
+        }
+        if let Ok(mut info) = self.frame_info.lock() {
+            *info = FrameInfo { canvas_width: self.canvas_width, canvas_height: self.canvas_height };
+        }
+
+        let stats = FrameStats { duration: started.elapsed(), planes };
+        self.last_frame_stats = Some(stats);
+        if let Some(times) = &mut self.frame_times {
+            times.record(stats.duration);
+        }
+        if self.send_stats {
+            let _ = self.backchannel_tx.send(Backchannel::Stats(stats));
+        }
+    }
+
+    /// Render all planes into a caller-owned 640x480 RGBA buffer, bypassing
+    /// the shared framebuffer and its lock. With `interlace` only the current
+    /// field's scanlines are written. Returns the number of planes drawn.
+    ///
+    /// Panics if `display` is not exactly 640 * 480 * 4 bytes.
+    pub fn render_frame_into(&mut self, display: &mut [u8]) -> usize {
+        assert_eq!(display.len(), DISPLAY_WIDTH * DISPLAY_HEIGHT * 4, "display buffer must be 640x480 RGBA");
         let w = self.canvas_width;
         let h = self.canvas_height;
         let pixel_count = w as usize * h as usize;
 
         // Clear canvas buffer (only the used portion)
         self.canvas_buf[..pixel_count].fill(0);
+        self.canvas_buf_size = (w, h);
 
         // Render each plane into canvas buffer
-        for plane in self.planes.iter().flatten() {
-            match plane {
-                Plane::Mode1(p) => {
-                    let fresh_config = Mode1Config::from_xram(&self.xram, p.config_ptr);
-                    let current_plane = Mode1Plane { config: fresh_config, ..p.clone() };
-                    render_mode1(&current_plane, &self.xram, &mut self.canvas_buf[..pixel_count], w, h);
-                }
-                Plane::Mode3(p) => {
-                    let fresh_config = Mode3Config::from_xram(&self.xram, p.config_ptr);
-                    let current_plane = Mode3Plane { config: fresh_config, ..p.clone() };
-                    render_mode3(&current_plane, &self.xram, &mut self.canvas_buf[..pixel_count], w, h);
-                }
+        let mut palettes: [Option<ResolvedPalette>; 3] = [None, None, None];
+        let mut drawn = 0;
+        for (slot, plane) in self.planes.iter().enumerate() {
+            let Some(plane) = plane else { continue };
+            if !self.plane_enabled[slot] {
+                continue;
+            }
+            drawn += 1;
+            // Re-read the config so XRAM changes since MODE take effect
+            let current = plane.with_current_config(&self.xram);
+            let (mode, bpp, palette_ptr) = match &current {
+                Plane::Mode0(p) => (0, p.format.bits_per_pixel(), p.config.xram_palette_ptr),
+                Plane::Mode1(p) => (1, p.format.bpp(), p.config.xram_palette_ptr),
+                Plane::Mode3(p) => (3, p.format.bits_per_pixel(), p.config.xram_palette_ptr),
+            };
+
+            let started = Instant::now();
+            let cached = &mut self.palette_cache[slot];
+            if !cached.as_ref().is_some_and(|c| c.bpp == bpp && c.palette_ptr == palette_ptr) {
+                *cached = Some(CachedPalette {
+                    bpp,
+                    palette_ptr,
+                    range: xram_palette_range(bpp, palette_ptr),
+                    colors: resolve_palette(&self.xram, bpp, palette_ptr),
+                });
+                self.palette_rebuilds += 1;
+            }
+            let palette = &cached.as_ref().expect("filled above").colors;
+            let resolved = Instant::now();
+            let canvas = &mut self.canvas_buf[..pixel_count];
+            match &current {
+                Plane::Mode0(p) => render_mode0_with_palette(p, &self.xram, palette, canvas, w, h, w as usize),
+                Plane::Mode1(p) => render_mode1_with_palette(p, &self.xram, palette, canvas, w, h, w as usize),
+                Plane::Mode3(p) => render_mode3_with_palette(p, &self.xram, palette, canvas, w, h, w as usize),
+            }
+            if let Some(profile) = &mut self.profile {
+                profile.palette += resolved - started;
+                profile.modes[mode as usize] += resolved.elapsed();
+            }
+
+            if self.capture_palettes {
+                palettes[slot] = Some(ResolvedPalette { mode, bpp, palette_ptr, colors: palette.clone() });
             }
         }
+        if self.capture_palettes {
+            self.palettes = palettes;
+        }
 
         // Upscale canvas to 640x480 display buffer
-        let mut display = vec![0u8; DISPLAY_WIDTH * DISPLAY_HEIGHT * 4];
-        upscale_canvas(&self.canvas_buf[..pixel_count], w, h, &mut display);
-
-        if let Ok(mut fb) = self.framebuffer.lock() {
-            *fb = display;
+        let started = Instant::now();
+        let field = self.interlace.then_some((self.frame_count & 1) as usize);
+        let opts = UpscaleOptions { scale_mode: self.scale_mode, border: self.border_rgba, ..Default::default() };
+        upscale_canvas(&self.canvas_buf[..pixel_count], w, h, display, field, &opts);
+        // Post-process only the scanlines just written, never the kept field twice
+        for (y, row) in display.chunks_exact_mut(DISPLAY_WIDTH * 4).enumerate() {
+            if field.is_some_and(|f| y % 2 != f) {
+                continue;
+            }
+            self.post_process(row);
         }
+        if let Some(profile) = &mut self.profile {
+            profile.upscale += started.elapsed();
+            profile.frames += 1;
+        }
+        drawn
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crossbeam_channel::unbounded;
+
+    /// Write the size and pointers of a `vga_mode3_config_t` at `ptr`; wrap and
+    /// position keep their XRAM bytes (zero, so no wrap at (0, 0), in a fresh VGA).
+    fn set_mode3_config(xram: &mut [u8; 65536], ptr: u16, width: i16, height: i16, data_ptr: u16, palette_ptr: u16) {
+        use crate::ria_api::vga_mode3_config_t::*;
+        let mut field = |offset: u16, bytes: [u8; 2]| {
+            let at = (ptr + offset) as usize;
+            xram[at..at + 2].copy_from_slice(&bytes);
+        };
+        field(WIDTH_PX, width.to_le_bytes());
+        field(HEIGHT_PX, height.to_le_bytes());
+        field(XRAM_DATA_PTR, data_ptr.to_le_bytes());
+        field(XRAM_PALETTE_PTR, palette_ptr.to_le_bytes());
+    }
+
+    fn make_vga() -> (Vga, Sender<PixEvent>, Receiver<Backchannel>) {
+        let (pix_tx, pix_rx) = unbounded();
+        let (back_tx, back_rx) = unbounded();
+        let fb = Arc::new(Mutex::new(vec![0u8; DISPLAY_WIDTH * DISPLAY_HEIGHT * 4]));
+        (Vga::new(pix_rx, back_tx, fb), pix_tx, back_rx)
+    }
+
+    /// Send MODE xregs in the same descending order as the RIA's xreg.
+    fn program(vga: &mut Vga, values: [u16; 6]) {
+        for (i, &value) in values.iter().enumerate().rev() {
+            vga.handle_reg(PixRegWrite { device: 1, channel: 0, register: 1 + i as u8, value });
+        }
+    }
+
+    fn set_canvas(vga: &mut Vga, value: u16) {
+        vga.handle_reg(PixRegWrite { device: 1, channel: 0, register: 0, value });
+    }
+
+    #[test]
+    fn test_scanline_end_clamped_to_canvas() {
+        let (mut vga, _, back_rx) = make_vga();
+        set_canvas(&mut vga, 1); // 320x240
+        program(&mut vga, [3, 3, 0x0000, 0, 10, 1000]);
+        match &vga.planes[0] {
+            Some(Plane::Mode3(p)) => {
+                assert_eq!(p.scanline_begin, 10);
+                assert_eq!(p.scanline_end, 240);
+            }
+            other => panic!("expected Mode 3 plane, got {other:?}"),
+        }
+        assert_eq!(vga.scanline_clamps, 1);
+        let replies: Vec<_> = back_rx.try_iter().collect();
+        assert_eq!(replies, vec![Backchannel::Ack, Backchannel::AckPayload(0)]);
+    }
+
+    #[test]
+    fn test_scanline_end_zero_means_full_height() {
+        let (mut vga, _, _) = make_vga();
+        set_canvas(&mut vga, 1);
+        program(&mut vga, [1, 0, 0x0000, 0, 0, 0]);
+        match &vga.planes[0] {
+            Some(Plane::Mode1(p)) => assert_eq!(p.scanline_end, 0),
+            other => panic!("expected Mode 1 plane, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_inverted_scanline_range_naks() {
+        let (mut vga, _, back_rx) = make_vga();
+        set_canvas(&mut vga, 1);
+        back_rx.try_recv().unwrap(); // canvas ack
+        program(&mut vga, [3, 3, 0x0000, 0, 100, 50]);
+        assert!(vga.planes[0].is_none());
+        assert_eq!(back_rx.try_recv(), Ok(Backchannel::Nak));
+
+        // begin past the full canvas height with end = 0 is also empty
+        program(&mut vga, [1, 0, 0x0000, 0, 240, 0]);
+        assert!(vga.planes[0].is_none());
+        assert_eq!(back_rx.try_recv(), Ok(Backchannel::Nak));
+    }
+
+    #[test]
+    fn test_program_mode0() {
+        let (mut vga, _, back_rx) = make_vga();
+        set_canvas(&mut vga, 1);
+        program(&mut vga, [0, 0x13, 0x0100, 2, 0, 0]); // 8bpp, 16x16 tiles, plane 2
+        match &vga.planes[2] {
+            Some(Plane::Mode0(p)) => {
+                assert_eq!(p.format, ColorFormat::Bpp8);
+                assert_eq!(p.tile_size, 16);
+                assert_eq!(p.config_ptr, 0x0100);
+            }
+            other => panic!("expected Mode 0 plane, got {other:?}"),
+        }
+        let replies: Vec<_> = back_rx.try_iter().collect();
+        assert_eq!(replies, vec![Backchannel::Ack, Backchannel::AckPayload(2)]);
+    }
+
+    #[test]
+    fn test_capture_builtin_palette() {
+        let (mut vga, _, _back_rx) = make_vga();
+        vga.capture_palettes = true;
+        // Mode3Config at 0x0000: 8x1 bitmap, palette_ptr = 0xFFFF
+        set_mode3_config(&mut vga.xram, 0x0000, 8, 1, 0x0100, 0xFFFF);
+        set_canvas(&mut vga, 1);
+        program(&mut vga, [3, 3, 0x0000, 1, 0, 0]); // 8bpp on plane 1
+
+        vga.handle_event(PixEvent::FrameSync);
+
+        assert!(vga.palettes[0].is_none());
+        assert!(vga.palettes[2].is_none());
+        let captured = vga.palettes[1].as_ref().expect("plane 1 palette captured");
+        assert_eq!(captured.mode, 3);
+        assert_eq!(captured.source(), "builtin");
+        assert_eq!(captured.colors, palette::PALETTE_256.to_vec());
+    }
+
+    #[test]
+    fn test_config_latch_at_program_ignores_later_config_writes() {
+        let render_after_move = |latch: ConfigLatch| {
+            let (mut vga, _, _back_rx) = make_vga();
+            vga.config_latch = latch;
+            // Mode3Config at 0x0000: 8x1 bitmap whose first pixel is index 9
+            set_mode3_config(&mut vga.xram, 0x0000, 8, 1, 0x0100, 0xFFFF);
+            vga.xram[0x0100] = 9;
+            set_canvas(&mut vga, 1);
+            program(&mut vga, [3, 3, 0x0000, 0, 0, 0]);
+            // Move the bitmap 4 pixels right after MODE
+            vga.write_xram(2, 4);
+            vga.handle_event(PixEvent::FrameSync);
+            let (canvas, _, _) = vga.canvas_snapshot();
+            (canvas[0], canvas[4])
+        };
+        let red = palette::PALETTE_256[9];
+        assert_eq!(render_after_move(ConfigLatch::Live), (0, red));
+        assert_eq!(render_after_move(ConfigLatch::AtProgram), (red, 0));
+    }
+
+    #[test]
+    fn test_palette_rebuilt_only_when_palette_bytes_change() {
+        let (mut vga, _, _back_rx) = make_vga();
+        // Mode3Config at 0x0000: 8x1 8bpp bitmap at 0x0100, palette at 0x0200
+        set_mode3_config(&mut vga.xram, 0x0000, 8, 1, 0x0100, 0x0200);
+        vga.xram[0x0100] = 1;
+        vga.xram[0x0202..0x0204].copy_from_slice(&0x003Fu16.to_le_bytes()); // 1: opaque red
+        set_canvas(&mut vga, 1);
+        program(&mut vga, [3, 3, 0x0000, 0, 0, 0]);
+        let frame = |vga: &mut Vga| {
+            vga.handle_event(PixEvent::FrameSync);
+            (vga.palette_rebuilds, vga.canvas_snapshot().0[0])
+        };
+
+        assert_eq!(frame(&mut vga), (1, 0xFF0000FF));
+        assert_eq!(frame(&mut vga), (1, 0xFF0000FF), "unchanged palette is reused");
+        vga.write_xram(0x0101, 1); // bitmap, not palette
+        assert_eq!(frame(&mut vga), (1, 0xFF0000FF));
+        // Index 1 becomes opaque green
+        vga.write_xram(0x0202, 0xE0);
+        vga.write_xram(0x0203, 0x07);
+        assert_eq!(frame(&mut vga), (2, 0x00FF00FF));
+        assert_eq!(frame(&mut vga), (2, 0x00FF00FF));
+    }
+
+    #[test]
+    fn test_frame_info_published_with_frame() {
+        let (mut vga, _, _back_rx) = make_vga();
+        let info = vga.frame_info();
+        set_canvas(&mut vga, 2); // 320x180
+        vga.handle_event(PixEvent::FrameSync);
+        assert_eq!(*info.lock().unwrap(), FrameInfo { canvas_width: 320, canvas_height: 180 });
+    }
+
+    #[test]
+    fn test_canvas_snapshot_native_resolution() {
+        let (mut vga, _, _back_rx) = make_vga();
+        assert_eq!(vga.canvas_snapshot(), (&[][..], 0, 0));
+        // Mode3Config at 0x0000: 16x8 8bpp bitmap at 0x0100, built-in palette
+        set_mode3_config(&mut vga.xram, 0x0000, 16, 8, 0x0100, 0xFFFF);
+        vga.xram[0x0100 + 5 * 16 + 7] = 9; // (7, 5): bright red
+        set_canvas(&mut vga, 1); // 320x240
+        program(&mut vga, [3, 3, 0x0000, 0, 0, 0]);
+
+        vga.handle_event(PixEvent::FrameSync);
+        let (canvas, w, h) = vga.canvas_snapshot();
+        assert_eq!((w, h, canvas.len()), (320, 240, 320 * 240));
+        assert_eq!(canvas[5 * 320 + 7], palette::PALETTE_256[9]);
+        assert_eq!(canvas[5 * 320 + 8], 0);
+    }
+
+    #[test]
+    fn test_plane_enable_mask() {
+        let (mut vga, _, _back_rx) = make_vga();
+        // Mode3Config at 0x0000: 16x8 8bpp bitmap at 0x0100, built-in palette
+        set_mode3_config(&mut vga.xram, 0x0000, 16, 8, 0x0100, 0xFFFF);
+        vga.xram[0x0100] = 9;
+        set_canvas(&mut vga, 1);
+        program(&mut vga, [3, 3, 0x0000, 1, 0, 0]);
+        let set_mask = |vga: &mut Vga, value| {
+            vga.handle_reg(PixRegWrite { device: 1, channel: 15, register: PLANE_ENABLE_REG, value });
+        };
+
+        set_mask(&mut vga, 0b101);
+        vga.handle_event(PixEvent::FrameSync);
+        assert!(vga.planes[1].is_some(), "disabling keeps the plane");
+        assert!(vga.canvas_snapshot().0.iter().all(|&px| px == 0));
+        assert_eq!(vga.last_frame_stats().unwrap().planes, 0);
+
+        set_mask(&mut vga, 0b010);
+        vga.handle_event(PixEvent::FrameSync);
+        assert_eq!(vga.canvas_snapshot().0[0], palette::PALETTE_256[9]);
+        assert_eq!(vga.last_frame_stats().unwrap().planes, 1);
+
+        // Programming a plane enables it again
+        set_mask(&mut vga, 0);
+        program(&mut vga, [3, 3, 0x0000, 1, 0, 0]);
+        assert_eq!(vga.plane_enabled, [false, true, false]);
+    }
+
+    #[test]
+    fn test_with_xram_renders_prepared_image() {
+        let mut xram = Box::new([0u8; 65536]);
+        // Mode3Config at 0x0200: 4x2 8bpp bitmap at 0x1000, built-in palette
+        set_mode3_config(&mut xram, 0x0200, 4, 2, 0x1000, 0xFFFF);
+        xram[0x1000 + 4 + 3] = 12; // (3, 1): bright blue
+
+        let (_pix_tx, pix_rx) = unbounded();
+        let (back_tx, _back_rx) = unbounded();
+        let fb = Arc::new(Mutex::new(vec![0u8; DISPLAY_WIDTH * DISPLAY_HEIGHT * 4]));
+        let mut vga = Vga::with_xram(pix_rx, back_tx, fb, xram);
+        set_canvas(&mut vga, 1);
+        program(&mut vga, [3, 3, 0x0200, 0, 0, 0]);
+
+        vga.handle_event(PixEvent::FrameSync);
+        let (canvas, w, _) = vga.canvas_snapshot();
+        assert_eq!(canvas[w as usize + 3], palette::PALETTE_256[12]);
+        assert_eq!(canvas.iter().filter(|&&px| px != 0).count(), 1);
+    }
+
+    #[test]
+    fn test_interlaced_fields_fill_all_scanlines() {
+        let (mut vga, _, _back_rx) = make_vga();
+        vga.interlace = true;
+        // Mode3Config at 0x0000: 320x240 1bpp bitmap at 0x0100, every pixel set
+        set_mode3_config(&mut vga.xram, 0x0000, 320, 240, 0x0100, 0xFFFF);
+        vga.xram[0x0100..0x0100 + 40 * 240].fill(0xFF);
+        set_canvas(&mut vga, 1);
+        program(&mut vga, [3, 0, 0x0000, 0, 0, 0]);
+
+        let lit_rows = |vga: &Vga| -> Vec<bool> {
+            let fb = vga.framebuffer.lock().unwrap();
+            fb.chunks_exact(DISPLAY_WIDTH * 4).map(|row| row.chunks_exact(4).all(|px| px[3] != 0)).collect()
+        };
+
+        vga.handle_event(PixEvent::FrameSync);
+        let rows = lit_rows(&vga);
+        assert!(rows.iter().step_by(2).all(|&lit| lit), "even field drawn");
+        assert!(rows.iter().skip(1).step_by(2).all(|&lit| !lit), "odd field not yet drawn");
+
+        vga.handle_event(PixEvent::FrameSync);
+        assert!(lit_rows(&vga).iter().all(|&lit| lit), "two fields fill every scanline");
+    }
+
+    #[test]
+    fn test_frame_stats_recorded_per_render() {
+        let (mut vga, _, back_rx) = make_vga();
+        assert_eq!(vga.last_frame_stats(), None);
+        set_canvas(&mut vga, 1);
+        program(&mut vga, [3, 3, 0x0000, 0, 0, 0]);
+        program(&mut vga, [1, 0, 0x0100, 2, 0, 0]);
+        back_rx.try_iter().for_each(drop);
+        vga.send_stats = true;
+        vga.frame_times = Some(FrameTimes::default());
+
+        vga.handle_event(PixEvent::FrameSync);
+        let stats = vga.last_frame_stats().expect("stats after a render");
+        assert!(!stats.duration.is_zero());
+        assert_eq!(stats.planes, 2);
+        assert!(back_rx.try_iter().any(|m| m == Backchannel::Stats(stats)));
+
+        vga.handle_event(PixEvent::FrameSync);
+        let times = vga.frame_times.as_ref().unwrap();
+        assert_eq!(times.frames, 2);
+        assert!(times.min <= times.max && !times.min.is_zero());
+    }
+
+    #[test]
+    fn test_unknown_canvas_code_naks() {
+        let (mut vga, _, back_rx) = make_vga();
+        set_canvas(&mut vga, 2); // 320x180
+        program(&mut vga, [3, 3, 0x0000, 0, 0, 0]);
+        back_rx.try_iter().for_each(drop);
+
+        set_canvas(&mut vga, 99);
+        assert_eq!(back_rx.try_recv(), Ok(Backchannel::Nak));
+        assert_eq!((vga.canvas_width, vga.canvas_height), (320, 180));
+        assert!(vga.planes[0].is_some(), "planes survive a rejected CANVAS");
+
+        set_canvas(&mut vga, 5);
+        assert_eq!(back_rx.try_recv(), Ok(Backchannel::Ack));
+        assert_eq!((vga.canvas_width, vga.canvas_height), (640, 240));
+    }
+
+    #[test]
+    fn test_mode_without_canvas_uses_640x480() {
+        let (mut vga, _, back_rx) = make_vga();
+        // Mode3Config at 0x0000: 1x1 bitmap, built-in palette
+        set_mode3_config(&mut vga.xram, 0x0000, 1, 1, 0x0100, 0xFFFF);
+        vga.xram[0x0100] = 9; // bright red
+        program(&mut vga, [3, 3, 0x0000, 0, 0, 0]);
+        assert_eq!(back_rx.try_recv(), Ok(Backchannel::AckPayload(0)));
+
+        vga.handle_event(PixEvent::FrameSync);
+        let info = *vga.frame_info().lock().unwrap();
+        assert_eq!(info, FrameInfo { canvas_width: 640, canvas_height: 480 });
+        // 1x scale: only display pixel (0,0) is red
+        let fb = vga.framebuffer.lock().unwrap();
+        assert_eq!(fb[0..4], palette::PALETTE_256[9].to_be_bytes());
+        assert_eq!(fb[4..8], [0; 4]);
+    }
+
+    #[test]
+    fn test_render_frame_into_matches_shared_framebuffer() {
+        let (mut vga, _, _back_rx) = make_vga();
+        // Mode3Config at 0x0000: 8x2 bitmap of 8bpp indices, built-in palette
+        set_mode3_config(&mut vga.xram, 0x0000, 8, 2, 0x0100, 0xFFFF);
+        for i in 0..16 {
+            vga.xram[0x0100 + i] = i as u8 + 1;
+        }
+        set_canvas(&mut vga, 1);
+        program(&mut vga, [3, 3, 0x0000, 0, 0, 0]);
+
+        vga.handle_event(PixEvent::FrameSync);
+        let shared = vga.framebuffer.lock().unwrap().clone();
+
+        // Stale contents must be fully overwritten
+        let mut display = vec![0xAAu8; DISPLAY_WIDTH * DISPLAY_HEIGHT * 4];
+        vga.render_frame_into(&mut display);
+        assert!(shared.iter().any(|&b| b != 0), "frame has visible content");
+        assert_eq!(display, shared);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_to_image() {
+        let (vga, _, _back_rx) = make_vga();
+        vga.framebuffer.lock().unwrap()[(DISPLAY_WIDTH + 2) * 4..(DISPLAY_WIDTH + 3) * 4]
+            .copy_from_slice(&[0x11, 0x22, 0x33, 0xFF]);
+        let img = vga.to_image();
+        assert_eq!(img.dimensions(), (640, 480));
+        assert_eq!(img.get_pixel(2, 1).0, [0x11, 0x22, 0x33, 0xFF]);
+        assert!(framebuffer_to_image(&[0; 16]).is_none());
+    }
+
+    #[test]
+    fn test_profile_accumulates_stage_times() {
+        let (mut vga, _, _back_rx) = make_vga();
+        vga.profile = Some(RenderProfile::default());
+        // Full-canvas 8bpp Mode 3 plane so every stage does real work
+        set_mode3_config(&mut vga.xram, 0x0000, 320, 180, 0x0100, 0xFFFF);
+        set_canvas(&mut vga, 2);
+        program(&mut vga, [3, 3, 0x0000, 0, 0, 0]);
+
+        vga.handle_event(PixEvent::FrameSync);
+        let profile = vga.profile.as_ref().unwrap();
+        assert_eq!(profile.frames, 1);
+        assert!(!profile.palette.is_zero());
+        assert!(!profile.modes[3].is_zero());
+        assert!(!profile.upscale.is_zero());
+        assert!(profile.report().contains("render_mode3"));
+    }
+
+    #[test]
+    fn test_frame_info_canvas_coord() {
+        let info = FrameInfo { canvas_width: 320, canvas_height: 180 };
+        assert_eq!(info.canvas_coord(0, 0), Some((0, 0)));
+        assert_eq!(info.canvas_coord(639, 359), Some((319, 179)));
+        assert_eq!(info.canvas_coord(100, 360), None); // letterbox
+        let info = FrameInfo { canvas_width: 640, canvas_height: 480 };
+        assert_eq!(info.canvas_coord(639, 479), Some((639, 479)));
+    }
+
+    #[test]
+    fn test_xram_seq_gap_detected() {
+        let (mut vga, _, _back_rx) = make_vga();
+        for seq in [0, 1, 3] {
+            vga.handle_event(PixEvent::Xram(XramWrite { addr: seq as u16, data: 1, seq }));
+        }
+        assert_eq!(vga.xram_seq_errors, 1);
+        assert_eq!(vga.xram[3], 1);
+    }
+
+    #[test]
+    fn test_xram_mask_mirrors_writes() {
+        let (mut vga, _, _back_rx) = make_vga();
+        vga.xram_mask = 0x7FFF;
+        vga.handle_event(PixEvent::Xram(XramWrite { addr: 0x8010, data: 7, seq: 0 }));
+        assert_eq!(vga.xram[0x0010], 7);
+        assert_eq!(vga.xram[0x8010], 7);
+
+        // Full mask: no aliases
+        vga.xram_mask = 0xFFFF;
+        vga.handle_event(PixEvent::Xram(XramWrite { addr: 0x8020, data: 9, seq: 1 }));
+        assert_eq!(vga.xram[0x8020], 9);
+        assert_eq!(vga.xram[0x0020], 0);
+    }
+
+    #[test]
+    fn test_half_rate_renders_every_other_frame() {
+        let (mut vga, _, back_rx) = make_vga();
+        vga.half_rate = true;
+        for _ in 0..4 {
+            vga.handle_event(PixEvent::FrameSync);
+        }
+        assert_eq!(vga.frames_rendered, 2);
+        // VSYNC still advances at the full rate by default
+        assert_eq!(back_rx.try_iter().count(), 4);
+    }
+
+    #[test]
+    fn test_half_rate_vsync_halved() {
+        let (mut vga, _, back_rx) = make_vga();
+        vga.half_rate = true;
+        vga.half_rate_vsync = true;
+        for _ in 0..4 {
+            vga.handle_event(PixEvent::FrameSync);
+        }
+        assert_eq!(vga.frames_rendered, 2);
+        let vsyncs: Vec<_> = back_rx.try_iter().collect();
+        assert_eq!(vsyncs, vec![Backchannel::Vsync(0x81), Backchannel::Vsync(0x82)]);
+    }
+
+    #[test]
+    fn test_full_rate_renders_every_frame() {
+        let (mut vga, _, _) = make_vga();
+        for _ in 0..4 {
+            vga.handle_event(PixEvent::FrameSync);
+        }
+        assert_eq!(vga.frames_rendered, 4);
+    }
+
+    #[test]
+    fn test_premultiply_alpha() {
+        let mut px = [255u8, 0, 0, 128, 10, 20, 30, 255, 200, 200, 200, 0];
+        premultiply_alpha(&mut px);
+        assert_eq!(px[0..4], [128, 0, 0, 128]);   // 50% red
+        assert_eq!(px[4..8], [10, 20, 30, 255]);  // opaque unchanged
+        assert_eq!(px[8..12], [0, 0, 0, 0]);      // transparent zeroed
+    }
+
+    #[test]
+    fn test_validate_layout_overlapping_planes() {
+        let (mut vga, _, _back_rx) = make_vga();
+        // Plane 0 at config 0x0000, plane 1 at 0x0100: both 320x240 1bpp
+        // (9600 bytes), with plane 1's bitmap starting inside plane 0's.
+        for (config, data) in [(0x0000usize, 0x1000u16), (0x0100, 0x2000)] {
+            vga.xram[config + 6..config + 8].copy_from_slice(&320i16.to_le_bytes());
+            vga.xram[config + 8..config + 10].copy_from_slice(&240i16.to_le_bytes());
+            vga.xram[config + 10..config + 12].copy_from_slice(&data.to_le_bytes());
+        }
+        set_canvas(&mut vga, 1);
+        program(&mut vga, [3, 0, 0x0000, 0, 0, 0]);
+        assert!(vga.validate_layout().is_empty(), "disjoint until plane 1 is added");
+        program(&mut vga, [3, 0, 0x0100, 1, 0, 0]);
+
+        let warnings = vga.validate_layout();
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        let layout::LayoutWarning::Overlap { a, b } = warnings[0];
+        assert_eq!((a.plane, a.kind, a.start, a.end), (0, layout::RegionKind::Data, 0x1000, 0x1000 + 9600));
+        assert_eq!((b.plane, b.kind, b.start), (1, layout::RegionKind::Data, 0x2000));
+    }
+
+    #[test]
+    fn test_plane_summary() {
+        let (mut vga, _, _back_rx) = make_vga();
+        assert_eq!(vga.active_plane_count(), 0);
+        set_canvas(&mut vga, 1);
+        program(&mut vga, [3, 3, 0x0000, 0, 0, 120]);
+        program(&mut vga, [1, 8, 0x0100, 2, 100, 0]);
+
+        assert_eq!(vga.active_plane_count(), 2);
+        assert_eq!(
+            vga.plane_summary(),
+            vec![
+                PlaneSummary { slot: 0, mode: 3, format: "Bpp8".into(), scanline_begin: 0, scanline_end: 120 },
+                PlaneSummary { slot: 2, mode: 1, format: "Bpp1_8x16".into(), scanline_begin: 100, scanline_end: 0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spawn_shutdown_joins_with_final_frame() {
+        let (pix_tx, pix_rx) = unbounded();
+        let (back_tx, _back_rx) = unbounded();
+        let fb = Arc::new(Mutex::new(vec![0u8; DISPLAY_WIDTH * DISPLAY_HEIGHT * 4]));
+        let handle = Vga::new(pix_rx, back_tx, fb.clone()).spawn();
+
+        // 1x1 8bpp bitmap on a 640x480 canvas, never followed by a FrameSync
+        let xram = [(6, 1), (8, 1), (10, 0x00), (11, 0x01), (12, 0xFF), (13, 0xFF), (0x0100, 9)];
+        for (seq, &(addr, data)) in xram.iter().enumerate() {
+            pix_tx.send(PixEvent::Xram(XramWrite { addr, data, seq: seq as u64 })).unwrap();
+        }
+        for (register, value) in [(3u8, 0u16), (2, 3), (1, 3)] {
+            pix_tx.send(PixEvent::Reg(PixRegWrite { device: 1, channel: 0, register, value })).unwrap();
+        }
+        pix_tx.send(PixEvent::Shutdown).unwrap();
+
+        // pix_tx is still alive: the thread stops on Shutdown, not disconnect
+        let vga = handle.join().expect("VGA thread joins");
+        assert_eq!(vga.frames_rendered, 0);
+        assert_eq!(fb.lock().unwrap()[0..4], palette::PALETTE_256[9].to_be_bytes());
+        drop(pix_tx);
+    }
+
+    #[test]
+    fn test_gamma_identity() {
+        let lut = GammaConfig::default().lut();
+        assert!(lut.iter().enumerate().all(|(i, &v)| v as usize == i));
+    }
+
+    #[test]
+    fn test_gamma_darkens_mid_grey() {
+        // 255 * (128/255)^2.2 = 55.97
+        let lut = GammaConfig { gamma: 2.2 }.lut();
+        assert_eq!((lut[0], lut[128], lut[255]), (0, 56, 255));
+
+        let mut px = [128u8, 128, 128, 128];
+        apply_gamma(&mut px, &lut);
+        assert_eq!(px, [56, 56, 56, 128], "alpha unchanged");
+    }
+
+    #[test]
+    fn test_set_gamma_applies_to_frame() {
+        let (mut vga, _, _back_rx) = make_vga();
+        // 1x1 8bpp bitmap at 0x0100, built-in palette
+        set_mode3_config(&mut vga.xram, 0x0000, 1, 1, 0x0100, 0xFFFF);
+        vga.xram[0x0100] = 7; // light grey
+        set_canvas(&mut vga, 1);
+        program(&mut vga, [3, 3, 0x0000, 0, 0, 0]);
+
+        let mut linear = vec![0u8; DISPLAY_WIDTH * DISPLAY_HEIGHT * 4];
+        vga.render_frame_into(&mut linear);
+        vga.set_gamma(GammaConfig { gamma: 2.2 });
+        let mut corrected = vec![0u8; DISPLAY_WIDTH * DISPLAY_HEIGHT * 4];
+        vga.render_frame_into(&mut corrected);
+
+        let lut = GammaConfig { gamma: 2.2 }.lut();
+        assert!(linear[0] > 0 && linear[0] < 255);
+        assert_eq!(corrected[..4], [lut[linear[0] as usize], lut[linear[1] as usize], lut[linear[2] as usize], linear[3]]);
+
+        vga.set_gamma(GammaConfig::default());
+        assert!(vga.gamma_lut.is_none());
+    }
 
     #[test]
     fn test_upscale_1x() {
@@ -299,7 +1611,7 @@ mod tests {
         canvas[639 + 479 * 640] = 0x00FF00FF; // green at (639,479)
 
         let mut display = vec![0u8; 640 * 480 * 4];
-        upscale_canvas(&canvas, 640, 480, &mut display);
+        upscale_canvas(&canvas, 640, 480, &mut display, None, &UpscaleOptions::default());
 
         assert_eq!(display[0], 0xFF); // R
         assert_eq!(display[1], 0x00); // G
@@ -321,7 +1633,7 @@ mod tests {
         canvas[1] = 0xFF0000FF; // red at (1,0)
 
         let mut display = vec![0u8; 640 * 480 * 4];
-        upscale_canvas(&canvas, 320, 240, &mut display);
+        upscale_canvas(&canvas, 320, 240, &mut display, None, &UpscaleOptions::default());
 
         // (0,0) in canvas -> 2x2 block at (0,0),(1,0),(0,1),(1,1) in display
         for (dx, dy) in [(0usize, 0usize), (1, 0), (0, 1), (1, 1)] {
@@ -349,7 +1661,7 @@ mod tests {
         canvas[0] = 0xFF0000FF; // red at (0,0)
 
         let mut display = vec![0u8; 640 * 480 * 4];
-        upscale_canvas(&canvas, 320, 180, &mut display);
+        upscale_canvas(&canvas, 320, 180, &mut display, None, &UpscaleOptions::default());
 
         // (0,0) should be red
         assert_eq!(display[0], 0xFF);
@@ -362,4 +1674,72 @@ mod tests {
         assert_eq!(display[idx + 2], 0x00);
         assert_eq!(display[idx + 3], 0x00);
     }
+
+    #[test]
+    fn test_stretch_fill_covers_display() {
+        // 320x180: 2x horizontal, 480/180 vertical, no border scanlines
+        let canvas: Vec<u32> = (0..180u32).flat_map(|y| [(y << 8) | 0xFF; 320]).collect();
+        let mut display = vec![0u8; 640 * 480 * 4];
+        let opts = UpscaleOptions { scale_mode: ScaleMode::StretchFill, ..Default::default() };
+        upscale_canvas(&canvas, 320, 180, &mut display, None, &opts);
+
+        for (y, row) in display.chunks_exact(640 * 4).enumerate() {
+            let source_row = (y * 180 / 480) as u8;
+            assert!(row.chunks_exact(4).all(|px| px == [0, 0, source_row, 0xFF]), "display row {y}");
+        }
+        // Stretching skips no canvas row and keeps the last one at the bottom
+        assert_eq!(display[479 * 640 * 4 + 2], 179);
+    }
+
+    #[test]
+    fn test_downscale_box_filter() {
+        // 640x480 canvas -> 320x240 preview, each 2x2 block averaged
+        let mut canvas = vec![0u32; 640 * 480];
+        canvas[0] = 0xFF0000FF;   // red
+        canvas[1] = 0x00FF00FF;   // green
+        canvas[640] = 0x0000FFFF; // blue
+        canvas[641] = 0xFFFFFFFF; // white
+
+        let mut display = vec![0u8; 320 * 240 * 4];
+        upscale_canvas(&canvas, 640, 480, &mut display, None, &UpscaleOptions { output: Some((320, 240)), ..Default::default() });
+
+        // (255 + 0 + 0 + 255) / 4 = 127.5, rounded up
+        assert_eq!(display[..4], [0x80, 0x80, 0x80, 0xFF]);
+        assert_eq!(display[4..8], [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_render_preview_box_filters_canvas() {
+        let (mut vga, _, _back_rx) = make_vga();
+        assert!(vga.render_preview(160, 120).iter().all(|&b| b == 0), "nothing rendered yet");
+
+        // Mode3Config at 0x0000: 16x8 8bpp bitmap at 0x0100, built-in palette
+        set_mode3_config(&mut vga.xram, 0x0000, 16, 8, 0x0100, 0xFFFF);
+        for addr in [0x0100, 0x0101, 0x0110, 0x0111] {
+            vga.xram[addr] = 9;
+        }
+        vga.xram[0x0102] = 9;
+        set_canvas(&mut vga, 1); // 320x240
+        program(&mut vga, [3, 3, 0x0000, 0, 0, 0]);
+        vga.handle_event(PixEvent::FrameSync);
+
+        let preview = vga.render_preview(160, 120);
+        assert_eq!(preview.len(), 160 * 120 * 4);
+        assert_eq!(preview[..4], palette::PALETTE_256[9].to_be_bytes());
+        // One of four pixels opaque: alpha 255 / 4 rounded
+        assert_eq!(preview[7], 0x40);
+    }
+
+    #[test]
+    fn test_letterbox_border_color() {
+        let (mut vga, _, _back_rx) = make_vga();
+        vga.border_rgba = [0x00, 0x00, 0xFF, 0xFF];
+        set_canvas(&mut vga, 2); // 320x180
+        let mut display = vec![0u8; DISPLAY_WIDTH * DISPLAY_HEIGHT * 4];
+        vga.render_frame_into(&mut display);
+
+        let row = |y: usize| &display[y * DISPLAY_WIDTH * 4..(y + 1) * DISPLAY_WIDTH * 4];
+        assert!(row(400).chunks_exact(4).all(|px| px == [0x00, 0x00, 0xFF, 0xFF]));
+        assert!(row(359).iter().all(|&b| b == 0), "canvas area is not the border");
+    }
 }
@@ -0,0 +1,323 @@
+use super::mode3::{get_pixel, ColorFormat};
+use super::palette::resolve_palette;
+use super::ConfigLatch;
+
+/// Size of one sprite entry in the XRAM sprite list.
+pub const SPRITE_SIZE: usize = 6;
+
+/// Mode 0 (sprite) configuration, read from XRAM at config_ptr.
+///
+/// This layout is defined by the emulator (8 bytes):
+///   uint16_t sprite_count     (2 bytes, offset 0)
+///   uint16_t xram_sprite_ptr  (2 bytes, offset 2) — array of `Sprite` entries
+///   uint16_t xram_tile_ptr    (2 bytes, offset 4) — tile bitmaps, indexed by `Sprite::tile`
+///   uint16_t xram_palette_ptr (2 bytes, offset 6)
+#[derive(Debug, Clone)]
+pub struct Mode0Config {
+    pub sprite_count: u16,
+    pub xram_sprite_ptr: u16,
+    pub xram_tile_ptr: u16,
+    pub xram_palette_ptr: u16,
+}
+
+impl Mode0Config {
+    /// Read config from XRAM at the given pointer.
+    pub fn from_xram(xram: &[u8; 65536], ptr: u16) -> Self {
+        let p = ptr as usize;
+        if p + 8 > 65536 {
+            return Self {
+                sprite_count: 0,
+                xram_sprite_ptr: 0,
+                xram_tile_ptr: 0,
+                xram_palette_ptr: 0,
+            };
+        }
+        Self {
+            sprite_count: u16::from_le_bytes([xram[p], xram[p + 1]]),
+            xram_sprite_ptr: u16::from_le_bytes([xram[p + 2], xram[p + 3]]),
+            xram_tile_ptr: u16::from_le_bytes([xram[p + 4], xram[p + 5]]),
+            xram_palette_ptr: u16::from_le_bytes([xram[p + 6], xram[p + 7]]),
+        }
+    }
+}
+
+/// One entry of the sprite list (6 bytes):
+///   int16_t x_pos_px   (2 bytes, offset 0)
+///   int16_t y_pos_px   (2 bytes, offset 2)
+///   uint8_t tile       (1 byte, offset 4)
+///   uint8_t attributes (1 byte, offset 5)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sprite {
+    pub x_pos_px: i16,
+    pub y_pos_px: i16,
+    pub tile: u8,
+    pub attributes: u8,
+}
+
+impl Sprite {
+    /// Attribute bit 0: draw palette index 0 instead of treating it as transparent.
+    pub const OPAQUE: u8 = 0x01;
+    /// Attribute bit 7: sprite is not drawn.
+    pub const HIDDEN: u8 = 0x80;
+
+    /// Read a sprite entry. The caller must ensure `ptr + SPRITE_SIZE` fits in XRAM.
+    pub fn from_xram(xram: &[u8; 65536], ptr: usize) -> Self {
+        Self {
+            x_pos_px: i16::from_le_bytes([xram[ptr], xram[ptr + 1]]),
+            y_pos_px: i16::from_le_bytes([xram[ptr + 2], xram[ptr + 3]]),
+            tile: xram[ptr + 4],
+            attributes: xram[ptr + 5],
+        }
+    }
+}
+
+/// A programmed Mode 0 (sprite) plane.
+#[derive(Debug, Clone)]
+pub struct Mode0Plane {
+    pub config: Mode0Config,
+    /// Indexed color format of the tile bitmaps (16bpp is not supported).
+    pub format: ColorFormat,
+    /// Tile width and height in pixels (8 or 16).
+    pub tile_size: u8,
+    pub scanline_begin: u16,
+    pub scanline_end: u16,
+    pub config_ptr: u16,
+    /// When `config` is read from XRAM. Emulator extension; MODE sets
+    /// `Vga::config_latch`.
+    pub config_latch: ConfigLatch,
+}
+
+/// Decode Mode 0 attributes: bits 3:0 are a Mode 3 color format,
+/// bit 4 selects 16x16 tiles (otherwise 8x8).
+pub fn mode0_attr(attr: u16) -> Option<(ColorFormat, u8)> {
+    let format = ColorFormat::from_attr(attr & 0x0F)?;
+    if format == ColorFormat::Bpp16 || attr & !0x1F != 0 {
+        return None;
+    }
+    let tile_size = if attr & 0x10 != 0 { 16 } else { 8 };
+    Some((format, tile_size))
+}
+
+/// Render a Mode 0 sprite plane into the framebuffer.
+///
+/// Sprites are drawn in list order, so later sprites cover earlier ones.
+/// Palette index 0 is transparent unless the sprite has the `OPAQUE` attribute,
+/// and pixels whose palette entry has alpha 0 are never drawn.
+#[allow(dead_code)]
+pub fn render_mode0(
+    plane: &Mode0Plane,
+    xram: &[u8; 65536],
+    framebuffer: &mut [u32],
+    canvas_width: u16,
+    canvas_height: u16,
+) {
+    let palette = resolve_palette(xram, plane.format.bits_per_pixel(), plane.config.xram_palette_ptr);
+    render_mode0_with_palette(plane, xram, &palette, framebuffer, canvas_width, canvas_height, canvas_width as usize);
+}
+
+/// `render_mode0` with the plane's palette already resolved, into a
+/// framebuffer whose rows are `fb_stride` pixels apart. A stride wider than
+/// `canvas_width` draws the canvas into the top-left of a larger buffer.
+pub fn render_mode0_with_palette(
+    plane: &Mode0Plane,
+    xram: &[u8; 65536],
+    palette: &[u32],
+    framebuffer: &mut [u32],
+    canvas_width: u16,
+    canvas_height: u16,
+    fb_stride: usize,
+) {
+    debug_assert!(fb_stride >= canvas_width as usize, "rows would overlap");
+    let cfg = &plane.config;
+    let tile_size = plane.tile_size as i32;
+    let bpp = plane.format.bits_per_pixel();
+    let sizeof_row = (tile_size as u32 * bpp).div_ceil(8) as usize;
+    let sizeof_tile = sizeof_row * tile_size as usize;
+
+    // Sprite list must fit in XRAM
+    let list_end = cfg.xram_sprite_ptr as usize + cfg.sprite_count as usize * SPRITE_SIZE;
+    if list_end > 0x10000 {
+        return;
+    }
+
+    let y_start = plane.scanline_begin as i32;
+    let y_end = if plane.scanline_end == 0 {
+        canvas_height as i32
+    } else {
+        plane.scanline_end as i32
+    }
+    .min(canvas_height as i32);
+
+    for i in 0..cfg.sprite_count as usize {
+        let sprite = Sprite::from_xram(xram, cfg.xram_sprite_ptr as usize + i * SPRITE_SIZE);
+        if sprite.attributes & Sprite::HIDDEN != 0 {
+            continue;
+        }
+
+        let tile_offset = cfg.xram_tile_ptr as usize + sprite.tile as usize * sizeof_tile;
+        if tile_offset + sizeof_tile > 0x10000 {
+            continue;
+        }
+
+        for row in 0..tile_size {
+            let scanline = sprite.y_pos_px as i32 + row;
+            if scanline < y_start || scanline >= y_end {
+                continue;
+            }
+            let row_offset = tile_offset + row as usize * sizeof_row;
+            let row_data = &xram[row_offset..row_offset + sizeof_row];
+
+            for col in 0..tile_size {
+                let screen_x = sprite.x_pos_px as i32 + col;
+                if screen_x < 0 || screen_x >= canvas_width as i32 {
+                    continue;
+                }
+                let Some(idx) = get_pixel(row_data, col as usize, &plane.format) else {
+                    continue;
+                };
+                if idx == 0 && sprite.attributes & Sprite::OPAQUE == 0 {
+                    continue;
+                }
+                let rgba = palette.get(idx as usize).copied().unwrap_or(0);
+                if rgba & 0xFF != 0 {
+                    let fb_idx = scanline as usize * fb_stride + screen_x as usize;
+                    framebuffer[fb_idx] = rgba;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vga::palette::PALETTE_256;
+
+    const CONFIG_PTR: u16 = 0x0000;
+    const SPRITE_PTR: u16 = 0x0010;
+    const TILE_PTR: u16 = 0x0100;
+
+    fn make_xram(sprites: &[Sprite]) -> Box<[u8; 65536]> {
+        let mut xram = Box::new([0u8; 65536]);
+        let p = CONFIG_PTR as usize;
+        xram[p..p + 2].copy_from_slice(&(sprites.len() as u16).to_le_bytes());
+        xram[p + 2..p + 4].copy_from_slice(&SPRITE_PTR.to_le_bytes());
+        xram[p + 4..p + 6].copy_from_slice(&TILE_PTR.to_le_bytes());
+        xram[p + 6..p + 8].copy_from_slice(&0xFFFFu16.to_le_bytes()); // built-in palette
+        for (i, s) in sprites.iter().enumerate() {
+            let o = SPRITE_PTR as usize + i * SPRITE_SIZE;
+            xram[o..o + 2].copy_from_slice(&s.x_pos_px.to_le_bytes());
+            xram[o + 2..o + 4].copy_from_slice(&s.y_pos_px.to_le_bytes());
+            xram[o + 4] = s.tile;
+            xram[o + 5] = s.attributes;
+        }
+        xram
+    }
+
+    fn make_plane(xram: &[u8; 65536]) -> Mode0Plane {
+        Mode0Plane {
+            config: Mode0Config::from_xram(xram, CONFIG_PTR),
+            format: ColorFormat::Bpp8,
+            tile_size: 8,
+            scanline_begin: 0,
+            scanline_end: 0,
+            config_ptr: CONFIG_PTR,
+            config_latch: ConfigLatch::Live,
+        }
+    }
+
+    #[test]
+    fn test_mode0_sprite_at_position() {
+        let sprite = Sprite { x_pos_px: 100, y_pos_px: 50, tile: 1, attributes: 0 };
+        let mut xram = make_xram(&[sprite]);
+        // Tile 1 (8bpp, 64 bytes): diagonal of color 9, everything else index 0
+        let tile = TILE_PTR as usize + 64;
+        for i in 0..8 {
+            xram[tile + i * 8 + i] = 9;
+        }
+
+        let plane = make_plane(&xram);
+        let mut fb = vec![0u32; 320 * 240];
+        render_mode0(&plane, &xram, &mut fb, 320, 240);
+
+        for y in 0..240usize {
+            for x in 0..320usize {
+                let on_diagonal = (100..108).contains(&x) && y == x - 50;
+                let expected = if on_diagonal { PALETTE_256[9] } else { 0 };
+                assert_eq!(fb[y * 320 + x], expected, "pixel ({x},{y})");
+            }
+        }
+    }
+
+    #[test]
+    fn test_mode0_opaque_and_hidden_attributes() {
+        let sprites = [
+            Sprite { x_pos_px: 0, y_pos_px: 0, tile: 0, attributes: Sprite::OPAQUE },
+            Sprite { x_pos_px: 8, y_pos_px: 0, tile: 0, attributes: Sprite::HIDDEN },
+        ];
+        let mut xram = make_xram(&sprites);
+        // Tile 0 is all index 16 (opaque black) except index 0 at (0,0)
+        xram[TILE_PTR as usize..TILE_PTR as usize + 64].fill(16);
+        xram[TILE_PTR as usize] = 0;
+        // Custom 8bpp palette where index 0 is opaque white
+        let pal = 0x2000usize;
+        xram[CONFIG_PTR as usize + 6..CONFIG_PTR as usize + 8]
+            .copy_from_slice(&(pal as u16).to_le_bytes());
+        xram[pal..pal + 2].copy_from_slice(&0xFFFFu16.to_le_bytes());
+        xram[pal + 32..pal + 34].copy_from_slice(&0x0020u16.to_le_bytes()); // 16: opaque black
+
+        let plane = make_plane(&xram);
+        let mut fb = vec![0u32; 16 * 8];
+        render_mode0(&plane, &xram, &mut fb, 16, 8);
+
+        assert_eq!(fb[0], 0xFFFFFFFF); // index 0 drawn by OPAQUE sprite
+        assert_eq!(fb[1], 0x000000FF);
+        assert!(fb[8..16].iter().all(|&px| px == 0)); // hidden sprite
+    }
+
+    #[test]
+    fn test_mode0_clips_at_canvas_edge() {
+        let sprite = Sprite { x_pos_px: -4, y_pos_px: 6, tile: 0, attributes: 0 };
+        let mut xram = make_xram(&[sprite]);
+        xram[TILE_PTR as usize..TILE_PTR as usize + 64].fill(9);
+
+        let plane = make_plane(&xram);
+        let mut fb = vec![0u32; 8 * 8];
+        render_mode0(&plane, &xram, &mut fb, 8, 8);
+
+        for y in 0..8usize {
+            for x in 0..8usize {
+                let expected = if x < 4 && y >= 6 { PALETTE_256[9] } else { 0 };
+                assert_eq!(fb[y * 8 + x], expected, "pixel ({x},{y})");
+            }
+        }
+    }
+
+    #[test]
+    fn test_mode0_fb_stride_clips_at_canvas_edge() {
+        // A sprite hanging off the right of an 8x8 canvas drawn into a
+        // 12-pixel-wide buffer: the columns past the canvas stay untouched
+        let sprite = Sprite { x_pos_px: 4, y_pos_px: 2, tile: 0, attributes: 0 };
+        let mut xram = make_xram(&[sprite]);
+        xram[TILE_PTR as usize..TILE_PTR as usize + 64].fill(9);
+
+        let plane = make_plane(&xram);
+        let mut fb = vec![0u32; 12 * 8];
+        render_mode0_with_palette(&plane, &xram, &PALETTE_256, &mut fb, 8, 8, 12);
+
+        for y in 0..8usize {
+            for x in 0..12usize {
+                let expected = if (4..8).contains(&x) && y >= 2 { PALETTE_256[9] } else { 0 };
+                assert_eq!(fb[y * 12 + x], expected, "pixel ({x},{y})");
+            }
+        }
+    }
+
+    #[test]
+    fn test_mode0_attr() {
+        assert_eq!(mode0_attr(3), Some((ColorFormat::Bpp8, 8)));
+        assert_eq!(mode0_attr(0x12), Some((ColorFormat::Bpp4Msb, 16)));
+        assert_eq!(mode0_attr(4), None); // 16bpp
+        assert_eq!(mode0_attr(0x23), None);
+    }
+}
@@ -1,5 +1,6 @@
 use super::font::{FONT8, FONT16};
 use super::palette::{resolve_palette, rgb565_to_rgba};
+use super::ConfigLatch;
 
 /// Mode 1 configuration, read from XRAM at config_ptr.
 /// Matches firmware mode1_config_t exactly (16 bytes):
@@ -12,6 +13,9 @@ use super::palette::{resolve_palette, rgb565_to_rgba};
 ///   uint16_t xram_data_ptr    (2 bytes, offset 10)
 ///   uint16_t xram_palette_ptr (2 bytes, offset 12)
 ///   uint16_t xram_font_ptr    (2 bytes, offset 14)
+///
+/// `advance_table` is an emulator extension that is not part of the XRAM struct;
+/// it comes from MODE xreg 7 and selects proportional glyph layout.
 #[derive(Debug, Clone)]
 pub struct Mode1Config {
     pub x_wrap: bool,
@@ -23,10 +27,14 @@ pub struct Mode1Config {
     pub xram_data_ptr: u16,
     pub xram_palette_ptr: u16,
     pub xram_font_ptr: u16,
+    /// XRAM address of a 256-byte table of glyph advances (1-8 px), or None
+    /// for fixed 8 px columns.
+    pub advance_table: Option<u16>,
 }
 
 /// Mode 1 format, encoding both font size and color depth.
 /// Matches firmware mode1_prog() attribute switch.
+#[allow(non_camel_case_types)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mode1Format {
     Bpp1_8x8,    // attr 0
@@ -79,7 +87,7 @@ impl Mode1Format {
     }
 
     /// Bits per pixel for palette resolution.
-    fn bpp(&self) -> u32 {
+    pub fn bpp(&self) -> u32 {
         match self {
             Self::Bpp1_8x8 | Self::Bpp1_8x16 => 1,
             Self::Bpp4r_8x8 | Self::Bpp4_8x8
@@ -98,6 +106,16 @@ pub struct Mode1Plane {
     pub scanline_begin: u16,
     pub scanline_end: u16,
     pub config_ptr: u16,
+    /// Blank scanlines below each character row, drawn in the cell's
+    /// background color. Emulator extension; negative values act as 0.
+    pub row_gap_px: i16,
+    /// 9-pixel cells like VGA text mode: the 9th column repeats the 8th for
+    /// `NINE_DOT_GLYPHS` (box drawing) and is background otherwise.
+    /// Emulator extension; ignored with a proportional advance table.
+    pub nine_dot: bool,
+    /// When `config` is read from XRAM. Emulator extension; MODE sets
+    /// `Vga::config_latch`.
+    pub config_latch: ConfigLatch,
 }
 
 impl Mode1Config {
@@ -115,6 +133,7 @@ impl Mode1Config {
                 xram_data_ptr: 0,
                 xram_palette_ptr: 0,
                 xram_font_ptr: 0,
+                advance_table: None,
             };
         }
         Self {
@@ -127,22 +146,85 @@ impl Mode1Config {
             xram_data_ptr: u16::from_le_bytes([xram[p + 10], xram[p + 11]]),
             xram_palette_ptr: u16::from_le_bytes([xram[p + 12], xram[p + 13]]),
             xram_font_ptr: u16::from_le_bytes([xram[p + 14], xram[p + 15]]),
+            advance_table: None,
+        }
+    }
+}
+
+/// Interpret MODE xreg 7 as an advance table pointer. 0xFFFF (and 0, the value
+/// left by programs that never send xreg 7) keep fixed-width layout, as does a
+/// table that would run past the end of XRAM.
+pub fn advance_table_ptr(xreg: u16) -> Option<u16> {
+    if xreg == 0 || xreg as usize + 256 > 0x10000 {
+        None
+    } else {
+        Some(xreg)
+    }
+}
+
+/// Glyphs whose rightmost font column is repeated into a `nine_dot` cell's
+/// 9th column, so CP437 box-drawing lines join across cells.
+pub const NINE_DOT_GLYPHS: std::ops::RangeInclusive<u8> = 0xB0..=0xDF;
+
+/// `bit_in_char` of a `nine_dot` cell's 9th column (bit "-1", wrapped).
+const NINTH_COLUMN: u8 = u8::MAX;
+
+/// Lay out one character row with proportional advances.
+/// Returns, for each pixel column of the row, the character column and the
+/// bit within the font byte (MSB first) that supplies it.
+fn proportional_row_layout(
+    xram: &[u8; 65536],
+    table_ptr: u16,
+    row_data_offset: usize,
+    width_chars: usize,
+    cell_size: usize,
+) -> Vec<(usize, u8)> {
+    let mut layout = Vec::with_capacity(width_chars * 8);
+    for char_col in 0..width_chars {
+        let cell_offset = row_data_offset + char_col * cell_size;
+        if cell_offset >= 0x10000 {
+            break;
+        }
+        let glyph_code = xram[cell_offset] as usize;
+        let advance = xram[table_ptr as usize + glyph_code].clamp(1, 8);
+        for px in 0..advance {
+            layout.push((char_col, 7 - px));
         }
     }
+    layout
 }
 
 /// Resolve font: use XRAM font if pointer is in bounds, else built-in.
 /// Matches firmware mode1_get_font():
 ///   if (font_ptr <= 0x10000 - 256 * font_height) return &xram[font_ptr]
 ///   else return built-in
-fn resolve_font<'a>(xram: &'a [u8; 65536], font_ptr: u16, font_height: i16) -> &'a [u8] {
+///
+/// Always returns exactly `256 * font_height` bytes, so any glyph row and
+/// code index in render_mode1 is in bounds.
+fn resolve_font(xram: &[u8; 65536], font_ptr: u16, font_height: i16) -> &[u8] {
     let font_size = 256 * font_height as usize;
-    if (font_ptr as usize) + font_size <= 0x10000 {
+    let font: &[u8] = if (font_ptr as usize) + font_size <= 0x10000 {
         &xram[font_ptr as usize..font_ptr as usize + font_size]
     } else if font_height == 8 {
         &FONT8
     } else {
         &FONT16
+    };
+    debug_assert_eq!(font.len(), font_size);
+    font
+}
+
+/// 16bpp cell attribute bit 0: draw the glyph's bottom row in fg.
+/// Emulator extension; the firmware ignores the attribute byte.
+pub const ATTR_UNDERLINE: u8 = 0x01;
+/// 16bpp cell attribute bit 1: swap fg and bg. Emulator extension.
+pub const ATTR_REVERSE: u8 = 0x02;
+
+/// Attribute byte of a cell: byte[1] for the 16bpp formats, 0 otherwise.
+fn cell_attributes(xram: &[u8; 65536], format: &Mode1Format, cell_offset: usize) -> u8 {
+    match format {
+        Mode1Format::Bpp16_8x8 | Mode1Format::Bpp16_8x16 => xram[cell_offset + 1],
+        _ => 0,
     }
 }
 
@@ -188,7 +270,7 @@ fn resolve_cell_colors(
             (bg, fg)
         }
         Mode1Format::Bpp16_8x8 | Mode1Format::Bpp16_8x16 => {
-            // 16bpp: byte[1] = attributes (ignored), bytes[2..4] = fg_color, bytes[4..6] = bg_color
+            // 16bpp: byte[1] = attributes, bytes[2..4] = fg_color, bytes[4..6] = bg_color
             let fg_raw = u16::from_le_bytes([
                 xram[cell_offset + 2],
                 xram[cell_offset + 3],
@@ -197,7 +279,12 @@ fn resolve_cell_colors(
                 xram[cell_offset + 4],
                 xram[cell_offset + 5],
             ]);
-            (rgb565_to_rgba(bg_raw), rgb565_to_rgba(fg_raw))
+            let (bg, fg) = (rgb565_to_rgba(bg_raw), rgb565_to_rgba(fg_raw));
+            if xram[cell_offset + 1] & ATTR_REVERSE != 0 {
+                (fg, bg)
+            } else {
+                (bg, fg)
+            }
         }
     }
 }
@@ -208,6 +295,7 @@ fn resolve_cell_colors(
 /// B in 15:8, A in 7:0), laid out as canvas_width x canvas_height pixels.
 ///
 /// Pixels are only written when alpha is non-zero (opaque).
+#[allow(dead_code)]
 pub fn render_mode1(
     plane: &Mode1Plane,
     xram: &[u8; 65536],
@@ -215,16 +303,35 @@ pub fn render_mode1(
     canvas_width: u16,
     canvas_height: u16,
 ) {
+    let palette = resolve_palette(xram, plane.format.bpp(), plane.config.xram_palette_ptr);
+    render_mode1_with_palette(plane, xram, &palette, framebuffer, canvas_width, canvas_height, canvas_width as usize);
+}
+
+/// `render_mode1` with the plane's palette already resolved, into a
+/// framebuffer whose rows are `fb_stride` pixels apart. A stride wider than
+/// `canvas_width` draws the canvas into the top-left of a larger buffer.
+pub fn render_mode1_with_palette(
+    plane: &Mode1Plane,
+    xram: &[u8; 65536],
+    palette: &[u32],
+    framebuffer: &mut [u32],
+    canvas_width: u16,
+    canvas_height: u16,
+    fb_stride: usize,
+) {
+    debug_assert!(fb_stride >= canvas_width as usize, "rows would overlap");
     let cfg = &plane.config;
     let font_height = plane.format.font_height();
     let cell_size = plane.format.cell_size();
 
+    // Also keeps the wrapping rem_euclid calls below from dividing by zero
     if cfg.width_chars < 1 || cfg.height_chars < 1 {
         return;
     }
 
     // Bounds check: character data must fit in XRAM
-    let height_px = cfg.height_chars as i32 * font_height as i32;
+    let row_pitch = font_height as i32 + plane.row_gap_px.max(0) as i32;
+    let height_px = cfg.height_chars as i32 * row_pitch;
     let sizeof_row = cfg.width_chars as usize * cell_size;
     let sizeof_data = cfg.height_chars as usize * sizeof_row;
     if sizeof_data > 0x10000usize.saturating_sub(cfg.xram_data_ptr as usize) {
@@ -232,7 +339,6 @@ pub fn render_mode1(
     }
 
     let font = resolve_font(xram, cfg.xram_font_ptr, font_height);
-    let palette = resolve_palette(xram, plane.format.bpp(), cfg.xram_palette_ptr);
 
     let y_start = plane.scanline_begin as i32;
     let y_end = if plane.scanline_end == 0 {
@@ -241,7 +347,8 @@ pub fn render_mode1(
         plane.scanline_end as i32
     };
 
-    let width_px = cfg.width_chars as i32 * 8;
+    let cell_width = if plane.nine_dot { 9 } else { 8 };
+    let width_px = cfg.width_chars as i32 * cell_width;
 
     for scanline in y_start..y_end {
         if scanline < 0 || scanline >= canvas_height as i32 {
@@ -250,7 +357,7 @@ pub fn render_mode1(
 
         let mut row = scanline - cfg.y_pos_px as i32;
 
-        // Y wrapping on height_chars * font_height pixels
+        // Y wrapping on height_chars * row_pitch pixels
         if cfg.y_wrap {
             row = row.rem_euclid(height_px);
         }
@@ -259,15 +366,25 @@ pub fn render_mode1(
             continue;
         }
 
-        let char_row = row / font_height as i32;
-        let font_row_in_glyph = row & (font_height as i32 - 1);
+        let char_row = row / row_pitch;
+        let font_row_in_glyph = row % row_pitch;
+        // Scanlines past the glyph are the row gap: background only
+        let in_gap = font_row_in_glyph >= font_height as i32;
         let font_row_offset = (font_row_in_glyph as usize) * 256;
         let row_data_offset = cfg.xram_data_ptr as usize + char_row as usize * sizeof_row;
 
+        let layout = cfg.advance_table.map(|table_ptr| {
+            proportional_row_layout(xram, table_ptr, row_data_offset, cfg.width_chars as usize, cell_size)
+        });
+        let width_px = layout.as_ref().map_or(width_px, |l| l.len() as i32);
+        if width_px == 0 {
+            continue;
+        }
+
         for screen_x in 0..canvas_width as i32 {
             let mut col = screen_x - cfg.x_pos_px as i32;
 
-            // X wrapping on width_chars * 8 pixels
+            // X wrapping on the row's width in pixels
             if cfg.x_wrap {
                 col = col.rem_euclid(width_px);
             }
@@ -276,23 +393,38 @@ pub fn render_mode1(
                 continue;
             }
 
-            let char_col = col / 8;
-            let bit_in_char = 7 - (col & 7); // MSB first
+            let (char_col, bit_in_char) = match &layout {
+                Some(l) => l[col as usize],
+                // MSB first; NINTH_COLUMN past bit 0 in a nine_dot cell
+                None => ((col / cell_width) as usize, 7u8.wrapping_sub((col % cell_width) as u8)),
+            };
 
-            let cell_offset = row_data_offset + char_col as usize * cell_size;
+            let cell_offset = row_data_offset + char_col * cell_size;
             if cell_offset >= 0x10000 {
                 continue;
             }
 
-            let glyph_code = xram[cell_offset] as usize;
-            let font_byte = font[font_row_offset + glyph_code];
-            let bit = (font_byte >> bit_in_char) & 1;
+            let bit = if in_gap {
+                0
+            } else {
+                let glyph_code = xram[cell_offset];
+                let font_byte = font[font_row_offset + glyph_code as usize];
+                let underline = font_row_in_glyph == font_height as i32 - 1
+                    && cell_attributes(xram, &plane.format, cell_offset) & ATTR_UNDERLINE != 0;
+                if underline {
+                    1
+                } else if bit_in_char == NINTH_COLUMN {
+                    if NINE_DOT_GLYPHS.contains(&glyph_code) { font_byte & 1 } else { 0 }
+                } else {
+                    (font_byte >> bit_in_char) & 1
+                }
+            };
 
-            let (bg, fg) = resolve_cell_colors(xram, &plane.format, cell_offset, &palette);
+            let (bg, fg) = resolve_cell_colors(xram, &plane.format, cell_offset, palette);
             let rgba = if bit == 1 { fg } else { bg };
 
             if rgba & 0xFF != 0 {
-                let fb_idx = scanline as usize * canvas_width as usize + screen_x as usize;
+                let fb_idx = scanline as usize * fb_stride + screen_x as usize;
                 framebuffer[fb_idx] = rgba;
             }
         }
@@ -324,6 +456,21 @@ mod tests {
         xram
     }
 
+    /// Plane with its config at 0x0000 and every emulator extension off;
+    /// tests override the fields they exercise.
+    fn make_plane(xram: &[u8; 65536], format: Mode1Format, scanline_end: u16) -> Mode1Plane {
+        Mode1Plane {
+            config: Mode1Config::from_xram(xram, 0),
+            format,
+            scanline_begin: 0,
+            scanline_end,
+            config_ptr: 0,
+            row_gap_px: 0,
+            nine_dot: false,
+            config_latch: ConfigLatch::Live,
+        }
+    }
+
     #[test]
     fn test_mode1_1bpp_single_char() {
         let config_ptr = 0x0000u16;
@@ -336,13 +483,7 @@ mod tests {
         let mut xram = xram;
         xram[data_ptr as usize] = 0xDB;
 
-        let plane = Mode1Plane {
-            config: Mode1Config::from_xram(&xram, config_ptr),
-            format: Mode1Format::Bpp1_8x8,
-            scanline_begin: 0,
-            scanline_end: 8,
-            config_ptr,
-        };
+        let plane = make_plane(&xram, Mode1Format::Bpp1_8x8, 8);
 
         // Canvas is 8x8 to fit exactly one character
         let mut fb = vec![0u32; 8 * 8];
@@ -366,13 +507,7 @@ mod tests {
         let mut xram = xram;
         xram[data_ptr as usize] = 0x20;
 
-        let plane = Mode1Plane {
-            config: Mode1Config::from_xram(&xram, config_ptr),
-            format: Mode1Format::Bpp1_8x8,
-            scanline_begin: 0,
-            scanline_end: 8,
-            config_ptr,
-        };
+        let plane = make_plane(&xram, Mode1Format::Bpp1_8x8, 8);
 
         let mut fb = vec![0u32; 8 * 8];
         render_mode1(&plane, &xram, &mut fb, 8, 8);
@@ -395,13 +530,7 @@ mod tests {
         xram[data_ptr as usize + 1] = 9;    // fg = bright red
         xram[data_ptr as usize + 2] = 12;   // bg = bright blue
 
-        let plane = Mode1Plane {
-            config: Mode1Config::from_xram(&xram, config_ptr),
-            format: Mode1Format::Bpp8_8x8,
-            scanline_begin: 0,
-            scanline_end: 8,
-            config_ptr,
-        };
+        let plane = make_plane(&xram, Mode1Format::Bpp8_8x8, 8);
 
         let mut fb = vec![0u32; 8 * 8];
         render_mode1(&plane, &xram, &mut fb, 8, 8);
@@ -415,6 +544,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_mode1_16bpp_underline_and_reverse() {
+        let config_ptr = 0x0000u16;
+        let data_ptr = 0x0100u16;
+        let mut xram = make_mode1_xram(config_ptr, data_ptr, 2, 1);
+        // 16bpp cells: glyph, attributes, fg (RGB565 LE), bg (RGB565 LE)
+        let (fg, bg) = (0x003Fu16, 0xF820u16); // red, blue, alpha bit 5 set
+        for (i, attr) in [ATTR_UNDERLINE, ATTR_REVERSE].into_iter().enumerate() {
+            let cell = data_ptr as usize + i * 6;
+            xram[cell] = 0x20; // space: every glyph pixel is bg
+            xram[cell + 1] = attr;
+            xram[cell + 2..cell + 4].copy_from_slice(&fg.to_le_bytes());
+            xram[cell + 4..cell + 6].copy_from_slice(&bg.to_le_bytes());
+        }
+
+        let plane = make_plane(&xram, Mode1Format::Bpp16_8x16, 16);
+        let mut fb = vec![0u32; 16 * 16];
+        render_mode1(&plane, &xram, &mut fb, 16, 16);
+
+        let (fg, bg) = (rgb565_to_rgba(fg), rgb565_to_rgba(bg));
+        // Underlined cell: bg above, fg on the last scanline
+        assert!(fb[..15 * 16].chunks(16).all(|row| row[..8].iter().all(|&px| px == bg)));
+        assert!(fb[15 * 16..15 * 16 + 8].iter().all(|&px| px == fg));
+        // Reversed cell: the blank glyph shows fg everywhere, no underline
+        assert!(fb.chunks(16).all(|row| row[8..].iter().all(|&px| px == fg)));
+    }
+
     #[test]
     fn test_mode1_config_from_xram() {
         let mut xram = Box::new([0u8; 65536]);
@@ -471,6 +627,9 @@ mod tests {
             scanline_begin: 0,
             scanline_end: 16, // 16 scanlines but only 1 char tall (8px), should wrap
             config_ptr,
+            row_gap_px: 0,
+            nine_dot: false,
+            config_latch: ConfigLatch::Live,
         };
 
         let mut fb = vec![0u32; 8 * 16];
@@ -480,4 +639,132 @@ mod tests {
         assert_ne!(fb[0] & 0xFF, 0, "row 0 should have content");
         assert_ne!(fb[8 * 8] & 0xFF, 0, "row 8 should wrap and have content");
     }
+
+    #[test]
+    fn test_mode1_proportional_advances() {
+        let config_ptr = 0x0000u16;
+        let data_ptr = 0x0100u16;
+        let table_ptr = 0x0200u16;
+        let mut xram = make_mode1_xram(config_ptr, data_ptr, 2, 1);
+        // Two full-block-sized cells with distinct colors and advances
+        let d = data_ptr as usize;
+        xram[d..d + 3].copy_from_slice(&[0x20, 0, 9]);    // space: all bg, bright red
+        xram[d + 3..d + 6].copy_from_slice(&[0xDB, 12, 0]); // full block: all fg, bright blue
+        let t = table_ptr as usize;
+        xram[t + 0x20] = 3;
+        xram[t + 0xDB] = 5;
+
+        let mut config = Mode1Config::from_xram(&xram, config_ptr);
+        config.advance_table = advance_table_ptr(table_ptr);
+        let plane = Mode1Plane {
+            config,
+            ..make_plane(&xram, Mode1Format::Bpp8_8x8, 8)
+        };
+
+        let mut fb = vec![0u32; 16 * 8];
+        render_mode1(&plane, &xram, &mut fb, 16, 8);
+
+        assert_eq!(fb[..3], [PALETTE_256[9]; 3], "first glyph is 3 px wide");
+        assert_eq!(fb[3..8], [PALETTE_256[12]; 5], "second glyph starts at column 3");
+        assert_eq!(fb[8..16], [0; 8], "nothing past the row's 8 px width");
+    }
+
+    #[test]
+    fn test_mode1_advance_table_ptr() {
+        assert_eq!(advance_table_ptr(0xFFFF), None);
+        assert_eq!(advance_table_ptr(0x0000), None);
+        assert_eq!(advance_table_ptr(0xFF01), None, "table must fit in XRAM");
+        assert_eq!(advance_table_ptr(0xFF00), Some(0xFF00));
+    }
+
+    #[test]
+    fn test_mode1_degenerate_size_with_wrap_draws_nothing() {
+        let config_ptr = 0x0000u16;
+        let data_ptr = 0x0100u16;
+        for (width, height) in [(0, 2), (2, 0), (-1, 2), (2, i16::MIN)] {
+            let mut xram = make_mode1_xram(config_ptr, data_ptr, width, height);
+            xram[config_ptr as usize] = 1;     // x_wrap
+            xram[config_ptr as usize + 1] = 1; // y_wrap
+            xram[data_ptr as usize..data_ptr as usize + 4].fill(0xDB); // full block
+
+            let plane = make_plane(&xram, Mode1Format::Bpp1_8x8, 0);
+
+            let mut fb = vec![0u32; 16 * 16];
+            render_mode1(&plane, &xram, &mut fb, 16, 16);
+            assert!(fb.iter().all(|&px| px == 0), "{width}x{height} chars drew pixels");
+        }
+    }
+
+    #[test]
+    fn test_mode1_row_gap_blank_scanlines() {
+        let config_ptr = 0x0000u16;
+        let data_ptr = 0x0100u16;
+        let mut xram = make_mode1_xram(config_ptr, data_ptr, 1, 2);
+        xram[data_ptr as usize] = 0xDB;     // row 0: full block
+        xram[data_ptr as usize + 1] = 0xDB; // row 1: full block
+
+        let mut plane = Mode1Plane {
+            row_gap_px: 2,
+            ..make_plane(&xram, Mode1Format::Bpp1_8x8, 0)
+        };
+
+        let mut fb = vec![0u32; 8 * 24];
+        render_mode1(&plane, &xram, &mut fb, 8, 24);
+        let lit = |fb: &[u32], y: usize| fb[y * 8..y * 8 + 8].iter().all(|&px| px & 0xFF != 0);
+        let blank = |fb: &[u32], y: usize| fb[y * 8..y * 8 + 8].iter().all(|&px| px == 0);
+        assert!((0..8).all(|y| lit(&fb, y)), "row 0 glyph");
+        assert!(blank(&fb, 8) && blank(&fb, 9), "2px gap is transparent background");
+        assert!((10..18).all(|y| lit(&fb, y)), "row 1 starts at scanline 10");
+        assert!((18..24).all(|y| blank(&fb, y)), "nothing below the last gap");
+
+        // Gap 0 packs rows as before
+        plane.row_gap_px = 0;
+        let mut fb = vec![0u32; 8 * 24];
+        render_mode1(&plane, &xram, &mut fb, 8, 24);
+        assert!((0..16).all(|y| lit(&fb, y)));
+    }
+
+    #[test]
+    fn test_mode1_nine_dot_repeats_box_drawing_column() {
+        let config_ptr = 0x0000u16;
+        let data_ptr = 0x0100u16;
+        let mut xram = make_mode1_xram(config_ptr, data_ptr, 2, 1);
+        xram[data_ptr as usize] = 0xC4; // box drawing horizontal line
+        xram[data_ptr as usize + 1] = b'A';
+
+        let plane = Mode1Plane {
+            nine_dot: true,
+            ..make_plane(&xram, Mode1Format::Bpp1_8x16, 0)
+        };
+        let mut fb = vec![0u32; 18 * 16];
+        render_mode1(&plane, &xram, &mut fb, 18, 16);
+
+        let lit = |x: usize, y: usize| fb[y * 18 + x] != 0;
+        assert!((0..16).any(|y| lit(7, y)), "the line reaches the cell's right edge");
+        for y in 0..16 {
+            assert_eq!(lit(8, y), lit(7, y), "box glyph row {y}: column 8 repeats column 7");
+            assert!(!lit(17, y), "'A' row {y}: column 8 is background");
+            let a_row = FONT16[y * 256 + b'A' as usize];
+            assert_eq!(lit(9, y), a_row & 0x80 != 0, "second cell starts at x = 9");
+        }
+    }
+
+    #[test]
+    fn test_mode1_font_near_xram_end_falls_back_to_builtin() {
+        let config_ptr = 0x0000u16;
+        let data_ptr = 0x0100u16;
+        let mut xram = make_mode1_xram(config_ptr, data_ptr, 1, 1);
+        // 8x16 font at 0xFF00 would need 4096 bytes but only 256 remain
+        xram[config_ptr as usize + 14..config_ptr as usize + 16].copy_from_slice(&0xFF00u16.to_le_bytes());
+        xram[data_ptr as usize] = 0xDB; // full block in the built-in font
+
+        assert_eq!(resolve_font(&xram, 0xFF00, 16), &FONT16[..]);
+
+        let plane = make_plane(&xram, Mode1Format::Bpp1_8x16, 0);
+
+        let mut fb = vec![0u32; 8 * 16];
+        render_mode1(&plane, &xram, &mut fb, 8, 16);
+        // The zeroed XRAM "font" would draw nothing; the built-in block is solid
+        assert!(fb.iter().all(|&px| px & 0xFF != 0));
+    }
 }
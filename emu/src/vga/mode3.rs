@@ -1,4 +1,5 @@
-use super::palette::{resolve_palette, rgb565_to_rgba};
+use super::palette::{resolve_palette, rgb565_opaque_to_rgba, rgb565_to_rgba};
+use super::ConfigLatch;
 
 /// Mode 3 configuration, read from XRAM at config_ptr.
 /// Matches firmware mode3_config_t exactly:
@@ -33,6 +34,7 @@ pub enum ColorFormat {
     Bpp1Lsb,  // attr 8: 1bpp LSB-first (bit 0 = pixel 0)
     Bpp2Lsb,  // attr 9: 2bpp LSB-first (bits[1:0] = pixel 0)
     Bpp4Lsb,  // attr 10: 4bpp LSB-first (low nibble = pixel 0)
+    Bpp8Swapped, // attr 11: 8bpp indexed, index nibbles swapped (emulator extension)
 }
 
 impl ColorFormat {
@@ -46,6 +48,7 @@ impl ColorFormat {
             8 => Some(Self::Bpp1Lsb),
             9 => Some(Self::Bpp2Lsb),
             10 => Some(Self::Bpp4Lsb),
+            11 => Some(Self::Bpp8Swapped),
             _ => None,
         }
     }
@@ -55,12 +58,33 @@ impl ColorFormat {
             Self::Bpp1Msb | Self::Bpp1Lsb => 1,
             Self::Bpp2Msb | Self::Bpp2Lsb => 2,
             Self::Bpp4Msb | Self::Bpp4Lsb => 4,
-            Self::Bpp8 => 8,
+            Self::Bpp8 | Self::Bpp8Swapped => 8,
             Self::Bpp16 => 16,
         }
     }
 }
 
+/// Bit layout of 16bpp direct-color pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rgb16Layout {
+    /// PICO_SCANVIDEO: R5 at bits 4:0, alpha at bit 5, G5 at 10:6, B5 at 15:11.
+    #[default]
+    PicoScanvideo,
+    /// Standard RGB565: R5 at bits 15:11, G6 at 10:5, B5 at 4:0. Always opaque.
+    #[allow(dead_code)]
+    Rgb565Opaque,
+}
+
+impl Rgb16Layout {
+    /// Decode a raw 16-bit pixel value to RGBA u32.
+    pub fn to_rgba(self, raw: u16) -> u32 {
+        match self {
+            Self::PicoScanvideo => rgb565_to_rgba(raw),
+            Self::Rgb565Opaque => rgb565_opaque_to_rgba(raw),
+        }
+    }
+}
+
 /// A programmed Mode 3 plane.
 #[derive(Debug, Clone)]
 pub struct Mode3Plane {
@@ -69,6 +93,54 @@ pub struct Mode3Plane {
     pub scanline_begin: u16,
     pub scanline_end: u16,
     pub config_ptr: u16,
+    /// Pixel layout for `ColorFormat::Bpp16`. Firmware always uses PICO_SCANVIDEO.
+    pub rgb16_layout: Rgb16Layout,
+    /// XRAM address of an `i16` x-offset per canvas scanline, added to
+    /// `x_pos_px` for raster effects. Emulator extension set from MODE xreg 7.
+    pub scroll_table_ptr: Option<u16>,
+    /// Canvas scanlines per bitmap row (1 or 2), for a scanline-doubled look
+    /// independent of the canvas upscale. Emulator extension set by MODE attr
+    /// bit `V_SCALE2_ATTR`.
+    pub v_scale: u8,
+    /// Palette index drawn as transparent whatever its palette alpha
+    /// (color-key transparency). Ignored for 16bpp direct color.
+    pub transparent_index: Option<u8>,
+    /// Bytes between the starts of consecutive bitmap rows, for bitmaps
+    /// stored with padded rows. `None` packs rows at `width_px` pixels.
+    pub row_stride_bytes: Option<usize>,
+    /// When `config` is read from XRAM. Emulator extension; MODE sets
+    /// `Vga::config_latch`.
+    pub config_latch: ConfigLatch,
+}
+
+impl Mode3Plane {
+    /// Bytes from one bitmap row to the next.
+    pub fn sizeof_row(&self) -> usize {
+        self.row_stride_bytes.unwrap_or_else(|| {
+            (self.config.width_px.max(0) as u32 * self.format.bits_per_pixel()).div_ceil(8) as usize
+        })
+    }
+}
+
+/// MODE attr bit requesting `v_scale` 2. Not a firmware attribute.
+pub const V_SCALE2_ATTR: u16 = 0x20;
+
+/// Interpret MODE xreg 7 as a scroll table pointer. 0xFFFF (and 0, the value
+/// left by programs that never send xreg 7) disable per-scanline scrolling.
+pub fn scroll_table_ptr(xreg: u16) -> Option<u16> {
+    match xreg {
+        0 | 0xFFFF => None,
+        ptr => Some(ptr),
+    }
+}
+
+/// Read the scroll offset for `scanline`. Entries past the end of XRAM read as 0.
+fn scroll_offset(xram: &[u8; 65536], table_ptr: u16, scanline: i32) -> i32 {
+    let addr = table_ptr as usize + scanline as usize * 2;
+    if addr + 2 > 0x10000 {
+        return 0;
+    }
+    i16::from_le_bytes([xram[addr], xram[addr + 1]]) as i32
 }
 
 impl Mode3Config {
@@ -114,40 +186,46 @@ impl Mode3Config {
 ///
 /// 4bpp MSB (mode3_render_4bpp_0r): high nibble=px0, low nibble=px1 (per byte)
 /// 4bpp LSB (mode3_render_4bpp_1r): low nibble=px0, high nibble=px1 (per byte)
-fn get_pixel(data: &[u8], col: usize, format: &ColorFormat) -> u8 {
-    match format {
-        ColorFormat::Bpp8 => data[col],
+///
+/// 8bpp swapped (no firmware equivalent): byte 0x1A is palette index 0xA1
+///
+/// Returns None if the byte holding `col` lies past the end of `data`, so
+/// callers may pass an exact-length row slice.
+pub(super) fn get_pixel(data: &[u8], col: usize, format: &ColorFormat) -> Option<u8> {
+    let pixel = match format {
+        ColorFormat::Bpp8 => *data.get(col)?,
+        ColorFormat::Bpp8Swapped => data.get(col)?.rotate_left(4),
         // 4bpp MSB: high nibble is even pixel, low nibble is odd pixel
         ColorFormat::Bpp4Msb => {
-            let byte = data[col / 2];
+            let byte = *data.get(col / 2)?;
             if col.is_multiple_of(2) { byte >> 4 } else { byte & 0x0F }
         }
         // 4bpp LSB: low nibble is even pixel, high nibble is odd pixel
         ColorFormat::Bpp4Lsb => {
-            let byte = data[col / 2];
+            let byte = *data.get(col / 2)?;
             if col.is_multiple_of(2) { byte & 0x0F } else { byte >> 4 }
         }
         // 2bpp MSB: bits[7:6]=px0, bits[5:4]=px1, bits[3:2]=px2, bits[1:0]=px3
         ColorFormat::Bpp2Msb => {
-            let byte = data[col / 4];
+            let byte = *data.get(col / 4)?;
             let shift = 6 - (col % 4) * 2;
             (byte >> shift) & 0x03
         }
         // 2bpp LSB: bits[1:0]=px0, bits[3:2]=px1, bits[5:4]=px2, bits[7:6]=px3
         ColorFormat::Bpp2Lsb => {
-            let byte = data[col / 4];
+            let byte = *data.get(col / 4)?;
             let shift = (col % 4) * 2;
             (byte >> shift) & 0x03
         }
         // 1bpp MSB: bit7=px0, bit6=px1, ..., bit0=px7
         ColorFormat::Bpp1Msb => {
-            let byte = data[col / 8];
+            let byte = *data.get(col / 8)?;
             let shift = 7 - (col % 8);
             (byte >> shift) & 0x01
         }
         // 1bpp LSB: bit0=px0, bit1=px1, ..., bit7=px7
         ColorFormat::Bpp1Lsb => {
-            let byte = data[col / 8];
+            let byte = *data.get(col / 8)?;
             let shift = col % 8;
             (byte >> shift) & 0x01
         }
@@ -155,7 +233,15 @@ fn get_pixel(data: &[u8], col: usize, format: &ColorFormat) -> u8 {
             // Not used via get_pixel — handled separately in render loop
             0
         }
-    }
+    };
+    Some(pixel)
+}
+
+/// Read a 16bpp pixel at a given column offset as a raw little-endian u16.
+/// Returns None if either byte lies past the end of `data`.
+fn get_pixel16(data: &[u8], col: usize) -> Option<u16> {
+    let bytes = data.get(col * 2..col * 2 + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
 }
 
 /// Render a Mode 3 plane into the framebuffer.
@@ -169,6 +255,7 @@ fn get_pixel(data: &[u8], col: usize, format: &ColorFormat) -> u8 {
 ///
 /// Pixels are only written when alpha is non-zero (opaque), mirroring the
 /// transparency convention used throughout the palette module.
+#[allow(dead_code)]
 pub fn render_mode3(
     plane: &Mode3Plane,
     xram: &[u8; 65536],
@@ -176,15 +263,32 @@ pub fn render_mode3(
     canvas_width: u16,
     canvas_height: u16,
 ) {
+    let palette = resolve_palette(xram, plane.format.bits_per_pixel(), plane.config.xram_palette_ptr);
+    render_mode3_with_palette(plane, xram, &palette, framebuffer, canvas_width, canvas_height, canvas_width as usize);
+}
+
+/// `render_mode3` with the plane's palette already resolved, into a
+/// framebuffer whose rows are `fb_stride` pixels apart. A stride wider than
+/// `canvas_width` draws the canvas into the top-left of a larger buffer.
+pub fn render_mode3_with_palette(
+    plane: &Mode3Plane,
+    xram: &[u8; 65536],
+    palette: &[u32],
+    framebuffer: &mut [u32],
+    canvas_width: u16,
+    canvas_height: u16,
+    fb_stride: usize,
+) {
+    debug_assert!(fb_stride >= canvas_width as usize, "rows would overlap");
     let cfg = &plane.config;
 
-    // Validate: width and height must be positive, matching firmware NULL-return check
+    // Validate: width and height must be positive, matching firmware NULL-return check.
+    // This also keeps the wrapping rem_euclid calls below from dividing by zero.
     if cfg.width_px < 1 || cfg.height_px < 1 {
         return;
     }
 
-    let bpp = plane.format.bits_per_pixel();
-    let sizeof_row = (cfg.width_px as u32 * bpp).div_ceil(8) as usize;
+    let sizeof_row = plane.sizeof_row();
 
     // Bounds check: entire bitmap must fit in XRAM, matching firmware check:
     //   sizeof_bitmap > 0x10000 - config->xram_data_ptr
@@ -193,8 +297,6 @@ pub fn render_mode3(
         return;
     }
 
-    let palette = resolve_palette(xram, plane.format.bits_per_pixel(), cfg.xram_palette_ptr);
-
     let y_start = plane.scanline_begin as i32;
     let y_end = if plane.scanline_end == 0 {
         canvas_height as i32
@@ -207,7 +309,7 @@ pub fn render_mode3(
             continue;
         }
 
-        let mut row = scanline - cfg.y_pos_px as i32;
+        let mut row = (scanline - cfg.y_pos_px as i32).div_euclid(plane.v_scale.max(1) as i32);
 
         // Y wrapping — mirrors firmware mode3_scanline_to_data():
         //   if (row < 0) row += (-(row+1)/height + 1) * height;
@@ -221,10 +323,21 @@ pub fn render_mode3(
             continue;
         }
 
+        // In bounds for every row: the whole bitmap fits below 0x10000
         let row_offset = cfg.xram_data_ptr as usize + row as usize * sizeof_row;
+        let row_data = &xram[row_offset..row_offset + sizeof_row];
+        let x_pos = cfg.x_pos_px as i32
+            + plane.scroll_table_ptr.map_or(0, |ptr| scroll_offset(xram, ptr, scanline));
+
+        // Without wrap only the plane's own columns can draw, so skip the rest
+        let (x_first, x_last) = if cfg.x_wrap {
+            (0, canvas_width as i32)
+        } else {
+            (x_pos.max(0), (x_pos + cfg.width_px as i32).min(canvas_width as i32))
+        };
 
-        for screen_x in 0..canvas_width as i32 {
-            let mut col = screen_x - cfg.x_pos_px as i32;
+        for screen_x in x_first..x_last {
+            let mut col = screen_x - x_pos;
 
             // X wrapping — mirrors firmware mode3_fill_cols():
             //   if (col < 0 && x_wrap) col += (-(col+1)/width + 1) * width;
@@ -239,31 +352,19 @@ pub fn render_mode3(
                 continue;
             }
 
-            let fb_idx = scanline as usize * canvas_width as usize + screen_x as usize;
+            let fb_idx = scanline as usize * fb_stride + screen_x as usize;
 
+            // Both paths read only within the row, so a pixel can never
+            // straddle 0x10000; one past the row would read as transparent
             let rgba = if plane.format == ColorFormat::Bpp16 {
-                // Direct color: 2 bytes per pixel in PICO_SCANVIDEO format
-                let byte_offset = row_offset + col as usize * 2;
-                if byte_offset + 1 < 0x10000 {
-                    let raw = u16::from_le_bytes([
-                        xram[byte_offset],
-                        xram[byte_offset + 1],
-                    ]);
-                    rgb565_to_rgba(raw)
-                } else {
-                    0
-                }
+                // Direct color: 2 bytes per pixel, decoded per the plane's layout
+                get_pixel16(row_data, col as usize)
+                    .map_or(0, |raw| plane.rgb16_layout.to_rgba(raw))
             } else {
-                let pixel_idx = get_pixel(
-                    &xram[row_offset..],
-                    col as usize,
-                    &plane.format,
-                );
-                if (pixel_idx as usize) < palette.len() {
-                    palette[pixel_idx as usize]
-                } else {
-                    0
-                }
+                get_pixel(row_data, col as usize, &plane.format)
+                    .filter(|&idx| Some(idx) != plane.transparent_index)
+                    .and_then(|idx| palette.get(idx as usize).copied())
+                    .unwrap_or(0)
             };
 
             // Only draw if pixel is opaque (alpha != 0), matching firmware transparency convention
@@ -299,6 +400,24 @@ mod tests {
         xram
     }
 
+    /// Plane with its config at 0x0000 and every emulator extension off;
+    /// tests override the fields they exercise.
+    fn make_plane(xram: &[u8; 65536], format: ColorFormat, scanline_end: u16) -> Mode3Plane {
+        Mode3Plane {
+            config: Mode3Config::from_xram(xram, 0),
+            format,
+            scanline_begin: 0,
+            scanline_end,
+            config_ptr: 0,
+            rgb16_layout: Rgb16Layout::PicoScanvideo,
+            scroll_table_ptr: None,
+            v_scale: 1,
+            transparent_index: None,
+            row_stride_bytes: None,
+            config_latch: ConfigLatch::Live,
+        }
+    }
+
     #[test]
     fn test_mode3_8bpp_single_pixel() {
         let config_ptr = 0x0000u16;
@@ -308,13 +427,7 @@ mod tests {
         // Set pixel (0,0) to color index 9 (bright red)
         xram[data_ptr as usize] = 9;
 
-        let plane = Mode3Plane {
-            config: Mode3Config::from_xram(&xram, config_ptr),
-            format: ColorFormat::Bpp8,
-            scanline_begin: 0,
-            scanline_end: 4,
-            config_ptr: 0,
-        };
+        let plane = make_plane(&xram, ColorFormat::Bpp8, 4);
 
         let mut fb = vec![0u32; 4 * 4];
         render_mode3(&plane, &xram, &mut fb, 4, 4);
@@ -323,6 +436,27 @@ mod tests {
         assert_eq!(fb[0], PALETTE_256[9]);
     }
 
+    #[test]
+    fn test_mode3_fb_stride_places_rows() {
+        let data_ptr = 0x0100u16;
+        let mut xram = make_xram_with_config(0, data_ptr, 4, 4);
+        for i in 0..16 {
+            xram[data_ptr as usize + i] = i as u8 + 1;
+        }
+        let plane = make_plane(&xram, ColorFormat::Bpp8, 4);
+
+        let mut canvas = vec![0u32; 4 * 4];
+        render_mode3_with_palette(&plane, &xram, &PALETTE_256, &mut canvas, 4, 4, 4);
+        // The same 4x4 canvas in the top-left of a 10-pixel-wide buffer
+        let mut wide = vec![0u32; 10 * 4];
+        render_mode3_with_palette(&plane, &xram, &PALETTE_256, &mut wide, 4, 4, 10);
+        for y in 0..4 {
+            assert_eq!(wide[y * 10..y * 10 + 4], canvas[y * 4..y * 4 + 4], "row {y}");
+            assert!(wide[y * 10 + 4..y * 10 + 10].iter().all(|&px| px == 0), "row {y} past the canvas");
+        }
+        assert_eq!(wide[10], PALETTE_256[5]);
+    }
+
     #[test]
     fn test_mode3_1bpp_msb() {
         let config_ptr = 0x0000u16;
@@ -332,13 +466,7 @@ mod tests {
         // Byte 0b10100101 -> pixels MSB-first: 1,0,1,0,0,1,0,1
         xram[data_ptr as usize] = 0b10100101;
 
-        let plane = Mode3Plane {
-            config: Mode3Config::from_xram(&xram, config_ptr),
-            format: ColorFormat::Bpp1Msb,
-            scanline_begin: 0,
-            scanline_end: 1,
-            config_ptr: 0,
-        };
+        let plane = make_plane(&xram, ColorFormat::Bpp1Msb, 1);
 
         let mut fb = vec![0u32; 8];
         render_mode3(&plane, &xram, &mut fb, 8, 1);
@@ -361,13 +489,7 @@ mod tests {
         xram[data_ptr as usize] = 1;
         xram[data_ptr as usize + 1] = 2;
 
-        let plane = Mode3Plane {
-            config: Mode3Config::from_xram(&xram, config_ptr),
-            format: ColorFormat::Bpp8,
-            scanline_begin: 0,
-            scanline_end: 4,
-            config_ptr: 0,
-        };
+        let plane = make_plane(&xram, ColorFormat::Bpp8, 4);
 
         let mut fb = vec![0u32; 4];
         render_mode3(&plane, &xram, &mut fb, 1, 4);
@@ -378,4 +500,245 @@ mod tests {
         assert_eq!(fb[2], PALETTE_256[1]);
         assert_eq!(fb[3], PALETTE_256[2]);
     }
+
+    #[test]
+    fn test_mode3_16bpp_rgb565_opaque() {
+        let config_ptr = 0x0000u16;
+        let data_ptr = 0x0100u16;
+        let mut xram = make_xram_with_config(config_ptr, data_ptr, 2, 1);
+        // Pixel 0: standard RGB565 full red. Pixel 1: black (no alpha bit needed).
+        xram[data_ptr as usize..data_ptr as usize + 2].copy_from_slice(&0xF800u16.to_le_bytes());
+
+        let mut plane = Mode3Plane {
+            rgb16_layout: Rgb16Layout::Rgb565Opaque,
+            ..make_plane(&xram, ColorFormat::Bpp16, 1)
+        };
+
+        let mut fb = vec![0u32; 2];
+        render_mode3(&plane, &xram, &mut fb, 2, 1);
+        assert_eq!(fb[0], 0xFF0000FF);
+        assert_eq!(fb[1], 0x000000FF); // opaque black
+
+        // The same bytes in PICO_SCANVIDEO layout have no alpha bit: nothing drawn
+        plane.rgb16_layout = Rgb16Layout::PicoScanvideo;
+        let mut fb = vec![0u32; 2];
+        render_mode3(&plane, &xram, &mut fb, 2, 1);
+        assert_eq!(fb, vec![0, 0]);
+    }
+
+    #[test]
+    fn test_get_pixel_short_slice() {
+        // One byte of 1bpp data covers columns 0-7; anything past is None
+        let data = [0b1000_0001u8];
+        assert_eq!(get_pixel(&data, 0, &ColorFormat::Bpp1Msb), Some(1));
+        assert_eq!(get_pixel(&data, 7, &ColorFormat::Bpp1Msb), Some(1));
+        assert_eq!(get_pixel(&data, 8, &ColorFormat::Bpp1Msb), None);
+        assert_eq!(get_pixel(&data, 2, &ColorFormat::Bpp4Lsb), None);
+        assert_eq!(get_pixel(&data, 1, &ColorFormat::Bpp8), None);
+        assert_eq!(get_pixel(&data, 1, &ColorFormat::Bpp8Swapped), None);
+        assert_eq!(get_pixel(&[], 0, &ColorFormat::Bpp2Msb), None);
+
+        // 16bpp needs both bytes of the pixel
+        assert_eq!(get_pixel16(&[0x3F, 0x00, 0xFF], 0), Some(0x003F));
+        assert_eq!(get_pixel16(&[0x3F, 0x00, 0xFF], 1), None);
+    }
+
+    #[test]
+    fn test_mode3_scroll_table_shifts_scanlines() {
+        let config_ptr = 0x0000u16;
+        let data_ptr = 0x0100u16;
+        let table_ptr = 0x0200u16;
+        let mut xram = make_xram_with_config(config_ptr, data_ptr, 4, 3);
+        // Each row has a single red pixel in column 0
+        for row in 0..3 {
+            xram[data_ptr as usize + row * 4] = 9;
+        }
+        // Scanline 1 shifts right by 2, scanline 2 left by 1 (off-canvas)
+        let t = table_ptr as usize;
+        xram[t + 2..t + 4].copy_from_slice(&2i16.to_le_bytes());
+        xram[t + 4..t + 6].copy_from_slice(&(-1i16).to_le_bytes());
+
+        let plane = Mode3Plane {
+            scroll_table_ptr: scroll_table_ptr(table_ptr),
+            ..make_plane(&xram, ColorFormat::Bpp8, 3)
+        };
+
+        let mut fb = vec![0u32; 4 * 3];
+        render_mode3(&plane, &xram, &mut fb, 4, 3);
+
+        let red_cols = |y: usize| (0..4).filter(|&x| fb[y * 4 + x] == PALETTE_256[9]).collect::<Vec<_>>();
+        assert_eq!(red_cols(0), vec![0], "scanline 0 is unscrolled");
+        assert_eq!(red_cols(1), vec![2], "scanline 1 shifted right by 2");
+        assert!(red_cols(2).is_empty(), "scanline 2 shifted off the left edge");
+    }
+
+    #[test]
+    fn test_scroll_table_ptr_disabled_values() {
+        assert_eq!(scroll_table_ptr(0xFFFF), None);
+        assert_eq!(scroll_table_ptr(0x0000), None);
+        assert_eq!(scroll_table_ptr(0x1234), Some(0x1234));
+    }
+
+    #[test]
+    fn test_mode3_v_scale_doubles_rows() {
+        let config_ptr = 0x0000u16;
+        let data_ptr = 0x0100u16;
+        let mut xram = make_xram_with_config(config_ptr, data_ptr, 1, 2);
+        xram[data_ptr as usize] = 9;      // row 0: bright red
+        xram[data_ptr as usize + 1] = 12; // row 1: bright blue
+
+        let plane = Mode3Plane {
+            v_scale: 2,
+            ..make_plane(&xram, ColorFormat::Bpp8, 5)
+        };
+
+        let mut fb = vec![0u32; 5];
+        render_mode3(&plane, &xram, &mut fb, 1, 5);
+        let (red, blue) = (PALETTE_256[9], PALETTE_256[12]);
+        assert_eq!(fb, vec![red, red, blue, blue, 0], "each row covers two scanlines");
+    }
+
+    #[test]
+    fn test_mode3_transparent_index_color_key() {
+        let config_ptr = 0x0000u16;
+        let data_ptr = 0x0100u16;
+        let mut xram = make_xram_with_config(config_ptr, data_ptr, 4, 1);
+        xram[data_ptr as usize..data_ptr as usize + 4].copy_from_slice(&[5, 9, 5, 12]);
+        assert_ne!(PALETTE_256[5] & 0xFF, 0, "index 5 is opaque in the palette");
+
+        let mut plane = Mode3Plane {
+            transparent_index: Some(5),
+            ..make_plane(&xram, ColorFormat::Bpp8, 1)
+        };
+
+        let below = 0x11223344;
+        let mut fb = vec![below; 4];
+        render_mode3(&plane, &xram, &mut fb, 4, 1);
+        assert_eq!(fb, vec![below, PALETTE_256[9], below, PALETTE_256[12]], "keyed pixels not drawn");
+
+        plane.transparent_index = None;
+        render_mode3(&plane, &xram, &mut fb, 4, 1);
+        assert_eq!(fb[0], PALETTE_256[5]);
+    }
+
+    #[test]
+    fn test_mode3_row_stride_padded_rows() {
+        let config_ptr = 0x0000u16;
+        let data_ptr = 0x0100u16;
+        let mut xram = make_xram_with_config(config_ptr, data_ptr, 2, 2);
+        // Rows padded to 256 bytes: row 1 starts at data_ptr + 256
+        let d = data_ptr as usize;
+        xram[d..d + 3].copy_from_slice(&[9, 10, 11]);
+        xram[d + 256..d + 258].copy_from_slice(&[12, 13]);
+
+        let mut plane = Mode3Plane {
+            row_stride_bytes: Some(256),
+            ..make_plane(&xram, ColorFormat::Bpp8, 2)
+        };
+
+        let mut fb = vec![0u32; 3 * 2];
+        render_mode3(&plane, &xram, &mut fb, 3, 2);
+        let p = |i: usize| PALETTE_256[i];
+        assert_eq!(fb, vec![p(9), p(10), 0, p(12), p(13), 0], "visible width stays width_px");
+
+        // Without a stride, row 1 is packed right after row 0
+        plane.row_stride_bytes = None;
+        let mut fb = vec![0u32; 3 * 2];
+        render_mode3(&plane, &xram, &mut fb, 3, 2);
+        assert_eq!(&fb[3..5], &[p(11), 0]);
+    }
+
+    #[test]
+    fn test_mode3_degenerate_size_with_wrap_draws_nothing() {
+        let config_ptr = 0x0000u16;
+        let data_ptr = 0x0100u16;
+        for (width, height) in [(0, 4), (4, 0), (-3, 4), (4, i16::MIN), (0, 0)] {
+            let mut xram = make_xram_with_config(config_ptr, data_ptr, width, height);
+            xram[config_ptr as usize] = 1;     // x_wrap
+            xram[config_ptr as usize + 1] = 1; // y_wrap
+            xram[data_ptr as usize..data_ptr as usize + 16].fill(9);
+
+            let plane = make_plane(&xram, ColorFormat::Bpp8, 0);
+
+            let mut fb = vec![0u32; 4 * 4];
+            render_mode3(&plane, &xram, &mut fb, 4, 4);
+            assert!(fb.iter().all(|&px| px == 0), "{width}x{height} bitmap drew pixels");
+        }
+    }
+
+    #[test]
+    fn test_mode3_8bpp_swapped_nibbles() {
+        assert_eq!(ColorFormat::from_attr(11), Some(ColorFormat::Bpp8Swapped));
+        assert_eq!(get_pixel(&[0x1A], 0, &ColorFormat::Bpp8Swapped), Some(0xA1));
+
+        let config_ptr = 0x0000u16;
+        let data_ptr = 0x0100u16;
+        let mut xram = make_xram_with_config(config_ptr, data_ptr, 1, 1);
+        xram[data_ptr as usize] = 0x1A;
+
+        let plane = make_plane(&xram, ColorFormat::Bpp8Swapped, 1);
+
+        let mut fb = vec![0u32; 1];
+        render_mode3(&plane, &xram, &mut fb, 1, 1);
+        assert_eq!(fb[0], PALETTE_256[0xA1]);
+    }
+
+    #[test]
+    fn test_mode3_x_clipping_matches_full_scan() {
+        let config_ptr = 0x0000u16;
+        let data_ptr = 0x0100u16;
+        let (width, height) = (6i16, 3i16);
+        let (canvas_w, canvas_h) = (32u16, 4u16);
+        let mut xram = make_xram_with_config(config_ptr, data_ptr, width, height);
+        for i in 0..(width * height) as usize {
+            xram[data_ptr as usize + i] = 1 + i as u8;
+        }
+
+        for x_pos in [-10i16, -3, 0, 5, 29, 32, 40] {
+            xram[config_ptr as usize + 2..config_ptr as usize + 4].copy_from_slice(&x_pos.to_le_bytes());
+            let plane = make_plane(&xram, ColorFormat::Bpp8, 0);
+
+            let mut fb = vec![0u32; canvas_w as usize * canvas_h as usize];
+            render_mode3(&plane, &xram, &mut fb, canvas_w, canvas_h);
+
+            // Reference: test every canvas column against the plane extent
+            let mut expected = vec![0u32; fb.len()];
+            for y in 0..height as i32 {
+                for screen_x in 0..canvas_w as i32 {
+                    let col = screen_x - x_pos as i32;
+                    if (0..width as i32).contains(&col) {
+                        let idx = xram[data_ptr as usize + (y * width as i32 + col) as usize];
+                        expected[y as usize * canvas_w as usize + screen_x as usize] = PALETTE_256[idx as usize];
+                    }
+                }
+            }
+            assert_eq!(fb, expected, "x_pos {x_pos}");
+        }
+    }
+
+    #[test]
+    fn test_mode3_16bpp_bitmap_at_xram_end() {
+        let config_ptr = 0x0000u16;
+        let data_ptr = 0xFFF0u16;
+        // 8 pixels of 16bpp fill 0xFFF0..=0xFFFF exactly
+        let mut xram = make_xram_with_config(config_ptr, data_ptr, 8, 1);
+        xram[config_ptr as usize] = 1; // x_wrap
+        xram[config_ptr as usize + 2..config_ptr as usize + 4].copy_from_slice(&3i16.to_le_bytes());
+        xram[0xFFFE..].copy_from_slice(&0xFFFFu16.to_le_bytes()); // last pixel: white, opaque
+
+        let mut plane = make_plane(&xram, ColorFormat::Bpp16, 0);
+
+        let mut fb = vec![0u32; 16 * 2];
+        render_mode3(&plane, &xram, &mut fb, 16, 2);
+        // Pixel 7 lands at x = 3 + 7 - 8 = 2 and again at 10
+        assert_eq!(fb[2], plane.rgb16_layout.to_rgba(0xFFFF));
+        assert_eq!(fb[10], fb[2]);
+        assert_eq!(fb.iter().filter(|&&px| px != 0).count(), 2);
+
+        // One more row would run past 0x10000: nothing is drawn
+        plane.config.height_px = 2;
+        let mut fb = vec![0u32; 16 * 2];
+        render_mode3(&plane, &xram, &mut fb, 16, 2);
+        assert!(fb.iter().all(|&px| px == 0));
+    }
 }
@@ -22,6 +22,41 @@ const fn pico_rgba_transparent(r: u8, g: u8, b: u8) -> u32 {
     ((r8 as u32) << 24) | ((g8 as u32) << 16) | ((b8 as u32) << 8)
 }
 
+/// An RGBA color unpacked from the `u32` packing used for palettes and
+/// framebuffers: R in bits 31:24, G in 23:16, B in 15:8, A in 7:0.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    /// 0 is transparent; the built-in palettes use only 0 and 0xFF.
+    pub a: u8,
+}
+
+#[allow(dead_code)]
+impl Rgba {
+    pub const fn from_u32(rgba: u32) -> Self {
+        let [r, g, b, a] = rgba.to_be_bytes();
+        Self { r, g, b, a }
+    }
+
+    pub const fn to_u32(self) -> u32 {
+        u32::from_be_bytes([self.r, self.g, self.b, self.a])
+    }
+}
+
+/// `PALETTE_2` as `Rgba` colors.
+#[allow(dead_code)]
+pub fn palette_2() -> [Rgba; 2] {
+    PALETTE_2.map(Rgba::from_u32)
+}
+
+/// `PALETTE_256` as `Rgba` colors.
+#[allow(dead_code)]
+pub fn palette_256() -> [Rgba; 256] {
+    PALETTE_256.map(Rgba::from_u32)
+}
+
 /// 1bpp default palette. Index 0 = transparent black, index 1 = opaque light grey.
 pub const PALETTE_2: [u32; 2] = [
     pico_rgba_transparent(0, 0, 0),
@@ -99,10 +134,7 @@ pub fn resolve_palette(xram: &[u8; 65536], bpp: u32, palette_ptr: u16) -> Vec<u3
 
     let count = 1usize << bpp;
 
-    if palette_ptr & 1 == 0
-        && palette_ptr > 0
-        && (palette_ptr as usize + count * 2) <= 0x10000
-    {
+    if xram_palette_valid(bpp, palette_ptr) {
         let mut pal = Vec::with_capacity(count);
         for i in 0..count {
             let offset = palette_ptr as usize + i * 2;
@@ -117,6 +149,69 @@ pub fn resolve_palette(xram: &[u8; 65536], bpp: u32, palette_ptr: u16) -> Vec<u3
     }
 }
 
+/// XRAM bytes `resolve_palette` reads, or None when it uses a built-in palette.
+pub fn xram_palette_range(bpp: u32, palette_ptr: u16) -> Option<std::ops::Range<usize>> {
+    xram_palette_valid(bpp, palette_ptr).then(|| palette_ptr as usize..palette_ptr as usize + (2usize << bpp))
+}
+
+/// True if `resolve_palette` reads the palette from XRAM rather than
+/// falling back to a built-in one.
+fn xram_palette_valid(bpp: u32, palette_ptr: u16) -> bool {
+    bpp < 16
+        && palette_ptr & 1 == 0
+        && palette_ptr > 0
+        && (palette_ptr as usize + (2usize << bpp)) <= 0x10000
+}
+
+/// A plane's palette as resolved for its last render, for debugging dumps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedPalette {
+    pub mode: u8,
+    pub bpp: u32,
+    pub palette_ptr: u16,
+    /// Entries as RGBA u32; empty for 16bpp direct color.
+    pub colors: Vec<u32>,
+}
+
+impl ResolvedPalette {
+    #[allow(dead_code)]
+    pub fn resolve(xram: &[u8; 65536], mode: u8, bpp: u32, palette_ptr: u16) -> Self {
+        Self { mode, bpp, palette_ptr, colors: resolve_palette(xram, bpp, palette_ptr) }
+    }
+
+    /// Where the colors came from: "xram", "builtin", or "direct" (16bpp).
+    pub fn source(&self) -> &'static str {
+        if self.bpp >= 16 {
+            "direct"
+        } else if xram_palette_valid(self.bpp, self.palette_ptr) {
+            "xram"
+        } else {
+            "builtin"
+        }
+    }
+}
+
+/// Format per-plane resolved palettes as JSON, colors as "#RRGGBBAA".
+/// Unprogrammed planes are `null`.
+pub fn palettes_json(palettes: &[Option<ResolvedPalette>]) -> String {
+    let mut out = String::from("{\n  \"planes\": [\n");
+    for (i, entry) in palettes.iter().enumerate() {
+        match entry {
+            Some(p) => {
+                let colors: Vec<String> = p.colors.iter().map(|c| format!("\"#{c:08X}\"")).collect();
+                out.push_str(&format!(
+                    "    {{\"plane\": {i}, \"mode\": {}, \"bpp\": {}, \"palette_ptr\": \"0x{:04X}\", \"source\": \"{}\", \"colors\": [{}]}}",
+                    p.mode, p.bpp, p.palette_ptr, p.source(), colors.join(", ")
+                ));
+            }
+            None => out.push_str("    null"),
+        }
+        out.push_str(if i + 1 < palettes.len() { ",\n" } else { "\n" });
+    }
+    out.push_str("  ]\n}\n");
+    out
+}
+
 /// Convert a 16-bit PICO_SCANVIDEO pixel value (as stored in XRAM custom palettes) to RGBA u32.
 ///
 /// PICO_SCANVIDEO DPI format (from firmware scanvideo.h):
@@ -137,6 +232,20 @@ pub fn rgb565_to_rgba(raw: u16) -> u32 {
     ((r as u32) << 24) | ((g as u32) << 16) | ((b as u32) << 8) | (alpha as u32)
 }
 
+/// Convert a standard little-endian RGB565 pixel value to opaque RGBA u32.
+///
+/// Layout: R5 at bits 15:11, G6 at bits 10:5, B5 at bits 4:0. There is no
+/// alpha bit, so every pixel is opaque.
+pub fn rgb565_opaque_to_rgba(raw: u16) -> u32 {
+    let r5 = (raw >> 11) as u8;
+    let g6 = ((raw >> 5) & 0x3F) as u8;
+    let b5 = (raw & 0x1F) as u8;
+    let r = (r5 << 3) | (r5 >> 2);
+    let g = (g6 << 2) | (g6 >> 4);
+    let b = (b5 << 3) | (b5 >> 2);
+    ((r as u32) << 24) | ((g as u32) << 16) | ((b as u32) << 8) | 0xFF
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -201,6 +310,20 @@ mod tests {
         assert_eq!((rgba_val >> 8) & 0xFF, 0x00);  // B = 0
     }
 
+    #[test]
+    fn test_rgb565_opaque_red() {
+        // Standard RGB565 full red: R5 = 0x1F at bits 15:11
+        assert_eq!(rgb565_opaque_to_rgba(0xF800), 0xFF0000FF);
+    }
+
+    #[test]
+    fn test_rgb565_opaque_green_and_blue() {
+        assert_eq!(rgb565_opaque_to_rgba(0x07E0), 0x00FF00FF); // G6 = 0x3F
+        assert_eq!(rgb565_opaque_to_rgba(0x001F), 0x0000FFFF); // B5 = 0x1F
+        // Black is still opaque: there is no alpha bit in this layout
+        assert_eq!(rgb565_opaque_to_rgba(0x0000), 0x000000FF);
+    }
+
     #[test]
     fn test_pico_rgba_matches_rgb565_roundtrip() {
         // Build the 16-bit PICO value for (205, 0, 0) the way hardware does
@@ -220,6 +343,22 @@ mod tests {
         assert_eq!(PALETTE_2[1] & 0xFF, 0xFF); // opaque
     }
 
+    #[test]
+    fn test_rgba_unpacks_crate_byte_order() {
+        // Firmware red is 205, stored through PICO_SCANVIDEO's 5 bits as 206
+        assert_eq!(Rgba::from_u32(PALETTE_256[1]), Rgba { r: 206, g: 0, b: 0, a: 0xFF });
+        assert_eq!(palette_256()[1].r, 206);
+        assert_eq!(palette_2(), [Rgba { r: 0, g: 0, b: 0, a: 0 }, Rgba { r: 198, g: 198, b: 198, a: 0xFF }]);
+        assert_eq!(Rgba { r: 0x12, g: 0x34, b: 0x56, a: 0x78 }.to_u32(), 0x12345678);
+    }
+
+    #[test]
+    fn test_rgba_round_trip_is_lossless() {
+        for &c in PALETTE_256.iter().chain(&PALETTE_2).chain(&[0, 0x12345678, 0xFFFF_FFFF, 0x8000_0001]) {
+            assert_eq!(Rgba::from_u32(c).to_u32(), c);
+        }
+    }
+
     #[test]
     fn test_resolve_palette_builtin_1bpp() {
         let xram = Box::new([0u8; 65536]);
@@ -243,4 +382,23 @@ mod tests {
         let pal = resolve_palette(&xram, 16, 0);
         assert!(pal.is_empty());
     }
+
+    #[test]
+    fn test_resolved_palette_source() {
+        let xram = Box::new([0u8; 65536]);
+        assert_eq!(ResolvedPalette::resolve(&xram, 3, 8, 0xFFFF).source(), "builtin");
+        assert_eq!(ResolvedPalette::resolve(&xram, 3, 8, 0x1000).source(), "xram");
+        assert_eq!(ResolvedPalette::resolve(&xram, 3, 16, 0x1000).source(), "direct");
+    }
+
+    #[test]
+    fn test_palettes_json() {
+        let xram = Box::new([0u8; 65536]);
+        let palettes = [Some(ResolvedPalette::resolve(&xram, 3, 1, 0xFFFF)), None];
+        let json = palettes_json(&palettes);
+        assert_eq!(
+            json,
+            "{\n  \"planes\": [\n    {\"plane\": 0, \"mode\": 3, \"bpp\": 1, \"palette_ptr\": \"0xFFFF\", \"source\": \"builtin\", \"colors\": [\"#00000000\", \"#C6C6C6FF\"]},\n    null\n  ]\n}\n"
+        );
+    }
 }
@@ -0,0 +1,98 @@
+//! Golden-image regression tests: render every test mode headlessly through the
+//! `screenshot` command and compare against `tests/golden/<mode>.png`.
+//!
+//! Run with `UPDATE_GOLDEN=1 cargo test --test golden` to rewrite the goldens.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Every `TestMode`, by its `Display` name. `test_harness` checks that each
+/// mode has a golden image, so a new mode without one fails there.
+const MODES: &[&str] = &[
+    "mono640x480",
+    "mono640x360",
+    "mono320x240",
+    "mono320x180",
+    "color2bpp640x360",
+    "color2bpp320x240",
+    "color2bpp320x180",
+    "color4bpp320x240",
+    "color4bpp320x180",
+    "color8bpp320x180",
+    "color16bpp320",
+    "text1bpp320x240",
+    "text8bpp320x240",
+    "mandelbrot",
+    "multi_plane",
+    "scroll_demo",
+];
+
+fn golden_path(mode: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{mode}.png"))
+}
+
+/// Decode an RGBA8 PNG into (width, height, pixels).
+fn read_png(path: &Path) -> (u32, u32, Vec<u8>) {
+    let file = fs::File::open(path).unwrap_or_else(|e| panic!("open {}: {e}", path.display()));
+    let mut reader = png::Decoder::new(file).read_info().expect("PNG header");
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).expect("PNG data");
+    assert_eq!(info.color_type, png::ColorType::Rgba, "{} is not RGBA", path.display());
+    buf.truncate(info.buffer_size());
+    (info.width, info.height, buf)
+}
+
+/// Write an RGBA8 PNG at maximum compression to keep the goldens small.
+fn write_png(path: &Path, width: u32, height: u32, pixels: &[u8]) {
+    let file = fs::File::create(path).unwrap_or_else(|e| panic!("create {}: {e}", path.display()));
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(png::Compression::Best);
+    encoder.write_header().unwrap().write_image_data(pixels).unwrap();
+}
+
+/// Render one mode with the `screenshot` command and return the PNG path.
+fn render(mode: &str) -> PathBuf {
+    let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("golden-{mode}.png"));
+    let status = Command::new(env!("CARGO_BIN_EXE_rp6502-emu"))
+        .args(["screenshot", "--mode", mode, "-q", "-o"])
+        .arg(&output)
+        .status()
+        .expect("run rp6502-emu");
+    assert!(status.success(), "screenshot --mode {mode} failed: {status}");
+    output
+}
+
+#[test]
+fn golden_images_match() {
+    let update = std::env::var("UPDATE_GOLDEN").is_ok_and(|v| v == "1");
+    let mut failures = Vec::new();
+
+    for &mode in MODES {
+        let (w, h, got) = read_png(&render(mode));
+        let golden = golden_path(mode);
+        if update {
+            fs::create_dir_all(golden.parent().unwrap()).unwrap();
+            write_png(&golden, w, h, &got);
+            continue;
+        }
+        if !golden.exists() {
+            failures.push(format!("{mode}: missing {} (run with UPDATE_GOLDEN=1)", golden.display()));
+            continue;
+        }
+
+        let (gw, gh, want) = read_png(&golden);
+        if (w, h) != (gw, gh) {
+            failures.push(format!("{mode}: rendered {w}x{h}, golden is {gw}x{gh}"));
+            continue;
+        }
+        let differing = got.chunks_exact(4).zip(want.chunks_exact(4)).filter(|(a, b)| a != b).count();
+        if differing > 0 {
+            failures.push(format!("{mode}: {differing} pixels differ"));
+        }
+    }
+
+    assert!(failures.is_empty(), "golden mismatches:\n  {}", failures.join("\n  "));
+}